@@ -0,0 +1,511 @@
+//! 信号回测评估
+//!
+//! 将 `SignalEngine` 产生的 Telemetry 信号切分为 "信号段" (episode)，
+//! 并用录制数据中的后续成交价评估每个信号段的质量。
+//!
+//! # 信号段定义
+//! 同一交易对上连续触发相同 `source` ("V"/"U"/"D") 的成交构成一个信号段；
+//! 某笔成交没有产生信号或 `source` 改变时，信号段结束。
+//!
+//! # 指标
+//! - 命中率: "U"/"D" 为 5s 前向收益方向正确；"V" 为 5s 内价格绝对变动超过 ask 调整量
+//! - 前向收益: 入场后 1s/5s/30s 的收益 (bps)，"U"/"D" 按方向取符号，"V" 取绝对值
+//! - 持续时间分布: 信号段时长的 p50/p90/max
+//! - 不利偏移: 信号段内价格相对入场价的最大不利变动 ($)
+//! - 报价保护: 被动报价按 `ask_adjust`/`bid_adjust` 挪开后，能避开的不利成交价差 ($)
+
+use std::collections::{BTreeMap, HashMap};
+use std::fmt;
+
+use crate::engine::{SignalEngine, SignalOutput};
+use crate::models::BinanceEvent;
+use crate::source::MarketDataSource;
+
+/// 前向收益评估时间点 (毫秒)
+pub const FORWARD_HORIZONS_MS: [u64; 3] = [1_000, 5_000, 30_000];
+
+/// 命中率使用的时间点 (毫秒)
+const HIT_HORIZON_MS: u64 = 5_000;
+
+/// 一个信号段
+#[derive(Debug, Clone)]
+pub struct SignalEpisode {
+    pub symbol: String,
+    pub source: String,
+    pub start_ms: u64,
+    pub end_ms: u64,
+    pub entry_price: f64,
+    pub max_price: f64,     // 信号段内最高成交价
+    pub min_price: f64,     // 信号段内最低成交价
+    pub ask_adjust: f64,    // 信号段内最大 ask 调整 ($)
+    pub bid_adjust: f64,    // 信号段内最大 bid 调整绝对值 ($)
+}
+
+impl SignalEpisode {
+    /// 方向符号: "U" = +1, "D" = -1, 其他 (波动率) = 0
+    fn direction(&self) -> f64 {
+        match self.source.as_str() {
+            "U" => 1.0,
+            "D" => -1.0,
+            _ => 0.0,
+        }
+    }
+
+    pub fn holding_secs(&self) -> f64 {
+        self.end_ms.saturating_sub(self.start_ms) as f64 / 1000.0
+    }
+
+    /// 相对入场价的最大不利偏移 ($)
+    pub fn adverse_excursion(&self) -> f64 {
+        let up = (self.max_price - self.entry_price).max(0.0);
+        let down = (self.entry_price - self.min_price).max(0.0);
+        match self.source.as_str() {
+            "U" => down,
+            "D" => up,
+            _ => up.max(down),
+        }
+    }
+
+    /// 被动报价的 (被保护的价差, 总不利变动)
+    ///
+    /// ask 被上移 `ask_adjust` 时，价格上冲中不超过该距离的部分不会成交在旧 ask 上；
+    /// bid 同理。只统计有调整的一侧。
+    pub fn quote_protection(&self) -> (f64, f64) {
+        let up = (self.max_price - self.entry_price).max(0.0);
+        let down = (self.entry_price - self.min_price).max(0.0);
+        let mut protected = 0.0;
+        let mut adverse = 0.0;
+        if self.ask_adjust > 0.0 {
+            protected += self.ask_adjust.min(up);
+            adverse += up;
+        }
+        if self.bid_adjust > 0.0 {
+            protected += self.bid_adjust.min(down);
+            adverse += down;
+        }
+        (protected, adverse)
+    }
+}
+
+/// 回测驱动器：一边喂事件给引擎，一边记录成交价序列与信号段
+pub struct Backtester {
+    engine: SignalEngine,
+    prices: HashMap<String, Vec<(u64, f64)>>,     // symbol -> [(ts_ms, price)]
+    active: HashMap<String, SignalEpisode>,       // symbol -> 进行中的信号段
+    episodes: Vec<SignalEpisode>,
+    events: u64,
+}
+
+impl Backtester {
+    pub fn new(engine: SignalEngine) -> Self {
+        Self {
+            engine,
+            prices: HashMap::new(),
+            active: HashMap::new(),
+            episodes: Vec::new(),
+            events: 0,
+        }
+    }
+
+    /// 处理一个事件
+    pub fn on_event(&mut self, event: &BinanceEvent) -> Result<(), Box<dyn std::error::Error>> {
+        self.events += 1;
+        let outputs = self.engine.on_event(event)?;
+
+        let BinanceEvent::Trade(trade) = event else {
+            return Ok(());
+        };
+        let price: f64 = trade.price.parse()?;
+        self.record_trade(&trade.symbol.to_lowercase(), trade.trade_time, price, &outputs);
+        Ok(())
+    }
+
+    /// 记录一笔成交及引擎为其产生的输出，延续、开启或结束该交易对的信号段
+    fn record_trade(&mut self, symbol: &str, ts: u64, price: f64, outputs: &[SignalOutput]) {
+        self.prices.entry(symbol.to_string()).or_default().push((ts, price));
+
+        // 同一笔成交可能附带转换事件包 ("N")，以最后一个信号包为准
+        let packet = outputs.iter().rev().find_map(|o| match o {
+            SignalOutput::Telemetry(p)
+                if p.symbol.eq_ignore_ascii_case(symbol) && matches!(p.source.as_str(), "V" | "U" | "D") =>
+            {
+                Some(p)
+            }
+            _ => None,
+        });
+
+        match packet {
+            Some(p) => {
+                if let Some(ep) = self.active.get_mut(symbol)
+                    && ep.source == p.source
+                {
+                    ep.end_ms = ts;
+                    ep.max_price = ep.max_price.max(price);
+                    ep.min_price = ep.min_price.min(price);
+                    ep.ask_adjust = ep.ask_adjust.max(p.ask_adjust);
+                    ep.bid_adjust = ep.bid_adjust.max(p.bid_adjust.abs());
+                    return;
+                }
+                self.close(symbol);
+                self.active.insert(symbol.to_string(), SignalEpisode {
+                    symbol: symbol.to_string(),
+                    source: p.source.clone(),
+                    start_ms: ts,
+                    end_ms: ts,
+                    entry_price: price,
+                    max_price: price,
+                    min_price: price,
+                    ask_adjust: p.ask_adjust,
+                    bid_adjust: p.bid_adjust.abs(),
+                });
+            }
+            None => self.close(symbol),
+        }
+    }
+
+    fn close(&mut self, symbol: &str) {
+        if let Some(ep) = self.active.remove(symbol) {
+            self.episodes.push(ep);
+        }
+    }
+
+    /// 跑完整个数据源
+    pub async fn run<S: MarketDataSource>(&mut self, source: &mut S) -> Result<(), Box<dyn std::error::Error>> {
+        while let Some(event) = source.next_event().await? {
+            self.on_event(&event)?;
+        }
+        Ok(())
+    }
+
+    /// 结束回测，计算报告
+    pub fn finish(mut self) -> BacktestReport {
+        let symbols: Vec<String> = self.active.keys().cloned().collect();
+        for symbol in symbols {
+            self.close(&symbol);
+        }
+
+        let mut by_source: BTreeMap<String, SourceAccumulator> = BTreeMap::new();
+        for ep in &self.episodes {
+            let prices = self.prices.get(&ep.symbol).map(Vec::as_slice).unwrap_or(&[]);
+            by_source.entry(ep.source.clone()).or_default().add(ep, prices);
+        }
+
+        BacktestReport {
+            events: self.events,
+            trades: self.prices.values().map(Vec::len).sum(),
+            sources: by_source.into_iter().map(|(k, v)| (k, v.into_metrics())).collect(),
+        }
+    }
+
+    pub fn episodes(&self) -> &[SignalEpisode] {
+        &self.episodes
+    }
+}
+
+/// 查找 `ts_ms` 之后第一笔成交价
+fn price_at(prices: &[(u64, f64)], ts_ms: u64) -> Option<f64> {
+    let idx = prices.partition_point(|(t, _)| *t < ts_ms);
+    prices.get(idx).map(|(_, p)| *p)
+}
+
+#[derive(Default)]
+struct SourceAccumulator {
+    count: usize,
+    hits: usize,
+    hit_samples: usize,
+    fwd_sum_bps: [f64; FORWARD_HORIZONS_MS.len()],
+    fwd_samples: [usize; FORWARD_HORIZONS_MS.len()],
+    holding_secs: Vec<f64>,
+    adverse_sum: f64,
+    adverse_max: f64,
+    protected_sum: f64,
+    protectable_sum: f64,
+}
+
+impl SourceAccumulator {
+    fn add(&mut self, ep: &SignalEpisode, prices: &[(u64, f64)]) {
+        self.count += 1;
+        let dir = ep.direction();
+
+        for (i, h) in FORWARD_HORIZONS_MS.iter().enumerate() {
+            let Some(fwd) = price_at(prices, ep.start_ms + h) else { continue };
+            let ret_bps = (fwd / ep.entry_price - 1.0) * 10_000.0;
+            let signed = if dir == 0.0 { ret_bps.abs() } else { ret_bps * dir };
+            self.fwd_sum_bps[i] += signed;
+            self.fwd_samples[i] += 1;
+
+            if *h == HIT_HORIZON_MS {
+                self.hit_samples += 1;
+                let hit = if dir == 0.0 {
+                    (fwd - ep.entry_price).abs() >= ep.ask_adjust.max(ep.bid_adjust)
+                } else {
+                    signed > 0.0
+                };
+                if hit {
+                    self.hits += 1;
+                }
+            }
+        }
+
+        self.holding_secs.push(ep.holding_secs());
+
+        let adverse = ep.adverse_excursion();
+        self.adverse_sum += adverse;
+        self.adverse_max = self.adverse_max.max(adverse);
+
+        let (protected, protectable) = ep.quote_protection();
+        self.protected_sum += protected;
+        self.protectable_sum += protectable;
+    }
+
+    fn into_metrics(mut self) -> SourceMetrics {
+        self.holding_secs.sort_by(|a, b| a.partial_cmp(b).unwrap());
+        let n = self.count.max(1) as f64;
+        let mut avg_forward_bps = [None; FORWARD_HORIZONS_MS.len()];
+        for (i, avg) in avg_forward_bps.iter_mut().enumerate() {
+            if self.fwd_samples[i] > 0 {
+                *avg = Some(self.fwd_sum_bps[i] / self.fwd_samples[i] as f64);
+            }
+        }
+
+        SourceMetrics {
+            count: self.count,
+            hit_rate: (self.hit_samples > 0).then(|| self.hits as f64 / self.hit_samples as f64),
+            avg_forward_bps,
+            holding_p50_secs: percentile(&self.holding_secs, 0.5),
+            holding_p90_secs: percentile(&self.holding_secs, 0.9),
+            holding_max_secs: self.holding_secs.last().copied().unwrap_or(0.0),
+            avg_adverse_excursion: self.adverse_sum / n,
+            max_adverse_excursion: self.adverse_max,
+            avg_protected: self.protected_sum / n,
+            protection_ratio: (self.protectable_sum > 0.0).then(|| self.protected_sum / self.protectable_sum),
+        }
+    }
+}
+
+/// 已排序序列的分位数 (最近秩法)
+fn percentile(sorted: &[f64], q: f64) -> f64 {
+    if sorted.is_empty() {
+        return 0.0;
+    }
+    let idx = ((sorted.len() as f64 * q).ceil() as usize).clamp(1, sorted.len()) - 1;
+    sorted[idx]
+}
+
+/// 单个信号来源的评估指标
+#[derive(Debug, Clone)]
+pub struct SourceMetrics {
+    pub count: usize,
+    pub hit_rate: Option<f64>,                                   // 0.0 - 1.0
+    pub avg_forward_bps: [Option<f64>; FORWARD_HORIZONS_MS.len()],
+    pub holding_p50_secs: f64,
+    pub holding_p90_secs: f64,
+    pub holding_max_secs: f64,
+    pub avg_adverse_excursion: f64,                              // $
+    pub max_adverse_excursion: f64,                              // $
+    pub avg_protected: f64,                                      // $ / 信号段
+    pub protection_ratio: Option<f64>,                           // 被保护 / 总不利变动
+}
+
+/// 回测报告
+#[derive(Debug, Clone)]
+pub struct BacktestReport {
+    pub events: u64,
+    pub trades: usize,
+    pub sources: BTreeMap<String, SourceMetrics>,   // "D" / "U" / "V" -> 指标
+}
+
+fn fmt_opt(v: Option<f64>, scale: f64, suffix: &str) -> String {
+    v.map(|x| format!("{:.2}{}", x * scale, suffix)).unwrap_or_else(|| "-".to_string())
+}
+
+impl fmt::Display for BacktestReport {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(f, "Events: {} | Trades: {}", self.events, self.trades)?;
+        if self.sources.is_empty() {
+            return writeln!(f, "(no signals)");
+        }
+        for (source, m) in &self.sources {
+            writeln!(f, "── [{}] signals: {}", source, m.count)?;
+            writeln!(f, "   hit rate (5s)      : {}", fmt_opt(m.hit_rate, 100.0, "%"))?;
+            writeln!(
+                f,
+                "   fwd return 1s/5s/30s: {} / {} / {}",
+                fmt_opt(m.avg_forward_bps[0], 1.0, "bps"),
+                fmt_opt(m.avg_forward_bps[1], 1.0, "bps"),
+                fmt_opt(m.avg_forward_bps[2], 1.0, "bps"),
+            )?;
+            writeln!(
+                f,
+                "   holding p50/p90/max : {:.2}s / {:.2}s / {:.2}s",
+                m.holding_p50_secs, m.holding_p90_secs, m.holding_max_secs,
+            )?;
+            writeln!(
+                f,
+                "   adverse avg/max    : ${:.2} / ${:.2}",
+                m.avg_adverse_excursion, m.max_adverse_excursion,
+            )?;
+            writeln!(
+                f,
+                "   quote protection   : ${:.2} per signal ({} of adverse move)",
+                m.avg_protected, fmt_opt(m.protection_ratio, 100.0, "%"),
+            )?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Arc;
+
+    use super::*;
+    use crate::common::clock::EventClock;
+    use crate::config::MonitorConfig;
+    use crate::telemetry::{TelemetryPacket, Transition};
+
+    fn packet(source: &str, ask_adjust: f64, bid_adjust: f64) -> SignalOutput {
+        packet_for("BTCUSDT", source, ask_adjust, bid_adjust)
+    }
+
+    fn packet_for(symbol: &str, source: &str, ask_adjust: f64, bid_adjust: f64) -> SignalOutput {
+        SignalOutput::Telemetry(TelemetryPacket {
+            timestamp: 0,
+            symbol: symbol.to_string(),
+            source: source.to_string(),
+            ask_adjust,
+            bid_adjust,
+            degraded: false,
+            event: None,
+            term_vols: None,
+        })
+    }
+
+    fn backtester() -> Backtester {
+        let cfg: MonitorConfig = serde_yaml::from_str(include_str!("../config.example.yaml")).unwrap();
+        Backtester::new(SignalEngine::new_with_clock(cfg, Arc::new(EventClock::new(0))))
+    }
+
+    /// 手工构造的成交与信号序列：
+    /// - U 段 A: 0..1000ms，入场 100，最低 99 (不利 1)；同笔的其他交易对信号被忽略，1500ms 的 "N" 退出包结束该段
+    /// - U 段 B: 2000..4000ms，入场 102，无不利变动；5000ms 切换为 "D" 结束该段
+    /// - D 段 C: 仅 5000ms 一笔，入场 104
+    /// - V 段 D: 7000..9000ms，入场 100，最高 103 / 最低 99，ask/bid 调整 2
+    fn run_script() -> Backtester {
+        let mut exit = packet("N", 0.0, 0.0);
+        if let SignalOutput::Telemetry(p) = &mut exit {
+            p.event = Some(Transition::ExitPriceFallback);
+        }
+        let script: Vec<(u64, f64, Vec<SignalOutput>)> = vec![
+            (0, 100.0, vec![packet("N", 0.0, 0.0), packet("U", 0.5, -1.0)]),
+            (500, 99.0, vec![packet("U", 0.5, -1.0)]),
+            (1_000, 101.0, vec![packet("U", 0.5, -1.0), packet_for("ETHUSDT", "V", 9.0, -9.0)]),
+            (1_500, 102.0, vec![exit]),
+            (2_000, 102.0, vec![packet("U", 0.0, 0.0)]),
+            (4_000, 103.0, vec![packet("U", 0.0, 0.0)]),
+            (5_000, 104.0, vec![packet("D", 0.0, 0.0)]),
+            (6_000, 106.0, vec![]),
+            (7_000, 100.0, vec![packet("V", 2.0, -2.0)]),
+            (8_000, 103.0, vec![packet("V", 2.0, -2.0)]),
+            (9_000, 99.0, vec![packet("V", 1.0, -1.0)]),
+            (10_000, 100.0, vec![]),
+            (12_000, 101.0, vec![]),
+            (40_000, 110.0, vec![]),
+        ];
+        let mut bt = backtester();
+        for (ts, price, outputs) in script {
+            bt.record_trade("btcusdt", ts, price, &outputs);
+        }
+        bt
+    }
+
+    fn assert_close(got: f64, expected: f64) {
+        assert!((got - expected).abs() < 1e-9, "got {}, expected {}", got, expected);
+    }
+
+    #[test]
+    fn splits_signals_into_episodes() {
+        let bt = run_script();
+        let episodes: Vec<(&str, u64, u64, f64)> = bt.episodes()
+            .iter()
+            .map(|e| (e.source.as_str(), e.start_ms, e.end_ms, e.entry_price))
+            .collect();
+        assert_eq!(episodes, vec![
+            ("U", 0, 1_000, 100.0),
+            ("U", 2_000, 4_000, 102.0),
+            ("D", 5_000, 5_000, 104.0),
+            ("V", 7_000, 9_000, 100.0),
+        ]);
+        let v = &bt.episodes()[3];
+        assert_eq!((v.max_price, v.min_price, v.ask_adjust, v.bid_adjust), (103.0, 99.0, 2.0, 2.0));
+    }
+
+    #[test]
+    fn trend_metrics_match_hand_computed_values() {
+        let report = run_script().finish();
+        assert_eq!(report.trades, 14);
+        assert_eq!(report.sources.keys().collect::<Vec<_>>(), vec!["D", "U", "V"]);
+
+        let u = &report.sources["U"];
+        assert_eq!(u.count, 2);
+        // A: 5s 后 104 (+400bps) 命中；B: 5s 后 100 (-196bps) 未命中
+        assert_eq!(u.hit_rate, Some(0.5));
+        assert_close(u.avg_forward_bps[0].unwrap(), (100.0 + (103.0 / 102.0 - 1.0) * 1e4) / 2.0);
+        assert_close(u.avg_forward_bps[1].unwrap(), (400.0 + (100.0 / 102.0 - 1.0) * 1e4) / 2.0);
+        assert_close(u.avg_forward_bps[2].unwrap(), (1_000.0 + (110.0 / 102.0 - 1.0) * 1e4) / 2.0);
+        assert_eq!((u.holding_p50_secs, u.holding_p90_secs, u.holding_max_secs), (1.0, 2.0, 2.0));
+        assert_close(u.avg_adverse_excursion, 0.5);
+        assert_close(u.max_adverse_excursion, 1.0);
+        // A: ask 上移 $0.5 (上冲 $1 保护 $0.5)，bid 下移 $1 (下探 $1 全部保护)；B 无调整
+        assert_close(u.avg_protected, 1.5 / 2.0);
+        assert_close(u.protection_ratio.unwrap(), 0.75);
+
+        // D 段收益按做空方向取符号
+        let d = &report.sources["D"];
+        assert_eq!(d.count, 1);
+        assert_eq!(d.hit_rate, Some(1.0));
+        assert_close(d.avg_forward_bps[0].unwrap(), -(106.0 / 104.0 - 1.0) * 1e4);
+        assert_close(d.avg_forward_bps[1].unwrap(), -(100.0 / 104.0 - 1.0) * 1e4);
+        assert_close(d.avg_forward_bps[2].unwrap(), -(110.0 / 104.0 - 1.0) * 1e4);
+        assert_eq!(d.holding_max_secs, 0.0);
+        assert_eq!(d.max_adverse_excursion, 0.0);
+        assert_eq!(d.protection_ratio, None);
+    }
+
+    #[test]
+    fn volatility_metrics_match_hand_computed_values() {
+        let v = &run_script().finish().sources["V"];
+        assert_eq!(v.count, 1);
+        // 5s 后 101：|+$1| 小于调整量 $2，不算命中；收益取绝对值
+        assert_eq!(v.hit_rate, Some(0.0));
+        assert_close(v.avg_forward_bps[0].unwrap(), 300.0);
+        assert_close(v.avg_forward_bps[1].unwrap(), 100.0);
+        assert_close(v.avg_forward_bps[2].unwrap(), 1_000.0);
+        assert_eq!(v.holding_p50_secs, 2.0);
+        assert_close(v.max_adverse_excursion, 3.0);
+        // 上冲 $3 保护 $2，下探 $1 全部保护
+        assert_close(v.avg_protected, 3.0);
+        assert_close(v.protection_ratio.unwrap(), 0.75);
+    }
+
+    #[test]
+    fn percentile_uses_nearest_rank() {
+        let sorted = [1.0, 2.0, 3.0, 4.0, 5.0, 6.0, 7.0, 8.0, 9.0, 10.0];
+        assert_eq!(percentile(&sorted, 0.5), 5.0);
+        assert_eq!(percentile(&sorted, 0.9), 9.0);
+        assert_eq!(percentile(&sorted, 1.0), 10.0);
+        assert_eq!(percentile(&[], 0.5), 0.0);
+        assert_eq!(percentile(&[4.0], 0.0), 4.0);
+    }
+
+    #[test]
+    fn missing_forward_prices_are_not_sampled() {
+        let mut bt = backtester();
+        bt.record_trade("btcusdt", 0, 100.0, &[packet("U", 0.0, 0.0)]);
+        bt.record_trade("btcusdt", 2_000, 101.0, &[]);
+        let u = &bt.finish().sources["U"];
+        assert_close(u.avg_forward_bps[0].unwrap(), 100.0);
+        assert_eq!(u.avg_forward_bps[1], None);
+        assert_eq!(u.hit_rate, None);
+    }
+}
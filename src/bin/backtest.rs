@@ -0,0 +1,57 @@
+use std::sync::Arc;
+
+use tracing::error;
+
+use volatility_monitor::backtest::Backtester;
+use volatility_monitor::common::clock::EventClock;
+use volatility_monitor::config::MonitorConfig;
+use volatility_monitor::engine::SignalEngine;
+use volatility_monitor::source::ReplaySource;
+
+const USAGE: &str = "Usage: backtest <recording.jsonl.gz> [config.yaml]";
+
+#[tokio::main]
+async fn main() {
+    // Logs go to stderr (default "warn") so the report on stdout stays clean and pipeable.
+    tracing_subscriber::fmt()
+        .with_writer(std::io::stderr)
+        .with_env_filter(
+            tracing_subscriber::EnvFilter::try_from_default_env()
+                .unwrap_or_else(|_| tracing_subscriber::EnvFilter::new("warn")),
+        )
+        .init();
+
+    let args: Vec<String> = std::env::args().skip(1).collect();
+    let Some(recording) = args.first() else {
+        eprintln!("{}", USAGE);
+        std::process::exit(2);
+    };
+    let config_path = args.get(1).map(String::as_str).unwrap_or("config.yaml");
+
+    let cfg = match MonitorConfig::load_offline(config_path) {
+        Ok(c) => c,
+        Err(e) => {
+            error!("❌ Critical Error: Failed to load configuration: {}", e);
+            std::process::exit(1);
+        }
+    };
+
+    let mut source = match ReplaySource::open(recording) {
+        Ok(s) => s,
+        Err(e) => {
+            error!("❌ Failed to open recording {}: {}", recording, e);
+            std::process::exit(1);
+        }
+    };
+
+    // Event-time clock: staleness and cooldowns follow the recorded timestamps.
+    let engine = SignalEngine::new_with_clock(cfg, Arc::new(EventClock::default()));
+    let mut backtester = Backtester::new(engine);
+
+    if let Err(e) = backtester.run(&mut source).await {
+        error!("❌ Replay failed: {}", e);
+        std::process::exit(1);
+    }
+
+    print!("{}", backtester.finish());
+}
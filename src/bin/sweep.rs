@@ -0,0 +1,121 @@
+use tracing::{error, info};
+
+use volatility_monitor::config::MonitorConfig;
+use volatility_monitor::source::{MarketDataSource, ReplaySource};
+use volatility_monitor::sweep::{run_sweep, to_csv, SweepSpec};
+
+const USAGE: &str = "Usage: sweep <recording.jsonl.gz> <sweep.yaml> [--config config.yaml] [--out leaderboard.csv|.json] [--jobs N]";
+
+struct Args {
+    recording: String,
+    spec: String,
+    config: String,
+    out: Option<String>,
+    jobs: usize,
+}
+
+fn parse_args() -> Result<Args, String> {
+    let mut positional = Vec::new();
+    let mut config = "config.yaml".to_string();
+    let mut out = None;
+    let mut jobs = std::thread::available_parallelism().map(|n| n.get()).unwrap_or(1);
+
+    let mut it = std::env::args().skip(1);
+    while let Some(arg) = it.next() {
+        match arg.as_str() {
+            "--config" => config = it.next().ok_or("--config requires a value")?,
+            "--out" => out = Some(it.next().ok_or("--out requires a value")?),
+            "--jobs" => {
+                jobs = it.next()
+                    .and_then(|v| v.parse().ok())
+                    .ok_or("--jobs requires a positive integer")?;
+            }
+            _ if arg.starts_with("--") => return Err(format!("unknown option {}", arg)),
+            _ => positional.push(arg),
+        }
+    }
+
+    let [recording, spec]: [String; 2] = positional.try_into()
+        .map_err(|_| "expected <recording> and <sweep.yaml>".to_string())?;
+    Ok(Args { recording, spec, config, out, jobs })
+}
+
+#[tokio::main]
+async fn main() {
+    // Logs go to stderr (default "warn") so the ranking table on stdout stays clean and pipeable.
+    tracing_subscriber::fmt()
+        .with_writer(std::io::stderr)
+        .with_env_filter(
+            tracing_subscriber::EnvFilter::try_from_default_env()
+                .unwrap_or_else(|_| tracing_subscriber::EnvFilter::new("warn")),
+        )
+        .init();
+
+    let args = match parse_args() {
+        Ok(a) => a,
+        Err(e) => {
+            eprintln!("{}\n{}", e, USAGE);
+            std::process::exit(2);
+        }
+    };
+
+    let (cfg, spec) = match (MonitorConfig::load_offline(&args.config), SweepSpec::load_from(&args.spec)) {
+        (Ok(c), Ok(s)) => (c, s),
+        (Err(e), _) | (_, Err(e)) => {
+            error!("❌ Critical Error: {}", e);
+            std::process::exit(1);
+        }
+    };
+
+    // Load the recording once; every combination replays the same in-memory events.
+    let mut source = match ReplaySource::open(&args.recording) {
+        Ok(s) => s,
+        Err(e) => {
+            error!("❌ Failed to open recording {}: {}", args.recording, e);
+            std::process::exit(1);
+        }
+    };
+    let mut events = Vec::new();
+    loop {
+        match source.next_event().await {
+            Ok(Some(event)) => events.push(event),
+            Ok(None) => break,
+            Err(e) => {
+                error!("❌ Failed to read recording: {}", e);
+                std::process::exit(1);
+            }
+        }
+    }
+    info!("📼 Loaded {} events, running sweep on {} threads...", events.len(), args.jobs);
+
+    let results = match run_sweep(&cfg, &spec, &events, args.jobs) {
+        Ok(r) => r,
+        Err(e) => {
+            error!("❌ Sweep failed: {}", e);
+            std::process::exit(1);
+        }
+    };
+    let failed = results.iter().filter(|r| r.error.is_some()).count();
+    info!("🏁 {} combinations finished.", results.len());
+
+    let output = match args.out.as_deref() {
+        Some(path) if path.ends_with(".json") => serde_json::to_string_pretty(&results).unwrap(),
+        _ => to_csv(&results),
+    };
+    match args.out {
+        Some(path) => {
+            if let Err(e) = std::fs::write(&path, output) {
+                error!("❌ Failed to write {}: {}", path, e);
+                std::process::exit(1);
+            }
+            info!("📄 Leaderboard written to {}", path);
+        }
+        None => print!("{}", output),
+    }
+
+    // Failed combinations are listed at the bottom of the leaderboard; also fail the exit status.
+    if failed > 0 {
+        error!("❌ {} of {} combinations failed (see the error column)", failed, results.len());
+        std::process::exit(1);
+    }
+}
@@ -3,9 +3,100 @@ use tracing::{info, error};
 use tracing_subscriber::fmt::format::Writer;
 use tracing_subscriber::fmt::time::FormatTime;
 
-use volatility_monitor::config::MonitorConfig;
-use volatility_monitor::indicators::vol::InstantVolatilityIndicator;
+use volatility_monitor::config::{MonitorConfig, ENV_CONFIG_PATH};
+use volatility_monitor::engine::SignalEngine;
+use volatility_monitor::recorder::FrameRecorder;
+use volatility_monitor::reload::spawn_config_watcher;
 use volatility_monitor::run_connection;
+use volatility_monitor::telemetry::TelemetryServer;
+
+const USAGE: &str = "\
+Usage: volatility_monitor [--config <path>] [--check-config] [--dry-run]
+
+Options:
+  --config <path>   Config file (default: $BNVOL_CONFIG, then ./config.yaml)
+  --check-config    Load and validate the config, print the effective values and exit
+  --dry-run         Connect and compute signals without sending Slack messages,
+                    starting the telemetry server or recording frames
+
+The config file is watched while running; edits (or SIGHUP) are validated and applied
+without reconnecting. symbols, order_book, telemetry and record_path need a restart.
+
+Any config field can be overridden with a BNVOL_-prefixed environment variable,
+e.g. BNVOL_SLACK_WEBHOOK_URL=... or BNVOL_TREND__SLOPE_THRESHOLD=3.5 (`__` = nesting).
+Precedence: defaults < config file < BNVOL_* environment variables.";
+
+struct Args {
+    config: String,
+    check_config: bool,
+    dry_run: bool,
+}
+
+fn parse_args() -> Result<Args, String> {
+    let mut config = None;
+    let mut check_config = false;
+    let mut dry_run = false;
+
+    let mut it = std::env::args().skip(1);
+    while let Some(arg) = it.next() {
+        match arg.as_str() {
+            "--config" => config = Some(it.next().ok_or("--config requires a value")?),
+            "--check-config" => check_config = true,
+            "--dry-run" => dry_run = true,
+            "-h" | "--help" => {
+                println!("{}", USAGE);
+                std::process::exit(0);
+            }
+            _ => return Err(format!("unknown argument {}", arg)),
+        }
+    }
+
+    let config = config
+        .or_else(|| std::env::var(ENV_CONFIG_PATH).ok())
+        .unwrap_or_else(|| "config.yaml".to_string());
+    Ok(Args { config, check_config, dry_run })
+}
+
+/// Prints the effective configuration with secrets masked.
+fn print_effective_config(cfg: &MonitorConfig) {
+    let mut shown = cfg.clone();
+    if !shown.slack_webhook_url.is_empty() {
+        shown.slack_webhook_url = "<redacted>".to_string();
+    }
+    match serde_yaml::to_string(&shown) {
+        Ok(yaml) => println!("{}", yaml),
+        Err(e) => eprintln!("Failed to render config: {}", e),
+    }
+}
+
+/// Disables every side effect that leaves the process.
+fn apply_dry_run(cfg: &mut MonitorConfig) {
+    cfg.slack_enabled = false;
+    cfg.telemetry.enabled = false;
+    cfg.record_path = None;
+}
+
+/// Resolves on Ctrl+C (SIGINT) or SIGTERM.
+async fn shutdown_signal() {
+    #[cfg(unix)]
+    {
+        use tokio::signal::unix::{signal, SignalKind};
+        match signal(SignalKind::terminate()) {
+            Ok(mut term) => tokio::select! {
+                _ = tokio::signal::ctrl_c() => {}
+                _ = term.recv() => {}
+            },
+            Err(e) => {
+                error!("⚠️ Failed to install SIGTERM handler: {}", e);
+                let _ = tokio::signal::ctrl_c().await;
+            }
+        }
+    }
+    #[cfg(not(unix))]
+    {
+        let _ = tokio::signal::ctrl_c().await;
+    }
+}
 
 /// Custom timer implementation to format log timestamps using the system's local timezone.
 /// By default, tracing uses UTC (Zulu time), which can be confusing for local debugging.
@@ -31,33 +122,108 @@ async fn main() {
         .with_timer(LocalTimer)
         .init();
 
+    let args = match parse_args() {
+        Ok(a) => a,
+        Err(e) => {
+            eprintln!("{}\n\n{}", e, USAGE);
+            std::process::exit(2);
+        }
+    };
+
     // Load configuration immediately at startup.
     // Adopts a "Fail Fast" strategy: if the config is missing or invalid, exit immediately.
-    let cfg = match MonitorConfig::load() {
+    let mut cfg = match MonitorConfig::load_from(&args.config) {
         Ok(c) => c,
         Err(e) => {
             error!("❌ Critical Error: Failed to load configuration: {}", e);
-            return;
+            std::process::exit(1);
+        }
+    };
+
+    if args.check_config {
+        print_effective_config(&cfg);
+        info!("✅ Config {} is valid.", args.config);
+        return;
+    }
+
+    // Dry run: same data path, but nothing leaves the process.
+    if args.dry_run {
+        info!("🧪 Dry run: Slack, telemetry and recording are disabled.");
+        apply_dry_run(&mut cfg);
+    }
+
+    // Watch the config file (and SIGHUP) for hot reloads. Dry-run restrictions are re-applied
+    // to every reloaded config so a reload can never re-enable Slack.
+    let dry_run = args.dry_run;
+    let mut updates = spawn_config_watcher(args.config.clone(), cfg.clone(), move |c| {
+        if dry_run {
+            apply_dry_run(c);
         }
+    });
+
+    // Build the signal engine (one pipeline per configured symbol).
+    // Instantiated outside the loop so volatility windows and stats survive reconnections.
+    let mut engine = SignalEngine::new(cfg.clone());
+
+    // Optional raw-frame recorder for offline replay. Kept open across reconnections;
+    // each process start writes a new file so an unclean exit never corrupts an older recording.
+    let mut recorder = match cfg.record_path.as_deref() {
+        Some(path) => match FrameRecorder::create_session(path) {
+            Ok(r) => {
+                info!("⏺️ Recording raw frames to {}", r.path().display());
+                Some(r)
+            }
+            Err(e) => {
+                error!("❌ Critical Error: Failed to open record file {}: {}", path, e);
+                std::process::exit(1);
+            }
+        },
+        None => None,
     };
 
-    // Initialize the volatility calculator with config parameters.
-    // Instantiated outside the loop to potentially preserve state across reconnections.
-    let mut vol_calc = InstantVolatilityIndicator::new(
-        cfg.volatility.window_size,
-        cfg.volatility.stale_threshold_ms,
-        cfg.volatility.fallback_volatility,
-        cfg.volatility.expire_threshold_ms,
-    );
+    // Start the telemetry server once. A port that cannot be bound is a startup failure,
+    // not something to retry on every reconnection. Clients stay connected across
+    // Binance reconnects and receive "X"/"L" feed status packets instead.
+    let telemetry = match TelemetryServer::start(&cfg.telemetry).await {
+        Ok(t) => t,
+        Err(e) => {
+            error!(
+                "❌ Critical Error: Failed to bind telemetry on {}:{}: {}",
+                cfg.telemetry.bind_address, cfg.telemetry.port, e,
+            );
+            std::process::exit(1);
+        }
+    };
+
+    // SIGINT/SIGTERM end the loop so the recording gets its gzip trailer.
+    let shutdown = shutdown_signal();
+    tokio::pin!(shutdown);
 
     loop {
         info!("🚀 Starting Binance Volatility Monitor...");
 
         // Run the core connection logic imported from the library.
-        if let Err(e) = run_connection(&mut vol_calc, &cfg).await {
-            error!("⚠️ Connection lost: {:?}. Retrying in 5s...", e);
+        tokio::select! {
+            result = run_connection(&mut engine, &telemetry, recorder.as_mut(), Some(&mut updates)) => {
+                if let Err(e) = result {
+                    error!("⚠️ Connection lost: {:?}. Retrying in 5s...", e);
+                }
+            }
+            _ = &mut shutdown => break,
         }
 
-        sleep(Duration::from_secs(5)).await;
+        tokio::select! {
+            _ = sleep(Duration::from_secs(5)) => {}
+            _ = &mut shutdown => break,
+        }
+    }
+
+    info!("🛑 Shutting down...");
+    if let Some(recorder) = recorder {
+        let path = recorder.path().display().to_string();
+        match recorder.finish() {
+            Ok(()) => info!("⏹️ Recording finished: {}", path),
+            Err(e) => error!("❌ Failed to finish recording {}: {}", path, e),
+        }
     }
 }
\ No newline at end of file
@@ -0,0 +1,52 @@
+//! 时钟抽象
+//!
+//! 指标中的过期判断、报警冷却、直方图周期等都需要 "当前时间"。
+//! 实盘使用系统时钟；回放/回测使用事件时间，保证离线结果与实盘一致。
+
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+pub trait Clock: Send + Sync {
+    /// 当前时间 (Unix 毫秒)
+    fn now_ms(&self) -> u64;
+
+    /// 通知时钟观察到一个事件时间戳；系统时钟忽略，事件时钟据此推进
+    fn observe(&self, _event_ms: u64) {}
+}
+
+/// 系统时钟 (实盘)
+#[derive(Debug, Default, Clone, Copy)]
+pub struct WallClock;
+
+impl Clock for WallClock {
+    fn now_ms(&self) -> u64 {
+        SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .unwrap()
+            .as_millis() as u64
+    }
+}
+
+/// 事件时钟 (回放/回测/测试)
+///
+/// 当前时间等于迄今观察到的最大事件时间戳，只前进不后退。
+#[derive(Debug, Default)]
+pub struct EventClock {
+    now_ms: AtomicU64,
+}
+
+impl EventClock {
+    pub fn new(start_ms: u64) -> Self {
+        Self { now_ms: AtomicU64::new(start_ms) }
+    }
+}
+
+impl Clock for EventClock {
+    fn now_ms(&self) -> u64 {
+        self.now_ms.load(Ordering::Relaxed)
+    }
+
+    fn observe(&self, event_ms: u64) {
+        self.now_ms.fetch_max(event_ms, Ordering::Relaxed);
+    }
+}
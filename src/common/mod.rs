@@ -1 +1,3 @@
-pub mod ring_buffer;
\ No newline at end of file
+pub mod clock;
+pub mod ring_buffer;
+pub mod units;
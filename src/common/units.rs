@@ -0,0 +1,96 @@
+//! 带单位的数值类型
+//!
+//! 波动率在计算中是比例 (1.0 = 100%)，而配置与消息中习惯写百分比 (60.0 = 60%)。
+//! 两者混用曾导致阈值按比例比较、却按百分比展示。用不同的类型区分后，
+//! 比较前必须显式换算，编译器会拦住单位不一致的代码。
+//!
+//! 两个类型都以 `#[serde(transparent)]` 序列化为普通数字，线上格式不变。
+
+use std::fmt;
+
+use serde::{Deserialize, Serialize};
+
+/// 比例值 (1.0 = 100%)，例如年化波动率
+#[derive(Debug, Clone, Copy, Default, PartialEq, PartialOrd, Serialize, Deserialize)]
+#[serde(transparent)]
+pub struct Fraction(pub f64);
+
+/// 百分比值 (100.0 = 100%)，例如配置中的报警阈值
+#[derive(Debug, Clone, Copy, Default, PartialEq, PartialOrd, Serialize, Deserialize)]
+#[serde(transparent)]
+pub struct Percent(pub f64);
+
+impl Fraction {
+    pub fn to_percent(self) -> Percent {
+        Percent(self.0 * 100.0)
+    }
+}
+
+impl Percent {
+    pub fn to_fraction(self) -> Fraction {
+        Fraction(self.0 / 100.0)
+    }
+}
+
+impl From<Percent> for Fraction {
+    fn from(p: Percent) -> Self {
+        p.to_fraction()
+    }
+}
+
+impl From<Fraction> for Percent {
+    fn from(f: Fraction) -> Self {
+        f.to_percent()
+    }
+}
+
+/// 输出为 `60%` / `{:.2}` -> `60.00%`
+impl fmt::Display for Percent {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match f.precision() {
+            Some(p) => write!(f, "{:.*}%", p, self.0),
+            None => write!(f, "{}%", self.0),
+        }
+    }
+}
+
+/// 以百分比形式输出，与 `Percent` 一致
+impl fmt::Display for Fraction {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        fmt::Display::fmt(&self.to_percent(), f)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn conversions_round_trip() {
+        for pct in [0.0, 0.5, 10.0, 60.0, 123.456, 500.0] {
+            let p = Percent(pct);
+            assert!((p.to_fraction().to_percent().0 - pct).abs() < 1e-12, "{}", pct);
+            assert_eq!(Percent::from(Fraction::from(p)), p.to_fraction().to_percent());
+        }
+        for frac in [0.0, 0.005, 0.6, 1.0, 2.5] {
+            let f = Fraction(frac);
+            assert!((f.to_percent().to_fraction().0 - frac).abs() < 1e-12, "{}", frac);
+        }
+        assert_eq!(Percent(60.0).to_fraction(), Fraction(0.6));
+        assert_eq!(Fraction(0.25).to_percent(), Percent(25.0));
+    }
+
+    #[test]
+    fn display_as_percent() {
+        assert_eq!(Percent(60.0).to_string(), "60%");
+        assert_eq!(format!("{:.2}", Percent(60.0)), "60.00%");
+        assert_eq!(Fraction(0.25).to_string(), "25%");
+        assert_eq!(format!("{:.1}", Fraction(0.6)), "60.0%");
+    }
+
+    #[test]
+    fn serializes_as_plain_numbers() {
+        assert_eq!(serde_json::to_string(&Percent(60.0)).unwrap(), "60.0");
+        assert_eq!(serde_json::from_str::<Fraction>("0.6").unwrap(), Fraction(0.6));
+    }
+}
@@ -1,25 +1,99 @@
-use serde::Deserialize;
+//! 监控配置
+//!
+//! # 来源与优先级 (从低到高)
+//! 1. 字段默认值 (`default_*`)
+//! 2. YAML 配置文件：`--config <path>` > 环境变量 `BNVOL_CONFIG` > `./config.yaml`
+//! 3. `BNVOL_` 前缀的环境变量，覆盖任意字段
+//!
+//! 环境变量名去掉前缀后转小写，`__` (双下划线) 表示嵌套；数字、布尔、列表与 `{...}` 映射按 YAML 解析，
+//! 其余值原样作为字符串：
+//! ```text
+//! BNVOL_SLACK_WEBHOOK_URL=https://hooks.slack.com/...   -> slack_webhook_url
+//! BNVOL_THRESHOLD_PCT=80                                -> threshold_pct
+//! BNVOL_TREND__SLOPE_THRESHOLD=3.5                      -> trend.slope_threshold
+//! BNVOL_SYMBOLS='["btcusdt","ethusdt"]'                 -> symbols
+//! BNVOL_TREND__MAX_HOLDING_SECS=                        -> trend.max_holding_secs 取消设置
+//! ```
+//! 空值表示取消文件中的设置，字段回到默认值 (可选字段为 None，列表为空)；没有默认值的必填字段会报缺失。
+//! 覆盖后的配置同样经过 `validate()`；指向不存在字段的环境变量 (拼写错误) 直接报错，
+//! 而不是像文件中的未知键那样只给出警告。
+//!
+//! # 按交易对覆盖
+//! 以美元计的阈值 (斜率、价格回落、价差等) 因币种价格而异，`symbol_overrides` 可按交易对
+//! 覆盖 `SYMBOL_OVERRIDE_FIELDS` 中的字段，逐层合并到全局配置之上 (见 `for_symbol`)：
+//! ```yaml
+//! symbol_overrides:
+//!   solusdt:
+//!     trend: { slope_threshold: 0.02, min_price_fallback: 0.05, max_price_fallback: 0.2 }
+//!     volatility: { spread_adjust: 0.05 }
+//! ```
+//!
+//! # 单位
+//! 波动率相关字段以百分比填写，键名带 `_pct` 后缀 (60.0 = 60%)，解析为 `Percent`；
+//! 内部计算统一换算为 `Fraction` (1.0 = 100%)。旧版无后缀的键 (`threshold`、
+//! `histogram.step`、`volatility.fallback_volatility`) 仍可读取，见 `migrate_legacy_units`。
+
+use serde::{Deserialize, Serialize};
+use serde_yaml::Value;
+use std::collections::BTreeMap;
 use std::fs;
+use std::net::IpAddr;
+use tracing::{info, warn};
+
+use crate::common::units::Percent;
+use crate::indicators::vol::{EstimatorKind, VolWindow, WindowMode, MIN_BARS_PER_WINDOW};
+use crate::indicators::vol_regime::RegimeThresholds;
 
-#[derive(Debug, Deserialize, Clone)]
+#[derive(Debug, Deserialize, Serialize, Clone)]
 pub struct HistogramConfig {
     pub interval: u64,
-    pub step: f64,
+    pub step_pct: Percent,          // 直方图桶宽（%），例如 10.0 = 每桶 10%
     pub buckets: usize,
 }
 
 /// 波动率计算配置
-#[derive(Debug, Deserialize, Clone)]
+#[derive(Debug, Deserialize, Serialize, Clone)]
 pub struct VolatilityConfig {
+    #[serde(default)]
+    pub window_mode: WindowMode,    // count = 最近 window_size 笔; time = 最近 window_secs 秒
     pub window_size: usize,         // 采样窗口大小（数据点数量），例如 30
+    #[serde(default = "default_window_secs")]
+    pub window_secs: f64,           // 时间窗口长度（秒），例如 10.0，仅 time 模式
+    #[serde(default = "default_min_samples")]
+    pub min_samples: usize,         // 时间窗口内最少成交笔数，不足时视为不可用，仅 time 模式
     pub stale_threshold_ms: u64,    // 僵尸数据阈值（毫秒），例如 5000 = 5秒
-    pub fallback_volatility: f64,   // 数据过期时返回的防御性波动率，例如 0.5 = 50%
+    pub fallback_volatility_pct: Percent, // 数据过期时返回的防御性波动率（%），例如 50.0
     pub expire_threshold_ms: u64,   // 价格序列过期清除阈值（毫秒），例如 5000 = 5秒
-    pub spread_adjust: f64,         // 波动率报警时调大双边价差（$），例如 10.0
+    pub spread_adjust: f64,         // Elevated 状态时调大双边价差（$），例如 10.0
+    #[serde(default)]
+    pub estimator: EstimatorKind,   // 驱动报警与直方图的估计器，默认 rms
+    #[serde(default)]
+    pub report_estimators: Vec<EstimatorKind>, // 同时计算、并列输出到 VolatilityResult 的估计器
+    #[serde(default = "default_ewma_half_life_secs")]
+    pub ewma_half_life_secs: f64,   // ewma 半衰期（秒），例如 2.0
+    #[serde(default = "default_bar_ms")]
+    pub bar_ms: u64,                // bar_rv / parkinson / garman_klass / bipower 的 K 线周期（毫秒），例如 1000
+    #[serde(default = "default_tsrv_slow_ticks")]
+    pub tsrv_slow_ticks: usize,     // tsrv 慢尺度的采样间隔（笔），例如 5
+}
+
+impl VolatilityConfig {
+    /// 波动率计算器使用的窗口
+    pub fn window(&self) -> VolWindow {
+        match self.window_mode {
+            WindowMode::Count => VolWindow::Count(self.window_size),
+            WindowMode::Time => VolWindow::Time {
+                window_ms: (self.window_secs * 1000.0).round() as u64,
+                min_samples: self.min_samples,
+            },
+        }
+    }
 }
 
 /// 趋势监控配置（基于价格拟合 + OFI）
-#[derive(Debug, Deserialize, Clone)]
+///
+/// 同时也是 `TrendStateMachine` 的参数，所有字段都直接生效。
+#[derive(Debug, Deserialize, Serialize, Clone)]
 pub struct TrendConfig {
     // VWAP 参数
     pub vwap_window_ms: u64,        // VWAP 聚合窗口（毫秒），例如 100
@@ -45,15 +119,172 @@ pub struct TrendConfig {
     pub max_price_fallback: f64,    // 最大价格回落（$），例如 35.0
     pub entry_protection_secs: f64, // 入场保护期（秒），例如 1.0
     pub slope_weak_threshold: f64,  // 斜率不够明显的阈值，例如 0.5
+    #[serde(default = "default_slope_history_len")]
+    pub slope_history_len: usize,   // 斜率反转判断的历史样本数，例如 10
+    #[serde(default = "default_slope_reversal_min_secs")]
+    pub slope_reversal_min_secs: f64, // 入场后多久才允许斜率反转退出（秒），例如 5.0
+    #[serde(default = "default_slope_reversal_weak_count")]
+    pub slope_reversal_weak_count: usize, // 历史中走弱样本数超过该值时退出，例如 5
+    #[serde(default)]
+    pub max_holding_secs: Option<f64>, // 最长持仓时间（秒），不设置则不限制
+    #[serde(default)]
+    pub trailing_stop: Option<f64>, // 自入场以来最有利价格回撤超过该值（$）时退出，不设置则关闭
     
     // 预测参数
     pub predict_horizon_secs: f64,  // 预测时间范围（秒），例如 1.0
+    #[serde(default = "default_impact_qty")]
+    pub impact_qty: f64,            // 计算冲击价格的成交量（基础币），例如 1.0
     
     // 冷却
     pub cooldown_secs: f64,         // 信号冷却期（秒），例如 1.0
 }
 
-#[derive(Debug, Deserialize, Clone)]
+/// 深度数据模式
+#[derive(Debug, Deserialize, Serialize, Clone, Copy, PartialEq)]
+#[serde(rename_all = "lowercase")]
+pub enum DepthMode {
+    /// `depth20@100ms` 前 20 档快照 (默认)
+    Partial,
+    /// `depth@100ms` 增量 + REST 快照维护完整本地订单簿
+    Diff,
+}
+
+/// 订单簿配置
+#[derive(Debug, Deserialize, Serialize, Clone)]
+pub struct OrderBookConfig {
+    #[serde(default = "default_depth_mode")]
+    pub mode: DepthMode,
+    #[serde(default = "default_snapshot_url")]
+    pub snapshot_url: String,       // REST 快照地址，测试时可指向本地 mock 服务
+    #[serde(default = "default_snapshot_limit")]
+    pub snapshot_limit: u32,        // 快照档位数 (币安支持 5/10/20/50/100/500/1000)
+}
+
+impl Default for OrderBookConfig {
+    fn default() -> Self {
+        Self {
+            mode: default_depth_mode(),
+            snapshot_url: default_snapshot_url(),
+            snapshot_limit: default_snapshot_limit(),
+        }
+    }
+}
+
+/// Telemetry WebSocket 服务配置
+#[derive(Debug, Deserialize, Serialize, Clone)]
+pub struct TelemetryConfig {
+    #[serde(default = "default_telemetry_enabled")]
+    pub enabled: bool,
+    #[serde(default = "default_bind_address")]
+    pub bind_address: String,       // 监听地址，容器部署时设为 0.0.0.0
+    #[serde(default = "default_telemetry_port")]
+    pub port: u16,
+    #[serde(default = "default_channel_capacity")]
+    pub channel_capacity: usize,    // 广播通道容量，慢客户端超过后丢弃旧数据
+    #[serde(default)]
+    pub allowed_ips: Vec<IpAddr>,   // 客户端 IP 白名单，为空时不限制
+    #[serde(default = "default_heartbeat_interval_ms")]
+    pub heartbeat_interval_ms: u64, // 无信号时 "N" 心跳包间隔（毫秒），0 = 关闭
+}
+
+impl Default for TelemetryConfig {
+    fn default() -> Self {
+        Self {
+            enabled: default_telemetry_enabled(),
+            bind_address: default_bind_address(),
+            port: default_telemetry_port(),
+            channel_capacity: default_channel_capacity(),
+            allowed_ips: Vec::new(),
+            heartbeat_interval_ms: default_heartbeat_interval_ms(),
+        }
+    }
+}
+
+/// 波动率状态分类配置
+///
+/// Elevated 的进入阈值为顶层的 `threshold_pct`，价差调整为 `volatility.spread_adjust`。
+#[derive(Debug, Deserialize, Serialize, Clone)]
+pub struct RegimeConfig {
+    #[serde(default = "default_regime_normal_pct")]
+    pub normal_pct: Percent,        // 进入 Normal 的年化波动率（%），低于该档为 Calm，例如 20.0
+    #[serde(default = "default_regime_extreme_pct")]
+    pub extreme_pct: Percent,       // 进入 Extreme 的年化波动率（%），例如 120.0
+    #[serde(default = "default_regime_hysteresis_pct")]
+    pub hysteresis_pct: Percent,    // 退出阈值比进入阈值低的比例（%），例如 15.0: 60% 进入、51% 退出
+    #[serde(default)]
+    pub normal_exit_pct: Option<Percent>,   // 离开 Normal 的波动率（%），不设置则为 normal_pct × (1 - hysteresis)
+    #[serde(default)]
+    pub elevated_exit_pct: Option<Percent>, // 离开 Elevated 的波动率（%），不设置则为 threshold_pct × (1 - hysteresis)
+    #[serde(default)]
+    pub extreme_exit_pct: Option<Percent>,  // 离开 Extreme 的波动率（%），不设置则为 extreme_pct × (1 - hysteresis)
+    #[serde(default = "default_regime_min_dwell_secs")]
+    pub min_dwell_secs: f64,        // 降档前在当前档位的最短停留时间（秒），升档不受限制
+    #[serde(default = "default_regime_extreme_spread_adjust")]
+    pub extreme_spread_adjust: f64, // Extreme 时调大双边价差（$），例如 20.0
+}
+
+impl Default for RegimeConfig {
+    fn default() -> Self {
+        Self {
+            normal_pct: default_regime_normal_pct(),
+            extreme_pct: default_regime_extreme_pct(),
+            hysteresis_pct: default_regime_hysteresis_pct(),
+            normal_exit_pct: None,
+            elevated_exit_pct: None,
+            extreme_exit_pct: None,
+            min_dwell_secs: default_regime_min_dwell_secs(),
+            extreme_spread_adjust: default_regime_extreme_spread_adjust(),
+        }
+    }
+}
+
+/// 多周期波动率期限结构配置
+#[derive(Debug, Deserialize, Serialize, Clone)]
+pub struct TermStructureConfig {
+    #[serde(default = "default_term_enabled")]
+    pub enabled: bool,
+    #[serde(default = "default_horizons_secs")]
+    pub horizons_secs: Vec<f64>,    // 各周期长度（秒），例如 [1, 10, 60, 300, 3600]
+    #[serde(default = "default_term_bucket_ms")]
+    pub bucket_ms: u64,             // 时间桶宽度（毫秒），决定短周期的边界精度
+    #[serde(default = "default_term_min_samples")]
+    pub min_samples: usize,         // 周期内最少收益率笔数，不足时该周期为 null
+    #[serde(default = "default_term_publish_interval_ms")]
+    pub publish_interval_ms: u64,   // 随 Telemetry 数据包下发期限结构、检查形态报警的间隔（毫秒）
+    #[serde(default = "default_term_short_secs")]
+    pub short_secs: f64,            // 形态报警使用的短周期（秒），须在 horizons_secs 中
+    #[serde(default = "default_term_long_secs")]
+    pub long_secs: f64,             // 形态报警使用的长周期（秒），须在 horizons_secs 中
+    #[serde(default)]
+    pub spike_ratio: Option<f64>,   // 短周期 / 长周期波动率 ≥ 该值时报警，不设置则关闭
+    #[serde(default)]
+    pub vol_of_vol_jump: Option<f64>, // 短周期波动率相邻两次采样的 |ln(σ_t / σ_t-1)| ≥ 该值时报警，不设置则关闭
+}
+
+impl Default for TermStructureConfig {
+    fn default() -> Self {
+        Self {
+            enabled: default_term_enabled(),
+            horizons_secs: default_horizons_secs(),
+            bucket_ms: default_term_bucket_ms(),
+            min_samples: default_term_min_samples(),
+            publish_interval_ms: default_term_publish_interval_ms(),
+            short_secs: default_term_short_secs(),
+            long_secs: default_term_long_secs(),
+            spike_ratio: None,
+            vol_of_vol_jump: None,
+        }
+    }
+}
+
+impl TermStructureConfig {
+    /// 周期在 `horizons_secs` 中的位置
+    pub fn horizon_index(&self, secs: f64) -> Option<usize> {
+        self.horizons_secs.iter().position(|&h| h == secs)
+    }
+}
+
+#[derive(Debug, Deserialize, Serialize, Clone)]
 pub struct MonitorConfig {
     // Maps directly to 'slack_webhook_url' in the YAML file.
     pub slack_webhook_url: String,
@@ -61,34 +292,1222 @@ pub struct MonitorConfig {
     #[serde(default = "default_slack_enabled")]
     pub slack_enabled: bool,
 
-    pub threshold: f64,
+    /// 监控的交易对列表，例如 ["btcusdt", "ethusdt"]，默认仅 btcusdt
+    #[serde(default = "default_symbols")]
+    pub symbols: Vec<String>,
+
+    /// 原始帧录制文件路径 (gzip)，为空时不录制；每次启动在文件名后附加启动时间写入新文件
+    #[serde(default)]
+    pub record_path: Option<String>,
+
+    /// 年化波动率报警阈值（%），即进入 Elevated 状态的阈值，例如 60.0
+    pub threshold_pct: Percent,
     pub cooldown_secs: u64,
 
     pub histogram: HistogramConfig,
     pub volatility: VolatilityConfig,
     pub trend: TrendConfig,
+    #[serde(default)]
+    pub order_book: OrderBookConfig,
+    #[serde(default)]
+    pub telemetry: TelemetryConfig,
+    #[serde(default)]
+    pub term_structure: TermStructureConfig,
+    #[serde(default)]
+    pub regime: RegimeConfig,
+
+    /// 按交易对覆盖的参数 (键不区分大小写)，见模块文档
+    #[serde(default)]
+    pub symbol_overrides: BTreeMap<String, Value>,
 }
 
+/// 允许在 `symbol_overrides` 中按交易对覆盖的顶层字段
+pub const SYMBOL_OVERRIDE_FIELDS: [&str; 4] = ["threshold_pct", "volatility", "trend", "regime"];
+
 impl MonitorConfig {
+    /// 波动率状态分类阈值 (由 `threshold_pct` 与 `regime` 组合)
+    pub fn regime_thresholds(&self) -> RegimeThresholds {
+        let r = &self.regime;
+        let mut t = RegimeThresholds::with_hysteresis(
+            r.normal_pct.to_fraction(),
+            self.threshold_pct.to_fraction(),
+            r.extreme_pct.to_fraction(),
+            r.hysteresis_pct.to_fraction().0,
+            (r.min_dwell_secs * 1000.0).round() as u64,
+        );
+        // 显式配置的退出阈值优先于统一的滞回比例
+        if let Some(exit) = r.normal_exit_pct {
+            t.normal_exit = exit.to_fraction();
+        }
+        if let Some(exit) = r.elevated_exit_pct {
+            t.elevated_exit = exit.to_fraction();
+        }
+        if let Some(exit) = r.extreme_exit_pct {
+            t.extreme_exit = exit.to_fraction();
+        }
+        t
+    }
+
+    /// 合并 `symbol_overrides` 后指定交易对实际使用的配置，没有覆盖时为全局配置的副本
+    pub fn for_symbol(&self, symbol: &str) -> Result<MonitorConfig, String> {
+        let Some(overlay) = self.symbol_overrides.iter()
+            .find(|(k, _)| k.eq_ignore_ascii_case(symbol))
+            .map(|(_, v)| v)
+        else {
+            return Ok(self.clone());
+        };
+        let mut merged = serde_yaml::to_value(self).map_err(|e| e.to_string())?;
+        merge_value(&mut merged, overlay);
+        serde_yaml::from_value(merged).map_err(|e| format!("invalid override: {}", e))
+    }
+
     /// Loads configuration from the 'config.yaml' file in the current working directory.
     pub fn load() -> Result<Self, Box<dyn std::error::Error>> {
-        // Attempt to read the file. Ensure 'config.yaml' exists in the root.
-        let yaml_content = fs::read_to_string("config.yaml")
-            .map_err(|_| "❌ Failed to read config.yaml. Make sure the file exists in the root directory.")?;
+        Self::load_from("config.yaml")
+    }
+
+    /// Loads configuration from an explicit YAML path, applying `BNVOL_*` environment overrides.
+    pub fn load_from(path: &str) -> Result<Self, Box<dyn std::error::Error>> {
+        Self::load_with_env(path, std::env::vars())
+    }
+
+    /// 离线工具 (backtest / sweep) 使用：这些工具从不发送 Slack，校验前关闭 `slack_enabled`，
+    /// 生产配置无需填写 webhook 即可直接复用
+    pub fn load_offline(path: &str) -> Result<Self, Box<dyn std::error::Error>> {
+        Self::load_with_env_and(path, std::env::vars(), |c| c.slack_enabled = false)
+    }
+
+    /// 从指定文件加载，并应用给定的环境变量覆盖 (只处理 `BNVOL_` 前缀)
+    pub fn load_with_env<I>(path: &str, vars: I) -> Result<Self, Box<dyn std::error::Error>>
+    where
+        I: IntoIterator<Item = (String, String)>,
+    {
+        Self::load_with_env_and(path, vars, |_| {})
+    }
+
+    /// `load_with_env`，并在校验前调整解析出的配置
+    fn load_with_env_and<I, F>(path: &str, vars: I, adjust: F) -> Result<Self, Box<dyn std::error::Error>>
+    where
+        I: IntoIterator<Item = (String, String)>,
+        F: FnOnce(&mut MonitorConfig),
+    {
+        let yaml_content = fs::read_to_string(path)
+            .map_err(|_| format!("❌ Failed to read {}. Make sure the file exists.", path))?;
+
+        let mut raw: Value = serde_yaml::from_str(&yaml_content)
+            .map_err(|e| format!("❌ Failed to parse {}: {}", path, e))?;
+        let overridden = apply_env_overrides(&mut raw, vars)?;
+        for key in &overridden {
+            info!("🔧 Config override from environment: {}", key);
+        }
+        for note in migrate_legacy_units(&mut raw) {
+            warn!("⚠️ Deprecated config ({}): {}", path, note);
+        }
+        // 空值的覆盖表示取消设置：解析前移除该键，字段回到默认值 (可选字段为 None，列表为空)
+        let unset: Vec<&String> = overridden.iter()
+            .filter(|key| remove_null_path(&mut raw, key))
+            .collect();
+        let mut config: MonitorConfig = serde_yaml::from_value(raw.clone())
+            .map_err(|e| format!("❌ Failed to parse {} (with environment overrides): {}", path, e))?;
+
+        // 拼错的参数会被 serde 静默忽略并使用默认值：文件中的逐个提示，
+        // 环境变量是显式指定的覆盖，指向不存在的字段时直接报错
+        let mut unknown = config.unknown_keys(&raw);
+        let known = serde_yaml::to_value(&config).unwrap_or(Value::Null);
+        for key in unset {
+            // 交易对覆盖中的路径对照顶层字段检查
+            let field = key.strip_prefix("symbol_overrides.")
+                .and_then(|rest| rest.split_once('.'))
+                .map_or(key.as_str(), |(_, field)| field);
+            if field.split('.').try_fold(&known, |node, k| node.get(k)).is_none() {
+                unknown.push(key.clone());
+            }
+        }
+        for key in unknown {
+            match overridden.iter().find(|o| *o == &key || o.starts_with(&format!("{}.", key))) {
+                Some(o) => {
+                    return Err(format!(
+                        "❌ Override variable {} does not match any config field (`{}` is unknown)",
+                        env_var_name(o), key,
+                    ).into());
+                }
+                None => warn!("⚠️ Unknown config key `{}` in {} is ignored (typo?)", key, path),
+            }
+        }
 
-        let config: MonitorConfig = serde_yaml::from_str(&yaml_content)
-            .map_err(|e| format!("❌ Failed to parse config.yaml: {}", e))?;
+        adjust(&mut config);
+        config.validate()
+            .map_err(|errors| format!("❌ Invalid config in {}:\n{}", path, format_errors(&errors)))?;
 
-        // validation: Ensure critical fields like the webhook URL are populated.
-        if config.slack_webhook_url.is_empty() {
-            return Err("❌ slack_webhook_url in config.yaml is empty!".into());
+        // 统一为小写，与 combined-stream 的流名称一致
+        for symbol in config.symbols.iter_mut() {
+            *symbol = symbol.to_lowercase();
         }
 
         Ok(config)
     }
 }
 
+/// 将 `overlay` 逐层合并到 `base`：映射按键递归合并，其余值整体替换
+fn merge_value(base: &mut Value, overlay: &Value) {
+    match (base, overlay) {
+        (Value::Mapping(base_map), Value::Mapping(overlay_map)) => {
+            for (key, value) in overlay_map {
+                match base_map.get_mut(key) {
+                    Some(child) => merge_value(child, value),
+                    None => {
+                        base_map.insert(key.clone(), value.clone());
+                    }
+                }
+            }
+        }
+        (base, overlay) => *base = overlay.clone(),
+    }
+}
+
+/// 点分路径指向的值为 null 时从所在映射中移除，返回是否移除
+fn remove_null_path(root: &mut Value, path: &str) -> bool {
+    let (parent, leaf) = match path.rsplit_once('.') {
+        Some((parent, leaf)) => (parent.split('.').try_fold(root, |n, k| n.get_mut(k)), leaf),
+        None => (Some(root), path),
+    };
+    match parent {
+        Some(Value::Mapping(map)) if map.get(leaf).is_some_and(Value::is_null) => map.remove(leaf).is_some(),
+        _ => false,
+    }
+}
+
+/// 环境变量前缀
+pub const ENV_PREFIX: &str = "BNVOL_";
+
+/// 指定配置文件路径的环境变量 (不作为字段覆盖)
+pub const ENV_CONFIG_PATH: &str = "BNVOL_CONFIG";
+
+/// 将 `BNVOL_` 环境变量覆盖到原始 YAML 上，返回被覆盖字段的点分路径
+///
+/// 中间层级不存在时自动创建；路径是否有效由之后的未知键检查负责 (`load_with_env` 中报错)。
+pub fn apply_env_overrides<I>(raw: &mut Value, vars: I) -> Result<Vec<String>, String>
+where
+    I: IntoIterator<Item = (String, String)>,
+{
+    let mut applied = Vec::new();
+    for (name, value) in vars {
+        let Some(field) = name.strip_prefix(ENV_PREFIX) else { continue };
+        if name == ENV_CONFIG_PATH || field.is_empty() {
+            continue;
+        }
+        let path: Vec<String> = field.split("__").map(|k| k.to_lowercase()).collect();
+        if path.iter().any(|k| k.is_empty()) {
+            return Err(format!("❌ Invalid override variable {}", name));
+        }
+        // 数字/布尔/列表按 YAML 解析；其余 (URL 等) 原样作为字符串，避免 `#`、`: ` 被误解析。
+        // 映射只接受显式的 `{...}` 写法，`a: b` 形式的文本仍是字符串；空值为 null (取消设置)
+        let parsed = match serde_yaml::from_str::<Value>(&value) {
+            _ if value.trim().is_empty() => Value::Null,
+            Ok(Value::Mapping(m)) if value.trim_start().starts_with('{') => Value::Mapping(m),
+            Ok(v) if !v.is_string() && !v.is_mapping() => v,
+            _ => Value::String(value),
+        };
+
+        let mut node = &mut *raw;
+        for key in &path {
+            if !node.is_mapping() {
+                *node = Value::Mapping(Default::default());
+            }
+            let Value::Mapping(map) = node else { unreachable!() };
+            node = map.entry(Value::String(key.clone())).or_insert(Value::Null);
+        }
+        *node = parsed;
+        applied.push(path.join("."));
+    }
+    applied.sort();
+    Ok(applied)
+}
+
+/// 点分路径对应的环境变量名，例如 `trend.slope_threshold` -> `BNVOL_TREND__SLOPE_THRESHOLD`
+fn env_var_name(path: &str) -> String {
+    format!("{}{}", ENV_PREFIX, path.replace('.', "__").to_uppercase())
+}
+
+/// 旧版 `threshold` 不超过该值时按比例解读 (旧代码实际的比较方式)，否则按百分比解读 (旧文档的写法)
+pub const LEGACY_THRESHOLD_FRACTION_MAX: f64 = 5.0;
+
+/// 将旧版无单位后缀的键改写为 `_pct` 键，返回每一处改写的说明
+///
+/// - `threshold`：旧代码直接与比例形式的年化波动率比较，但示例配置按百分比填写 (60.0)，
+///   因此按 `LEGACY_THRESHOLD_FRACTION_MAX` 判断原值的单位
+/// - `histogram.step`、`volatility.fallback_volatility`：旧版一直按比例使用，乘以 100
+///
+/// 新旧键同时存在时以新键为准，旧键被丢弃。
+pub fn migrate_legacy_units(raw: &mut Value) -> Vec<String> {
+    let mut notes = Vec::new();
+    migrate_key(raw, &[], "threshold", "threshold_pct", &mut notes, |v| {
+        if v <= LEGACY_THRESHOLD_FRACTION_MAX {
+            (v * 100.0, "read as a fraction")
+        } else {
+            (v, "read as a percentage")
+        }
+    });
+    migrate_key(raw, &["histogram"], "step", "step_pct", &mut notes, |v| (v * 100.0, "read as a fraction"));
+    migrate_key(raw, &["volatility"], "fallback_volatility", "fallback_volatility_pct", &mut notes, |v| {
+        (v * 100.0, "read as a fraction")
+    });
+    notes
+}
+
+fn migrate_key<F>(raw: &mut Value, parents: &[&str], old: &str, new: &str, notes: &mut Vec<String>, convert: F)
+where
+    F: Fn(f64) -> (f64, &'static str),
+{
+    let mut node = &mut *raw;
+    for key in parents {
+        match node.get_mut(*key) {
+            Some(child) => node = child,
+            None => return,
+        }
+    }
+    let Value::Mapping(map) = node else { return };
+    let Some(value) = map.remove(old) else { return };
+
+    let prefix = parents.iter().map(|p| format!("{}.", p)).collect::<String>();
+    let (old_path, new_path) = (format!("{}{}", prefix, old), format!("{}{}", prefix, new));
+    if map.contains_key(new) {
+        notes.push(format!("`{}` is ignored because `{}` is also set", old_path, new_path));
+        return;
+    }
+    match value.as_f64() {
+        Some(v) => {
+            let (pct, how) = convert(v);
+            let pct = (pct * 1e10).round() / 1e10;
+            notes.push(format!(
+                "`{}: {}` {} → `{}: {}`; rename it to silence this warning",
+                old_path, v, how, new_path, pct,
+            ));
+            map.insert(Value::String(new.to_string()), Value::from(pct));
+        }
+        // 非数字原样移交，由反序列化报出类型错误
+        None => {
+            map.insert(Value::String(new.to_string()), value);
+        }
+    }
+}
+
+impl MonitorConfig {
+    /// 找出原始 YAML 中未被配置模型使用的键 (点分路径)
+    ///
+    /// 与解析结果重新序列化后的结构对比：序列化结果包含全部已知字段
+    /// (含默认值与 null)，原始 YAML 中多出的键即为未知键。
+    pub fn unknown_keys(&self, raw: &Value) -> Vec<String> {
+        let known = match serde_yaml::to_value(self) {
+            Ok(v) => v,
+            Err(_) => return Vec::new(),
+        };
+        let mut unknown = Vec::new();
+        collect_unknown_keys(raw, &known, "", &mut unknown);
+        unknown
+    }
+}
+
+fn collect_unknown_keys(raw: &Value, known: &Value, prefix: &str, out: &mut Vec<String>) {
+    let (Value::Mapping(raw_map), Value::Mapping(known_map)) = (raw, known) else {
+        return;
+    };
+    for (key, raw_child) in raw_map {
+        let name = match key {
+            Value::String(s) => s.clone(),
+            other => serde_yaml::to_string(other).unwrap_or_default().trim().to_string(),
+        };
+        let path = if prefix.is_empty() { name } else { format!("{}.{}", prefix, name) };
+        match known_map.get(key) {
+            Some(known_child) => collect_unknown_keys(raw_child, known_child, &path, out),
+            None => out.push(path),
+        }
+    }
+}
+
+/// 配置校验错误：字段点分路径 + 说明
+#[derive(Debug, Clone, PartialEq)]
+pub struct ConfigError {
+    pub path: String,
+    pub message: String,
+}
+
+impl std::fmt::Display for ConfigError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}: {}", self.path, self.message)
+    }
+}
+
+/// 将校验错误格式化为逐行列表
+pub fn format_errors(errors: &[ConfigError]) -> String {
+    errors.iter().map(|e| format!("  - {}", e)).collect::<Vec<_>>().join("\n")
+}
+
+/// 收集校验错误的辅助结构
+#[derive(Default)]
+struct Checker {
+    errors: Vec<ConfigError>,
+}
+
+impl Checker {
+    fn check(&mut self, ok: bool, path: &str, message: impl Into<String>) {
+        if !ok {
+            self.errors.push(ConfigError { path: path.to_string(), message: message.into() });
+        }
+    }
+
+    // 以下比较对 NaN 一律判为不合法
+    fn positive(&mut self, v: f64, path: &str) {
+        self.check(v > 0.0, path, format!("must be > 0 (got {})", v));
+    }
+
+    fn non_negative(&mut self, v: f64, path: &str) {
+        self.check(v >= 0.0, path, format!("must be >= 0 (got {})", v));
+    }
+
+    fn in_range(&mut self, v: f64, min: f64, max: f64, path: &str) {
+        self.check((min..=max).contains(&v), path, format!("must be within [{}, {}] (got {})", min, max, v));
+    }
+}
+
+impl MonitorConfig {
+    /// 校验全部字段的取值范围，返回所有错误 (而不是遇到第一个就停止)
+    ///
+    /// 启动与热加载时都会调用；任何错误都会导致该配置被拒绝。
+    pub fn validate(&self) -> Result<(), Vec<ConfigError>> {
+        let mut c = Checker::default();
+
+        c.check(
+            !self.slack_enabled || !self.slack_webhook_url.is_empty(),
+            "slack_webhook_url",
+            "must not be empty when slack_enabled (set it in the file or via BNVOL_SLACK_WEBHOOK_URL)",
+        );
+        c.check(!self.symbols.is_empty(), "symbols", "must contain at least one symbol");
+        for (i, symbol) in self.symbols.iter().enumerate() {
+            c.check(
+                !symbol.is_empty() && symbol.chars().all(|ch| ch.is_ascii_alphanumeric()),
+                &format!("symbols[{}]", i),
+                format!("invalid symbol {:?}", symbol),
+            );
+        }
+        c.positive(self.threshold_pct.0, "threshold_pct");
+
+        let h = &self.histogram;
+        c.check(h.interval > 0, "histogram.interval", "must be > 0");
+        c.positive(h.step_pct.0, "histogram.step_pct");
+        c.check(h.buckets > 0, "histogram.buckets", "must be > 0");
+
+        let v = &self.volatility;
+        c.check(v.window_size >= 2, "volatility.window_size", format!("must be >= 2 (got {})", v.window_size));
+        c.positive(v.window_secs, "volatility.window_secs");
+        c.check(v.min_samples >= 2, "volatility.min_samples", format!("must be >= 2 (got {})", v.min_samples));
+        c.check(v.stale_threshold_ms > 0, "volatility.stale_threshold_ms", "must be > 0");
+        c.check(v.expire_threshold_ms > 0, "volatility.expire_threshold_ms", "must be > 0");
+        c.non_negative(v.fallback_volatility_pct.0, "volatility.fallback_volatility_pct");
+        c.non_negative(v.spread_adjust, "volatility.spread_adjust");
+        c.positive(v.ewma_half_life_secs, "volatility.ewma_half_life_secs");
+        c.check(v.bar_ms > 0, "volatility.bar_ms", "must be > 0");
+        c.check(v.tsrv_slow_ticks >= 2, "volatility.tsrv_slow_ticks", format!("must be >= 2 (got {})", v.tsrv_slow_ticks));
+        // K 线类估计器在计数窗口 (成交密集时只有几十毫秒) 或过短的时间窗口中永远凑不出完整 K 线
+        let selected = std::iter::once(("volatility.estimator".to_string(), v.estimator)).chain(
+            v.report_estimators.iter().enumerate().map(|(i, k)| (format!("volatility.report_estimators[{}]", i), *k)),
+        );
+        for (path, kind) in selected.filter(|(_, k)| k.is_bar_based()) {
+            c.check(
+                v.window_mode == WindowMode::Time,
+                &path,
+                format!("{} needs volatility.window_mode: time", kind.name()),
+            );
+            c.check(
+                v.window_secs * 1000.0 >= (MIN_BARS_PER_WINDOW * v.bar_ms) as f64,
+                &path,
+                format!(
+                    "{} needs volatility.window_secs to cover at least {} bars ({}s * 1000 < {} * {}ms)",
+                    kind.name(), MIN_BARS_PER_WINDOW, v.window_secs, MIN_BARS_PER_WINDOW, v.bar_ms,
+                ),
+            );
+        }
+
+        let t = &self.trend;
+        c.check(t.vwap_window_ms > 0, "trend.vwap_window_ms", "must be > 0");
+        c.check(t.vwap_series_max_len > 0, "trend.vwap_series_max_len", "must be > 0");
+        c.positive(t.fit_window_secs, "trend.fit_window_secs");
+        c.positive(t.fit_window_2s, "trend.fit_window_2s");
+        c.check(t.fit_min_points >= 2, "trend.fit_min_points", format!("must be >= 2 (got {})", t.fit_min_points));
+        c.in_range(t.fit_min_r2, 0.0, 1.0, "trend.fit_min_r2");
+        c.positive(t.ofi_cum_window_secs, "trend.ofi_cum_window_secs");
+        c.check(
+            t.ofi_decay > 0.0 && t.ofi_decay <= 1.0,
+            "trend.ofi_decay",
+            format!("must be within (0, 1] (got {})", t.ofi_decay),
+        );
+        c.non_negative(t.slope_threshold, "trend.slope_threshold");
+        c.non_negative(t.ofi_confirm_threshold, "trend.ofi_confirm_threshold");
+        c.in_range(t.slope_threshold_ratio, 0.0, 1.0, "trend.slope_threshold_ratio");
+        c.non_negative(t.min_price_fallback, "trend.min_price_fallback");
+        c.non_negative(t.max_price_fallback, "trend.max_price_fallback");
+        c.check(
+            t.min_price_fallback <= t.max_price_fallback,
+            "trend.min_price_fallback",
+            format!(
+                "must be <= trend.max_price_fallback ({} > {})",
+                t.min_price_fallback, t.max_price_fallback,
+            ),
+        );
+        c.non_negative(t.entry_protection_secs, "trend.entry_protection_secs");
+        c.check(t.slope_history_len > 0, "trend.slope_history_len", "must be > 0");
+        c.check(
+            t.slope_reversal_weak_count < t.slope_history_len,
+            "trend.slope_reversal_weak_count",
+            format!(
+                "must be < trend.slope_history_len ({} >= {})",
+                t.slope_reversal_weak_count, t.slope_history_len,
+            ),
+        );
+        c.non_negative(t.slope_reversal_min_secs, "trend.slope_reversal_min_secs");
+        if let Some(secs) = t.max_holding_secs {
+            c.positive(secs, "trend.max_holding_secs");
+        }
+        if let Some(stop) = t.trailing_stop {
+            c.positive(stop, "trend.trailing_stop");
+        }
+        c.non_negative(t.predict_horizon_secs, "trend.predict_horizon_secs");
+        c.positive(t.impact_qty, "trend.impact_qty");
+        c.non_negative(t.cooldown_secs, "trend.cooldown_secs");
+
+        let ob = &self.order_book;
+        c.check(!ob.snapshot_url.is_empty(), "order_book.snapshot_url", "must not be empty");
+        c.check(
+            [5, 10, 20, 50, 100, 500, 1000].contains(&ob.snapshot_limit),
+            "order_book.snapshot_limit",
+            format!("must be one of 5/10/20/50/100/500/1000 (got {})", ob.snapshot_limit),
+        );
+
+        c.check(self.telemetry.channel_capacity > 0, "telemetry.channel_capacity", "must be > 0");
+
+        let r = &self.regime;
+        c.positive(r.normal_pct.0, "regime.normal_pct");
+        c.check(
+            r.normal_pct < self.threshold_pct,
+            "regime.normal_pct",
+            format!("must be < threshold_pct ({} >= {})", r.normal_pct.0, self.threshold_pct.0),
+        );
+        c.check(
+            r.extreme_pct > self.threshold_pct,
+            "regime.extreme_pct",
+            format!("must be > threshold_pct ({} <= {})", r.extreme_pct.0, self.threshold_pct.0),
+        );
+        c.check(
+            (0.0..100.0).contains(&r.hysteresis_pct.0),
+            "regime.hysteresis_pct",
+            format!("must be within [0, 100) (got {})", r.hysteresis_pct.0),
+        );
+        for (path, exit, enter, enter_path) in [
+            ("regime.normal_exit_pct", r.normal_exit_pct, r.normal_pct, "regime.normal_pct"),
+            ("regime.elevated_exit_pct", r.elevated_exit_pct, self.threshold_pct, "threshold_pct"),
+            ("regime.extreme_exit_pct", r.extreme_exit_pct, r.extreme_pct, "regime.extreme_pct"),
+        ] {
+            if let Some(exit) = exit {
+                c.positive(exit.0, path);
+                c.check(exit < enter, path, format!("must be < {} ({} >= {})", enter_path, exit.0, enter.0));
+            }
+        }
+        c.non_negative(r.min_dwell_secs, "regime.min_dwell_secs");
+        c.non_negative(r.extreme_spread_adjust, "regime.extreme_spread_adjust");
+
+        let ts = &self.term_structure;
+        c.check(!ts.horizons_secs.is_empty(), "term_structure.horizons_secs", "must contain at least one horizon");
+        for (i, &secs) in ts.horizons_secs.iter().enumerate() {
+            c.positive(secs, &format!("term_structure.horizons_secs[{}]", i));
+        }
+        c.check(ts.bucket_ms > 0, "term_structure.bucket_ms", "must be > 0");
+        c.check(ts.min_samples >= 1, "term_structure.min_samples", "must be >= 1");
+        c.check(ts.publish_interval_ms > 0, "term_structure.publish_interval_ms", "must be > 0");
+        c.check(
+            ts.horizon_index(ts.short_secs).is_some(),
+            "term_structure.short_secs",
+            format!("must be one of term_structure.horizons_secs (got {})", ts.short_secs),
+        );
+        c.check(
+            ts.horizon_index(ts.long_secs).is_some(),
+            "term_structure.long_secs",
+            format!("must be one of term_structure.horizons_secs (got {})", ts.long_secs),
+        );
+        c.check(
+            ts.short_secs < ts.long_secs,
+            "term_structure.short_secs",
+            format!("must be < term_structure.long_secs ({} >= {})", ts.short_secs, ts.long_secs),
+        );
+        if let Some(ratio) = ts.spike_ratio {
+            c.check(ratio > 1.0, "term_structure.spike_ratio", format!("must be > 1 (got {})", ratio));
+        }
+        if let Some(jump) = ts.vol_of_vol_jump {
+            c.positive(jump, "term_structure.vol_of_vol_jump");
+        }
+
+        self.validate_symbol_overrides(&mut c);
+
+        if c.errors.is_empty() { Ok(()) } else { Err(c.errors) }
+    }
+
+    /// 校验每个交易对的覆盖：只能覆盖允许的字段、不能有未知键，合并后的配置同样需要通过校验
+    /// (与全局配置相同的错误不重复报告)
+    fn validate_symbol_overrides(&self, c: &mut Checker) {
+        let global_errors = c.errors.clone();
+        let known = serde_yaml::to_value(self).unwrap_or(Value::Null);
+
+        for (symbol, overlay) in &self.symbol_overrides {
+            let path = format!("symbol_overrides.{}", symbol);
+            c.check(
+                self.symbols.iter().any(|s| s.eq_ignore_ascii_case(symbol)),
+                &path,
+                "is not one of symbols",
+            );
+            let Value::Mapping(map) = overlay else {
+                c.check(false, &path, "must be a mapping");
+                continue;
+            };
+            for (key, child) in map {
+                let name = key.as_str().unwrap_or_default();
+                let field_path = format!("{}.{}", path, name);
+                if !SYMBOL_OVERRIDE_FIELDS.contains(&name) {
+                    c.check(
+                        false,
+                        &field_path,
+                        format!("cannot be overridden per symbol (allowed: {})", SYMBOL_OVERRIDE_FIELDS.join(", ")),
+                    );
+                    continue;
+                }
+                let mut unknown = Vec::new();
+                collect_unknown_keys(child, &known[name], &field_path, &mut unknown);
+                for key in unknown {
+                    c.check(false, &key, "unknown key");
+                }
+            }
+
+            let mut resolved = match self.for_symbol(symbol) {
+                Ok(cfg) => cfg,
+                Err(e) => {
+                    c.check(false, &path, e);
+                    continue;
+                }
+            };
+            resolved.symbol_overrides.clear();
+            for e in resolved.validate().err().unwrap_or_default() {
+                if !global_errors.contains(&e) {
+                    c.check(false, &format!("{}.{}", path, e.path), e.message);
+                }
+            }
+        }
+    }
+}
+
 /// 默认启用 Slack 报警
 fn default_slack_enabled() -> bool {
     true
-}
\ No newline at end of file
+}
+
+/// 默认只监控 BTC/USDT
+fn default_symbols() -> Vec<String> {
+    vec!["btcusdt".to_string()]
+}
+
+fn default_slope_history_len() -> usize {
+    10
+}
+
+fn default_slope_reversal_min_secs() -> f64 {
+    5.0
+}
+
+fn default_slope_reversal_weak_count() -> usize {
+    5
+}
+
+fn default_impact_qty() -> f64 {
+    1.0
+}
+
+fn default_window_secs() -> f64 {
+    10.0
+}
+
+fn default_min_samples() -> usize {
+    20
+}
+
+fn default_ewma_half_life_secs() -> f64 {
+    2.0
+}
+
+fn default_bar_ms() -> u64 {
+    1000
+}
+
+fn default_tsrv_slow_ticks() -> usize {
+    5
+}
+
+fn default_depth_mode() -> DepthMode {
+    DepthMode::Partial
+}
+
+fn default_snapshot_url() -> String {
+    "https://fapi.binance.com/fapi/v1/depth".to_string()
+}
+
+fn default_snapshot_limit() -> u32 {
+    1000
+}
+
+fn default_telemetry_enabled() -> bool {
+    true
+}
+
+fn default_bind_address() -> String {
+    "127.0.0.1".to_string()
+}
+
+fn default_telemetry_port() -> u16 {
+    9001
+}
+
+fn default_channel_capacity() -> usize {
+    2000
+}
+
+fn default_heartbeat_interval_ms() -> u64 {
+    1000
+}
+
+fn default_term_enabled() -> bool {
+    true
+}
+
+fn default_horizons_secs() -> Vec<f64> {
+    vec![1.0, 10.0, 60.0, 300.0, 3600.0]
+}
+
+fn default_term_bucket_ms() -> u64 {
+    100
+}
+
+fn default_term_min_samples() -> usize {
+    10
+}
+
+fn default_term_publish_interval_ms() -> u64 {
+    1000
+}
+
+fn default_term_short_secs() -> f64 {
+    10.0
+}
+
+fn default_term_long_secs() -> f64 {
+    300.0
+}
+
+fn default_regime_normal_pct() -> Percent {
+    Percent(20.0)
+}
+
+fn default_regime_extreme_pct() -> Percent {
+    Percent(120.0)
+}
+
+fn default_regime_hysteresis_pct() -> Percent {
+    Percent(15.0)
+}
+
+fn default_regime_min_dwell_secs() -> f64 {
+    5.0
+}
+
+fn default_regime_extreme_spread_adjust() -> f64 {
+    20.0
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::common::units::Fraction;
+
+    /// 以示例配置为基准
+    fn example() -> MonitorConfig {
+        serde_yaml::from_str(include_str!("../config.example.yaml")).expect("config.example.yaml parses")
+    }
+
+    /// 校验失败的字段路径
+    fn error_paths(cfg: &MonitorConfig) -> Vec<String> {
+        match cfg.validate() {
+            Ok(()) => Vec::new(),
+            Err(errors) => errors.into_iter().map(|e| e.path).collect(),
+        }
+    }
+
+    #[test]
+    fn example_config_is_valid() {
+        assert_eq!(example().validate(), Ok(()));
+    }
+
+    #[test]
+    fn validate_rejects_ofi_decay_outside_unit_interval() {
+        for decay in [1.5, 0.0, -0.2, f64::NAN] {
+            let mut cfg = example();
+            cfg.trend.ofi_decay = decay;
+            assert_eq!(error_paths(&cfg), vec!["trend.ofi_decay"], "ofi_decay = {}", decay);
+        }
+        let mut cfg = example();
+        cfg.trend.ofi_decay = 1.0;
+        assert_eq!(cfg.validate(), Ok(()));
+    }
+
+    #[test]
+    fn validate_rejects_zero_histogram_buckets() {
+        let mut cfg = example();
+        cfg.histogram.buckets = 0;
+        assert_eq!(error_paths(&cfg), vec!["histogram.buckets"]);
+    }
+
+    #[test]
+    fn validate_rejects_min_price_fallback_above_max() {
+        let mut cfg = example();
+        cfg.trend.min_price_fallback = 40.0;
+        cfg.trend.max_price_fallback = 35.0;
+        let errors = cfg.validate().unwrap_err();
+        assert_eq!(errors.len(), 1);
+        assert_eq!(errors[0].path, "trend.min_price_fallback");
+        assert!(errors[0].message.contains("40 > 35"), "{}", errors[0].message);
+
+        cfg.trend.min_price_fallback = 35.0;
+        assert_eq!(cfg.validate(), Ok(()));
+    }
+
+    #[test]
+    fn validate_rejects_fit_min_points_below_two() {
+        for points in [0, 1] {
+            let mut cfg = example();
+            cfg.trend.fit_min_points = points;
+            assert_eq!(error_paths(&cfg), vec!["trend.fit_min_points"], "fit_min_points = {}", points);
+        }
+    }
+
+    #[test]
+    fn validate_collects_all_errors() {
+        let mut cfg = example();
+        cfg.trend.ofi_decay = 1.5;
+        cfg.histogram.buckets = 0;
+        cfg.trend.min_price_fallback = 40.0;
+        cfg.trend.max_price_fallback = 35.0;
+        cfg.trend.fit_min_points = 1;
+        assert_eq!(
+            error_paths(&cfg),
+            vec!["histogram.buckets", "trend.fit_min_points", "trend.ofi_decay", "trend.min_price_fallback"],
+        );
+        let message = format_errors(&cfg.validate().unwrap_err());
+        assert_eq!(message.lines().count(), 4);
+    }
+
+    #[test]
+    fn validate_requires_time_window_for_bar_estimators() {
+        let mut cfg = example();
+        cfg.volatility.window_mode = WindowMode::Count;
+        cfg.volatility.estimator = EstimatorKind::Parkinson;
+        cfg.volatility.report_estimators = vec![EstimatorKind::Ewma, EstimatorKind::BarRv];
+        let errors = cfg.validate().unwrap_err();
+        let paths: Vec<&str> = errors.iter().map(|e| e.path.as_str()).collect();
+        assert_eq!(paths, vec!["volatility.estimator", "volatility.report_estimators[1]"]);
+        assert!(errors[0].message.contains("parkinson needs volatility.window_mode: time"), "{}", errors[0]);
+
+        cfg.volatility.window_mode = WindowMode::Time;
+        assert_eq!(cfg.validate(), Ok(()));
+
+        // 非 K 线类估计器不受窗口模式限制
+        cfg.volatility.window_mode = WindowMode::Count;
+        cfg.volatility.estimator = EstimatorKind::Tsrv;
+        cfg.volatility.report_estimators = vec![EstimatorKind::Rms, EstimatorKind::Ewma];
+        assert_eq!(cfg.validate(), Ok(()));
+    }
+
+    #[test]
+    fn validate_requires_three_bars_per_window() {
+        let mut cfg = example();
+        cfg.volatility.window_mode = WindowMode::Time;
+        cfg.volatility.estimator = EstimatorKind::Bipower;
+        cfg.volatility.bar_ms = 1_000;
+        cfg.volatility.window_secs = 2.5;
+        let errors = cfg.validate().unwrap_err();
+        assert_eq!(errors.len(), 1);
+        assert_eq!(errors[0].path, "volatility.estimator");
+        assert!(errors[0].message.contains("2.5s * 1000 < 3 * 1000ms"), "{}", errors[0]);
+
+        cfg.volatility.window_secs = 3.0;
+        assert_eq!(cfg.validate(), Ok(()));
+        cfg.volatility.bar_ms = 1_001;
+        assert!(cfg.validate().is_err());
+    }
+
+    #[test]
+    fn regime_exit_thresholds_default_to_hysteresis() {
+        let mut cfg = example();
+        let t = cfg.regime_thresholds();
+        assert!((t.elevated_exit.0 - cfg.threshold_pct.to_fraction().0 * 0.85).abs() < 1e-12);
+        assert!((t.normal_exit.0 - cfg.regime.normal_pct.to_fraction().0 * 0.85).abs() < 1e-12);
+
+        cfg.regime.elevated_exit_pct = Some(Percent(45.0));
+        let t = cfg.regime_thresholds();
+        assert_eq!(t.elevated_exit, Fraction(0.45));
+        assert!((t.extreme_exit.0 - cfg.regime.extreme_pct.to_fraction().0 * 0.85).abs() < 1e-12);
+        assert_eq!(cfg.validate(), Ok(()));
+    }
+
+    #[test]
+    fn validate_rejects_exit_threshold_not_below_enter() {
+        let mut cfg = example();
+        cfg.regime.elevated_exit_pct = Some(cfg.threshold_pct);
+        cfg.regime.extreme_exit_pct = Some(Percent(0.0));
+        assert_eq!(error_paths(&cfg), vec!["regime.elevated_exit_pct", "regime.extreme_exit_pct"]);
+    }
+
+    /// 覆盖到某个交易对上的 YAML 片段
+    fn overlay(cfg: &mut MonitorConfig, symbol: &str, yaml: &str) {
+        cfg.symbol_overrides.insert(symbol.to_string(), serde_yaml::from_str(yaml).unwrap());
+    }
+
+    /// 每条规则一行：(说明, 对示例配置的修改, 期望的错误路径)
+    #[test]
+    fn validate_reports_each_rule_at_its_field_path() {
+        type Mutation = fn(&mut MonitorConfig);
+        let cases: &[(&str, Mutation, &[&str])] = &[
+            ("empty webhook", |c| c.slack_webhook_url.clear(), &["slack_webhook_url"]),
+            ("webhook not needed when slack is off", |c| {
+                c.slack_webhook_url.clear();
+                c.slack_enabled = false;
+            }, &[]),
+            ("no symbols", |c| {
+                c.symbols.clear();
+                c.symbol_overrides.clear();
+            }, &["symbols"]),
+            ("bad symbol", |c| c.symbols.push("sol/usdt".into()), &["symbols[2]"]),
+            ("threshold", |c| c.threshold_pct = Percent(0.0), &["threshold_pct", "regime.normal_pct"]),
+            ("histogram.interval", |c| c.histogram.interval = 0, &["histogram.interval"]),
+            ("histogram.step_pct", |c| c.histogram.step_pct = Percent(0.0), &["histogram.step_pct"]),
+            ("window_size", |c| c.volatility.window_size = 1, &["volatility.window_size"]),
+            ("window_secs", |c| c.volatility.window_secs = 0.0, &["volatility.window_secs"]),
+            ("vol min_samples", |c| c.volatility.min_samples = 1, &["volatility.min_samples"]),
+            ("stale threshold", |c| c.volatility.stale_threshold_ms = 0, &["volatility.stale_threshold_ms"]),
+            ("expire threshold", |c| c.volatility.expire_threshold_ms = 0, &["volatility.expire_threshold_ms"]),
+            ("fallback", |c| c.volatility.fallback_volatility_pct = Percent(-1.0), &["volatility.fallback_volatility_pct"]),
+            ("vol spread", |c| c.volatility.spread_adjust = -1.0, &["volatility.spread_adjust"]),
+            ("ewma half-life", |c| c.volatility.ewma_half_life_secs = 0.0, &["volatility.ewma_half_life_secs"]),
+            ("bar_ms", |c| c.volatility.bar_ms = 0, &["volatility.bar_ms"]),
+            ("tsrv ticks", |c| c.volatility.tsrv_slow_ticks = 1, &["volatility.tsrv_slow_ticks"]),
+            ("vwap window", |c| c.trend.vwap_window_ms = 0, &["trend.vwap_window_ms"]),
+            ("vwap len", |c| c.trend.vwap_series_max_len = 0, &["trend.vwap_series_max_len"]),
+            ("fit window", |c| c.trend.fit_window_secs = 0.0, &["trend.fit_window_secs"]),
+            ("fit window 2s", |c| c.trend.fit_window_2s = -1.0, &["trend.fit_window_2s"]),
+            ("fit r2", |c| c.trend.fit_min_r2 = 1.5, &["trend.fit_min_r2"]),
+            ("ofi window", |c| c.trend.ofi_cum_window_secs = 0.0, &["trend.ofi_cum_window_secs"]),
+            ("slope threshold", |c| c.trend.slope_threshold = -1.0, &["trend.slope_threshold"]),
+            ("ofi confirm", |c| c.trend.ofi_confirm_threshold = -1.0, &["trend.ofi_confirm_threshold"]),
+            ("slope ratio", |c| c.trend.slope_threshold_ratio = 1.5, &["trend.slope_threshold_ratio"]),
+            ("min fallback", |c| c.trend.min_price_fallback = -1.0, &["trend.min_price_fallback"]),
+            ("max fallback", |c| c.trend.max_price_fallback = -1.0, &["trend.max_price_fallback", "trend.min_price_fallback"]),
+            ("entry protection", |c| c.trend.entry_protection_secs = -1.0, &["trend.entry_protection_secs"]),
+            ("slope history", |c| c.trend.slope_history_len = 0, &["trend.slope_history_len", "trend.slope_reversal_weak_count"]),
+            ("weak count = history", |c| c.trend.slope_reversal_weak_count = c.trend.slope_history_len, &["trend.slope_reversal_weak_count"]),
+            ("weak count < history", |c| c.trend.slope_reversal_weak_count = c.trend.slope_history_len - 1, &[]),
+            ("reversal min secs", |c| c.trend.slope_reversal_min_secs = -1.0, &["trend.slope_reversal_min_secs"]),
+            ("max holding", |c| c.trend.max_holding_secs = Some(0.0), &["trend.max_holding_secs"]),
+            ("trailing stop", |c| c.trend.trailing_stop = Some(-1.0), &["trend.trailing_stop"]),
+            ("predict horizon", |c| c.trend.predict_horizon_secs = -1.0, &["trend.predict_horizon_secs"]),
+            ("impact qty", |c| c.trend.impact_qty = 0.0, &["trend.impact_qty"]),
+            ("trend cooldown", |c| c.trend.cooldown_secs = -1.0, &["trend.cooldown_secs"]),
+            ("snapshot url", |c| c.order_book.snapshot_url.clear(), &["order_book.snapshot_url"]),
+            ("snapshot limit", |c| c.order_book.snapshot_limit = 25, &["order_book.snapshot_limit"]),
+            ("channel capacity", |c| c.telemetry.channel_capacity = 0, &["telemetry.channel_capacity"]),
+            ("normal zero", |c| c.regime.normal_pct = Percent(0.0), &["regime.normal_pct"]),
+            ("normal >= threshold", |c| c.regime.normal_pct = c.threshold_pct, &["regime.normal_pct"]),
+            ("extreme <= threshold", |c| c.regime.extreme_pct = c.threshold_pct, &["regime.extreme_pct"]),
+            ("hysteresis", |c| c.regime.hysteresis_pct = Percent(100.0), &["regime.hysteresis_pct"]),
+            ("normal exit >= enter", |c| c.regime.normal_exit_pct = Some(c.regime.normal_pct), &["regime.normal_exit_pct"]),
+            ("normal exit zero", |c| c.regime.normal_exit_pct = Some(Percent(0.0)), &["regime.normal_exit_pct"]),
+            ("extreme exit >= enter", |c| c.regime.extreme_exit_pct = Some(Percent(130.0)), &["regime.extreme_exit_pct"]),
+            ("exits below enter", |c| {
+                c.regime.normal_exit_pct = Some(Percent(15.0));
+                c.regime.elevated_exit_pct = Some(Percent(55.0));
+                c.regime.extreme_exit_pct = Some(Percent(100.0));
+            }, &[]),
+            ("min dwell", |c| c.regime.min_dwell_secs = -1.0, &["regime.min_dwell_secs"]),
+            ("extreme spread", |c| c.regime.extreme_spread_adjust = -1.0, &["regime.extreme_spread_adjust"]),
+            ("no horizons", |c| c.term_structure.horizons_secs.clear(), &[
+                "term_structure.horizons_secs",
+                "term_structure.short_secs",
+                "term_structure.long_secs",
+            ]),
+            ("negative horizon", |c| c.term_structure.horizons_secs[0] = -1.0, &["term_structure.horizons_secs[0]"]),
+            ("bucket", |c| c.term_structure.bucket_ms = 0, &["term_structure.bucket_ms"]),
+            ("term min_samples", |c| c.term_structure.min_samples = 0, &["term_structure.min_samples"]),
+            ("publish interval", |c| c.term_structure.publish_interval_ms = 0, &["term_structure.publish_interval_ms"]),
+            ("short not a horizon", |c| c.term_structure.short_secs = 5.0, &["term_structure.short_secs"]),
+            ("long not a horizon", |c| c.term_structure.long_secs = 120.0, &["term_structure.long_secs"]),
+            ("short >= long", |c| c.term_structure.short_secs = 300.0, &["term_structure.short_secs"]),
+            ("spike ratio", |c| c.term_structure.spike_ratio = Some(1.0), &["term_structure.spike_ratio"]),
+            ("vol of vol", |c| c.term_structure.vol_of_vol_jump = Some(0.0), &["term_structure.vol_of_vol_jump"]),
+            ("override unknown symbol", |c| overlay(c, "solusdt", "{trend: {slope_threshold: 0.1}}"), &["symbol_overrides.solusdt"]),
+            ("override not a mapping", |c| overlay(c, "ethusdt", "0.5"), &["symbol_overrides.ethusdt"]),
+            ("override disallowed field", |c| overlay(c, "ethusdt", "{histogram: {buckets: 10}}"), &["symbol_overrides.ethusdt.histogram"]),
+            ("override unknown key", |c| overlay(c, "ethusdt", "{trend: {slope_treshold: 0.1}}"), &["symbol_overrides.ethusdt.trend.slope_treshold"]),
+            ("override invalid value", |c| overlay(c, "ethusdt", "{trend: {ofi_decay: 2.0}}"), &["symbol_overrides.ethusdt.trend.ofi_decay"]),
+            ("override breaks regime order", |c| overlay(c, "ethusdt", "{threshold_pct: 150.0}"), &["symbol_overrides.ethusdt.regime.extreme_pct"]),
+        ];
+
+        for (name, mutate, expected) in cases {
+            let mut cfg = example();
+            mutate(&mut cfg);
+            assert_eq!(error_paths(&cfg), *expected, "{}", name);
+        }
+    }
+
+    fn migrated(yaml: &str) -> (Value, Vec<String>) {
+        let mut raw: Value = serde_yaml::from_str(yaml).unwrap();
+        let notes = migrate_legacy_units(&mut raw);
+        (raw, notes)
+    }
+
+    #[test]
+    fn legacy_threshold_unit_depends_on_magnitude() {
+        // 不超过 5.0 按比例 (×100)，否则按百分比原样保留
+        for (legacy, pct, how) in [
+            (0.8, 80.0, "read as a fraction"),
+            (5.0, 500.0, "read as a fraction"),
+            (5.01, 5.01, "read as a percentage"),
+            (80.0, 80.0, "read as a percentage"),
+        ] {
+            let (raw, notes) = migrated(&format!("threshold: {}", legacy));
+            assert_eq!(raw["threshold_pct"].as_f64(), Some(pct), "threshold: {}", legacy);
+            assert!(raw.get("threshold").is_none());
+            assert_eq!(notes.len(), 1);
+            assert!(notes[0].contains(how), "{}", notes[0]);
+            assert!(notes[0].contains(&format!("`threshold_pct: {}`", pct)), "{}", notes[0]);
+        }
+    }
+
+    #[test]
+    fn legacy_fraction_keys_are_scaled_to_percent() {
+        let (raw, notes) = migrated("histogram: {step: 0.1}\nvolatility: {fallback_volatility: 0.5}\n");
+        assert_eq!(raw["histogram"]["step_pct"].as_f64(), Some(10.0));
+        assert_eq!(raw["volatility"]["fallback_volatility_pct"].as_f64(), Some(50.0));
+        assert!(raw["histogram"].get("step").is_none());
+        assert_eq!(notes.len(), 2);
+
+        // 新键已存在时不做任何改写
+        let (raw, notes) = migrated("threshold_pct: 60.0\nhistogram: {step_pct: 10.0}\n");
+        assert_eq!(raw, serde_yaml::from_str::<Value>("threshold_pct: 60.0\nhistogram: {step_pct: 10.0}\n").unwrap());
+        assert!(notes.is_empty());
+    }
+
+    #[test]
+    fn legacy_key_is_ignored_when_new_key_is_set() {
+        let (raw, notes) = migrated(
+            "threshold: 0.8\nthreshold_pct: 60.0\nvolatility: {fallback_volatility: 0.5, fallback_volatility_pct: 40.0}\n",
+        );
+        assert_eq!(raw["threshold_pct"].as_f64(), Some(60.0));
+        assert_eq!(raw["volatility"]["fallback_volatility_pct"].as_f64(), Some(40.0));
+        assert!(raw.get("threshold").is_none());
+        assert!(raw["volatility"].get("fallback_volatility").is_none());
+        assert_eq!(notes, vec![
+            "`threshold` is ignored because `threshold_pct` is also set",
+            "`volatility.fallback_volatility` is ignored because `volatility.fallback_volatility_pct` is also set",
+        ]);
+    }
+
+    #[test]
+    fn legacy_config_loads_with_percent_fields() {
+        let yaml = include_str!("../config.example.yaml")
+            .replace("threshold_pct: 60.0", "threshold: 0.6")
+            .replace("step_pct: 10.0", "step: 0.1")
+            .replace("fallback_volatility_pct: 50.0", "fallback_volatility: 0.5");
+        let cfg = load_yaml_with_env("legacy", &yaml, &[]).unwrap();
+        assert_eq!(cfg.threshold_pct, Percent(60.0));
+        assert_eq!(cfg.histogram.step_pct, Percent(10.0));
+        assert_eq!(cfg.volatility.fallback_volatility_pct, Percent(50.0));
+
+        // 非数字的旧值不做换算，由反序列化报错
+        let yaml = include_str!("../config.example.yaml").replace("threshold_pct: 60.0", "threshold: high");
+        let err = load_yaml_with_env("legacy-invalid", &yaml, &[]).unwrap_err();
+        assert!(err.contains("invalid type: string \"high\""), "{}", err);
+    }
+
+    /// 把示例配置写入临时文件，再以给定的环境变量加载
+    fn load_example_with_env(name: &str, vars: &[(&str, &str)]) -> Result<MonitorConfig, String> {
+        load_yaml_with_env(name, include_str!("../config.example.yaml"), vars)
+    }
+
+    /// 把给定 YAML 写入临时文件，再以给定的环境变量加载
+    fn load_yaml_with_env(name: &str, yaml: &str, vars: &[(&str, &str)]) -> Result<MonitorConfig, String> {
+        let path = std::env::temp_dir().join(format!("bnvol-config-{}-{}.yaml", name, std::process::id()));
+        fs::write(&path, yaml).unwrap();
+        let vars = vars.iter().map(|(k, v)| (k.to_string(), v.to_string()));
+        let result = MonitorConfig::load_with_env(path.to_str().unwrap(), vars).map_err(|e| e.to_string());
+        fs::remove_file(&path).unwrap();
+        result
+    }
+
+    #[test]
+    fn offline_load_does_not_require_slack_webhook() {
+        let yaml = include_str!("../config.example.yaml")
+            .replace("slack_webhook_url: \"https://hooks.slack.com/services/YOUR/REAL/WEBHOOK\"", "slack_webhook_url: \"\"");
+        let path = std::env::temp_dir().join(format!("bnvol-config-offline-{}.yaml", std::process::id()));
+        fs::write(&path, yaml).unwrap();
+        let path = path.to_str().unwrap();
+
+        let err = MonitorConfig::load_with_env(path, Vec::new()).unwrap_err();
+        assert!(err.to_string().contains("slack_webhook_url"), "{}", err);
+        let cfg = MonitorConfig::load_with_env_and(path, Vec::new(), |c| c.slack_enabled = false).unwrap();
+        assert!(!cfg.slack_enabled);
+        assert!(MonitorConfig::load_offline(path).is_ok());
+        fs::remove_file(path).unwrap();
+    }
+
+    #[test]
+    fn env_override_replaces_file_value() {
+        let base = example();
+        assert_ne!(base.threshold_pct, Percent(80.0));
+        let cfg = load_example_with_env("file-value", &[
+            ("BNVOL_THRESHOLD_PCT", "80"),
+            ("BNVOL_CONFIG", "/somewhere/else.yaml"),
+            ("PATH", "/usr/bin"),
+        ]).unwrap();
+        assert_eq!(cfg.threshold_pct, Percent(80.0));
+        assert_eq!(cfg.histogram.buckets, base.histogram.buckets);
+    }
+
+    #[test]
+    fn env_override_nested_path() {
+        let cfg = load_example_with_env("nested", &[
+            ("BNVOL_VOLATILITY__WINDOW_SIZE", "50"),
+            ("BNVOL_TREND__SLOPE_THRESHOLD", "3.5"),
+            ("BNVOL_SYMBOL_OVERRIDES__ETHUSDT__TREND__IMPACT_QTY", "5"),
+        ]).unwrap();
+        assert_eq!(cfg.volatility.window_size, 50);
+        assert_eq!(cfg.trend.slope_threshold, 3.5);
+        assert_eq!(cfg.for_symbol("ethusdt").unwrap().trend.impact_qty, 5.0);
+        // 同一覆盖块中未被环境变量改动的字段保留
+        assert_eq!(cfg.for_symbol("ethusdt").unwrap().trend.slope_threshold, 0.2);
+    }
+
+    #[test]
+    fn env_override_list_value() {
+        let cfg = load_example_with_env("list", &[("BNVOL_SYMBOLS", r#"["BTCUSDT", "ethusdt", "solusdt"]"#)]).unwrap();
+        assert_eq!(cfg.symbols, vec!["btcusdt", "ethusdt", "solusdt"]);
+    }
+
+    #[test]
+    fn env_override_parses_scalars_as_yaml_and_keeps_other_strings() {
+        let mut raw = Value::Mapping(Default::default());
+        let vars = [
+            ("BNVOL_SLACK_ENABLED", "false"),
+            ("BNVOL_THRESHOLD_PCT", "72.5"),
+            ("BNVOL_SLACK_WEBHOOK_URL", "https://hooks.slack.com/a#b: c"),
+            ("BNVOL_TREND__MAX_HOLDING_SECS", ""),
+            ("BNVOL_TELEMETRY__BIND_ADDRESS", "localhost"),
+            ("BNVOL_SYMBOL_OVERRIDES__SOLUSDT", "{trend: {slope_threshold: 0.02}}"),
+        ];
+        let applied = apply_env_overrides(&mut raw, vars.iter().map(|(k, v)| (k.to_string(), v.to_string()))).unwrap();
+        assert_eq!(applied, vec![
+            "slack_enabled",
+            "slack_webhook_url",
+            "symbol_overrides.solusdt",
+            "telemetry.bind_address",
+            "threshold_pct",
+            "trend.max_holding_secs",
+        ]);
+        assert_eq!(raw["slack_enabled"], Value::Bool(false));
+        assert_eq!(raw["threshold_pct"].as_f64(), Some(72.5));
+        assert_eq!(raw["slack_webhook_url"], Value::String("https://hooks.slack.com/a#b: c".to_string()));
+        assert_eq!(raw["trend"]["max_holding_secs"], Value::Null);
+        assert_eq!(raw["telemetry"]["bind_address"], Value::String("localhost".to_string()));
+        assert_eq!(raw["symbol_overrides"]["solusdt"]["trend"]["slope_threshold"].as_f64(), Some(0.02));
+
+        let cfg = load_example_with_env("scalars", &[
+            ("BNVOL_SLACK_ENABLED", "false"),
+            ("BNVOL_SLACK_WEBHOOK_URL", "https://hooks.slack.com/a#b: c"),
+        ]).unwrap();
+        assert!(!cfg.slack_enabled);
+        assert_eq!(cfg.slack_webhook_url, "https://hooks.slack.com/a#b: c");
+    }
+
+    #[test]
+    fn empty_env_override_unsets_the_field() {
+        let yaml = include_str!("../config.example.yaml")
+            .replace("allowed_ips: []", "allowed_ips: [\"10.0.0.5\"]")
+            .replace("# max_holding_secs: 30.0", "max_holding_secs: 30.0");
+        let base = load_yaml_with_env("empty-base", &yaml, &[]).unwrap();
+        assert_eq!(base.telemetry.allowed_ips.len(), 1);
+        assert_eq!(base.trend.max_holding_secs, Some(30.0));
+        assert_eq!(base.for_symbol("ethusdt").unwrap().trend.impact_qty, 20.0);
+
+        let cfg = load_yaml_with_env("empty", &yaml, &[
+            ("BNVOL_TELEMETRY__ALLOWED_IPS", ""),
+            ("BNVOL_TREND__MAX_HOLDING_SECS", ""),
+            ("BNVOL_TERM_STRUCTURE__SPIKE_RATIO", " "),
+            ("BNVOL_SYMBOL_OVERRIDES__ETHUSDT__TREND__IMPACT_QTY", ""),
+        ]).unwrap();
+        assert!(cfg.telemetry.allowed_ips.is_empty());
+        assert_eq!(cfg.trend.max_holding_secs, None);
+        assert_eq!(cfg.term_structure.spike_ratio, None);
+        // 交易对覆盖中移除后沿用全局值
+        assert_eq!(cfg.for_symbol("ethusdt").unwrap().trend.impact_qty, cfg.trend.impact_qty);
+        assert_eq!(cfg.for_symbol("ethusdt").unwrap().trend.slope_threshold, 0.2);
+
+        // 没有默认值的字段不能取消
+        let err = load_yaml_with_env("empty-required", &yaml, &[("BNVOL_VOLATILITY__SPREAD_ADJUST", "")]).unwrap_err();
+        assert!(err.contains("missing field `spread_adjust`"), "{}", err);
+        // 拼错的字段名即使是空值也报错
+        let err = load_yaml_with_env("empty-typo", &yaml, &[("BNVOL_TREND__MAX_HOLDNG_SECS", "")]).unwrap_err();
+        assert!(err.contains("BNVOL_TREND__MAX_HOLDNG_SECS"), "{}", err);
+    }
+
+    #[test]
+    fn env_override_rejects_unknown_paths() {
+        for (var, unknown) in [
+            ("BNVOL_TREND__SLOPE_TRESHOLD", "trend.slope_treshold"),
+            ("BNVOL_THRESHOLD_PERCENT", "threshold_percent"),
+            ("BNVOL_NOT_A_SECTION__FIELD", "not_a_section"),
+        ] {
+            let err = load_example_with_env("unknown", &[(var, "1")]).unwrap_err();
+            assert!(err.contains(var) && err.contains(&format!("`{}`", unknown)), "{}: {}", var, err);
+        }
+    }
+
+    #[test]
+    fn env_override_rejects_invalid_paths_and_values() {
+        for (var, value) in [
+            ("BNVOL_TREND____SLOPE_THRESHOLD", "1"),
+            ("BNVOL_TREND__", "1"),
+            ("BNVOL_THRESHOLD_PCT__VALUE", "1"),
+            ("BNVOL_VOLATILITY__WINDOW_SIZE", "many"),
+            ("BNVOL_HISTOGRAM__BUCKETS", "0"),
+        ] {
+            assert!(load_example_with_env("invalid", &[(var, value)]).is_err(), "{}={} accepted", var, value);
+        }
+    }
+
+    #[test]
+    fn symbol_overrides_merge_over_global_config() {
+        let cfg = example();
+        let eth = cfg.for_symbol("ETHUSDT").unwrap();
+        assert_eq!(eth.trend.slope_threshold, 0.2);
+        assert_eq!(eth.trend.impact_qty, 20.0);
+        assert_eq!(eth.volatility.spread_adjust, 0.5);
+        // 未覆盖的字段沿用全局值
+        assert_eq!(eth.trend.fit_min_points, cfg.trend.fit_min_points);
+        assert_eq!(eth.volatility.window_size, cfg.volatility.window_size);
+
+        let btc = cfg.for_symbol("btcusdt").unwrap();
+        assert_eq!(btc.trend.slope_threshold, cfg.trend.slope_threshold);
+        assert_eq!(btc.trend.impact_qty, 1.0);
+    }
+}
@@ -0,0 +1,405 @@
+//! 信号引擎
+//!
+//! `SignalEngine` 是纯计算组件：输入 `BinanceEvent`，输出 `SignalOutput`。
+//! 它不持有网络连接、不发送 Slack，也不写 Telemetry，
+//! 因此可以用 `VecSource` / `ReplaySource` 完全确定性地驱动。
+
+use std::sync::Arc;
+
+use serde_yaml::Value;
+use tracing::warn;
+
+use crate::common::clock::{Clock, WallClock};
+use crate::common::units::Fraction;
+use crate::config::MonitorConfig;
+use crate::indicators::term_structure::TermPoint;
+use crate::indicators::vol::VolatilityResult;
+use crate::indicators::vol_regime::VolRegime;
+use crate::models::BinanceEvent;
+use crate::pipeline::SymbolPipeline;
+use crate::reload::{diff_configs, RESTART_ONLY_FIELDS};
+use crate::source::MarketDataSource;
+use crate::telemetry::{SymbolState, TelemetryPacket};
+
+/// 波动率状态切换报警：进入或离开 Elevated / Extreme (升档已经过冷却过滤)
+#[derive(Debug, Clone)]
+pub struct VolAlert {
+    pub symbol: String,
+    pub timestamp_ms: u64,           // 触发报警的成交时间
+    pub volatility: VolatilityResult,
+    pub price: f64,                  // 触发时的成交价
+    pub from: VolRegime,
+    pub to: VolRegime,
+    pub threshold: Fraction,         // 越过的阈值：升档为 `to` 的进入阈值，降档为 `from` 的退出阈值
+}
+
+/// 期限结构形态报警 (已经过冷却过滤)
+#[derive(Debug, Clone)]
+pub struct TermAlert {
+    pub symbol: String,
+    pub timestamp_ms: u64,           // 触发报警的成交时间
+    pub kind: TermAlertKind,
+    pub short_secs: f64,             // 比较使用的短周期 (秒)
+    pub long_secs: f64,              // 比较使用的长周期 (秒)
+    pub term: Vec<TermPoint>,        // 触发时的完整期限结构
+}
+
+/// 期限结构报警类型
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum TermAlertKind {
+    /// 短周期 / 长周期波动率之比超过 `spike_ratio`
+    Spike { ratio: f64 },
+    /// 短周期波动率相邻两次采样的对数变化超过 `vol_of_vol_jump`
+    VolOfVolJump { change: f64 },
+}
+
+/// 引擎输出
+#[derive(Debug, Clone)]
+pub enum SignalOutput {
+    /// 推送给报价端的价差调整
+    Telemetry(TelemetryPacket),
+    /// 波动率状态切换 Slack 报警
+    VolAlert(VolAlert),
+    /// 期限结构形态 Slack 报警
+    TermAlert(TermAlert),
+    /// 周期性波动率直方图报告 (已格式化为 Slack 文本)
+    HistogramReport { symbol: String, report: String },
+    /// 本地订单簿失步，需要数据源重新获取快照
+    ResyncRequired { symbol: String },
+}
+
+/// 多交易对信号引擎
+pub struct SignalEngine {
+    cfg: MonitorConfig,
+    clock: Arc<dyn Clock>,
+    pipelines: Vec<SymbolPipeline>,
+    symbol_cfgs: Vec<MonitorConfig>, // 与 pipelines 一一对应，已合并 `symbol_overrides`
+}
+
+/// 交易对实际使用的配置；覆盖无法合并时 (校验会提前拒绝) 退回全局配置
+fn symbol_config(cfg: &MonitorConfig, symbol: &str) -> MonitorConfig {
+    cfg.for_symbol(symbol).unwrap_or_else(|e| {
+        warn!("⚠️ [{}] symbol_overrides ignored: {}", symbol, e);
+        cfg.clone()
+    })
+}
+
+impl SignalEngine {
+    /// 按 `cfg.symbols` 为每个交易对创建一条管线 (使用系统时钟)
+    pub fn new(cfg: MonitorConfig) -> Self {
+        Self::new_with_clock(cfg, Arc::new(WallClock))
+    }
+
+    /// 使用指定时钟创建引擎；回放/回测应传入 `EventClock`
+    pub fn new_with_clock(cfg: MonitorConfig, clock: Arc<dyn Clock>) -> Self {
+        let symbol_cfgs: Vec<MonitorConfig> = cfg.symbols.iter().map(|s| symbol_config(&cfg, s)).collect();
+        let pipelines = cfg.symbols.iter()
+            .zip(&symbol_cfgs)
+            .map(|(s, symbol_cfg)| SymbolPipeline::new(s, symbol_cfg, clock.clone()))
+            .collect();
+        Self { cfg, clock, pipelines, symbol_cfgs }
+    }
+
+    pub fn config(&self) -> &MonitorConfig {
+        &self.cfg
+    }
+
+    pub fn pipelines(&self) -> &[SymbolPipeline] {
+        &self.pipelines
+    }
+
+    /// 热加载：在两个事件之间整体替换配置，返回变更列表 (见 `reload::diff_configs`)
+    ///
+    /// `reload::RESTART_ONLY_FIELDS` 中的字段保留当前值，变更时记录警告。
+    pub fn apply_config(&mut self, new: MonitorConfig) -> Vec<String> {
+        let (new, _ignored) = self.keep_restart_only_fields(new);
+        let changes = diff_configs(&self.cfg, &new);
+        if changes.is_empty() {
+            return changes;
+        }
+        let symbol_cfgs: Vec<MonitorConfig> = self.cfg.symbols.iter().map(|s| symbol_config(&new, s)).collect();
+        for ((pipeline, old), symbol_cfg) in self.pipelines.iter_mut().zip(&self.symbol_cfgs).zip(&symbol_cfgs) {
+            pipeline.apply_config(old, symbol_cfg);
+        }
+        self.cfg = new;
+        self.symbol_cfgs = symbol_cfgs;
+        changes
+    }
+
+    /// 将 `RESTART_ONLY_FIELDS` 恢复为当前值，返回处理后的配置与被忽略的字段
+    fn keep_restart_only_fields(&self, new: MonitorConfig) -> (MonitorConfig, Vec<&'static str>) {
+        let (Ok(Value::Mapping(old_map)), Ok(Value::Mapping(mut new_map))) =
+            (serde_yaml::to_value(&self.cfg), serde_yaml::to_value(&new))
+        else {
+            return (new, Vec::new());
+        };
+
+        let mut ignored = Vec::new();
+        for field in RESTART_ONLY_FIELDS {
+            let old_value = old_map.get(field).cloned().unwrap_or(Value::Null);
+            if new_map.get(field) != Some(&old_value) {
+                ignored.push(field);
+                new_map.insert(Value::from(field), old_value);
+            }
+        }
+        if ignored.is_empty() {
+            return (new, ignored);
+        }
+        warn!("⚠️ Config reload: {} changed but requires a restart; keeping current values.", ignored.join(", "));
+        (serde_yaml::from_value(Value::Mapping(new_map)).unwrap_or(new), ignored)
+    }
+
+    /// 指定交易对的当前信号状态 (忽略大小写)，未配置时返回 None
+    pub fn state(&self, symbol: &str) -> Option<SymbolState> {
+        self.pipelines.iter()
+            .zip(&self.symbol_cfgs)
+            .find(|(p, _)| p.matches(symbol))
+            .map(|(p, cfg)| p.state(cfg))
+    }
+
+    /// 新连接/新回放开始时调用，重置各管线的订单簿与趋势状态
+    pub fn reset_session(&mut self) {
+        for (pipeline, cfg) in self.pipelines.iter_mut().zip(&self.symbol_cfgs) {
+            pipeline.reset_session(cfg);
+        }
+    }
+
+    /// 处理一个事件，返回本次产生的全部输出
+    ///
+    /// 未配置的交易对事件会被忽略。
+    pub fn on_event(&mut self, event: &BinanceEvent) -> Result<Vec<SignalOutput>, Box<dyn std::error::Error>> {
+        let mut out = Vec::new();
+        self.clock.observe(event.timestamp_ms());
+
+        for (pipeline, cfg) in self.pipelines.iter_mut().zip(&self.symbol_cfgs) {
+            pipeline.maybe_report_histogram(cfg, &mut out);
+        }
+
+        let Some((pipeline, cfg)) = self.pipelines.iter_mut()
+            .zip(&self.symbol_cfgs)
+            .find(|(p, _)| p.matches(event.symbol()))
+        else {
+            return Ok(out);
+        };
+
+        match event {
+            BinanceEvent::Trade(trade) => pipeline.on_trade(trade, cfg, &mut out)?,
+            BinanceEvent::Depth(depth) => pipeline.on_depth(depth, cfg, &mut out),
+            BinanceEvent::Snapshot(snapshot) => pipeline.on_snapshot(snapshot),
+        }
+        Ok(out)
+    }
+
+    /// 将数据源跑到结束，收集全部输出 (用于回放与测试)
+    pub async fn run_to_end<S: MarketDataSource>(
+        &mut self,
+        source: &mut S,
+    ) -> Result<Vec<SignalOutput>, Box<dyn std::error::Error>> {
+        let mut outputs = Vec::new();
+        while let Some(event) = source.next_event().await? {
+            outputs.extend(self.on_event(&event)?);
+        }
+        Ok(outputs)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::common::clock::EventClock;
+    use crate::config::DepthMode;
+    use crate::indicators::vol_regime::VolRegime;
+    use crate::models::DepthSnapshot;
+    use crate::source::VecSource;
+    use crate::telemetry::Transition;
+
+    const T0: u64 = 1_700_000_000_000;
+
+    fn trade(agg_id: u64, ts: u64, price: f64, buyer_maker: bool) -> BinanceEvent {
+        BinanceEvent::from_frame(&format!(
+            r#"{{"e":"aggTrade","s":"BTCUSDT","a":{},"T":{},"p":"{:.2}","q":"0.5","m":{}}}"#,
+            agg_id, ts, price, buyer_maker,
+        )).unwrap()
+    }
+
+    fn depth(update_id: u64, ts: u64, mid: f64, bid_qty: f64) -> BinanceEvent {
+        BinanceEvent::from_frame(&format!(
+            r#"{{"e":"depthUpdate","s":"BTCUSDT","T":{},"U":{},"u":{},"pu":{},"b":[["{:.2}","{}"]],"a":[["{:.2}","1.0"]]}}"#,
+            ts, update_id, update_id, update_id - 1, mid - 0.05, bid_qty, mid + 0.05,
+        )).unwrap()
+    }
+
+    fn test_config() -> MonitorConfig {
+        let mut cfg: MonitorConfig = serde_yaml::from_str(include_str!("../config.example.yaml")).unwrap();
+        cfg.symbols = vec!["btcusdt".to_string()];
+        cfg.symbol_overrides.clear();
+        cfg.order_book.mode = DepthMode::Partial;
+        cfg
+    }
+
+    /// 8 秒匀速上涨 (10 USDT/s，买盘持续加厚) 之后 2 秒急跌 400 USDT
+    fn rally_then_crash() -> Vec<BinanceEvent> {
+        let mut events = Vec::new();
+        for i in 0..100u64 {
+            let ts = T0 + i * 100;
+            let (price, bid_qty, buyer_maker) = if i < 80 {
+                (50_000.0 + i as f64, 1.0 + i as f64, false)
+            } else {
+                (50_080.0 - 20.0 * (i - 79) as f64, 80.0, true)
+            };
+            events.push(depth(i + 1, ts, price, bid_qty));
+            events.push(trade(i + 1, ts + 50, price, buyer_maker));
+        }
+        events
+    }
+
+    fn transitions(outputs: &[SignalOutput]) -> Vec<Transition> {
+        outputs.iter()
+            .filter_map(|o| match o {
+                SignalOutput::Telemetry(p) => p.event,
+                _ => None,
+            })
+            .collect()
+    }
+
+    #[tokio::test]
+    async fn scripted_session_enters_and_exits_trend() {
+        let mut engine = SignalEngine::new_with_clock(test_config(), Arc::new(EventClock::new(0)));
+        let outputs = engine.run_to_end(&mut VecSource::new(rally_then_crash())).await.unwrap();
+
+        let events = transitions(&outputs);
+        let entry = events.iter().position(|e| *e == Transition::EnterLong).expect("no trend entry");
+        let exit = events.iter().position(|e| *e == Transition::ExitPriceFallback).expect("no trend exit");
+        assert!(entry < exit);
+        assert!(events[exit..].contains(&Transition::CooldownStart));
+
+        let entry_packet = outputs.iter()
+            .find_map(|o| match o {
+                SignalOutput::Telemetry(p) if p.event == Some(Transition::EnterLong) => Some(p),
+                _ => None,
+            })
+            .unwrap();
+        assert_eq!(entry_packet.source, "U");
+    }
+
+    #[tokio::test]
+    async fn scripted_crash_raises_volatility_alert() {
+        let mut engine = SignalEngine::new_with_clock(test_config(), Arc::new(EventClock::new(0)));
+        let outputs = engine.run_to_end(&mut VecSource::new(rally_then_crash())).await.unwrap();
+
+        let alerts: Vec<&VolAlert> = outputs.iter()
+            .filter_map(|o| match o {
+                SignalOutput::VolAlert(a) => Some(a),
+                _ => None,
+            })
+            .collect();
+        assert_eq!(alerts.len(), 1);
+        assert_eq!(alerts[0].symbol, "btcusdt");
+        assert_eq!(alerts[0].to, VolRegime::Elevated);
+        assert!(alerts[0].timestamp_ms >= T0 + 8_000);
+
+        assert!(outputs.iter().any(|o| matches!(o, SignalOutput::Telemetry(p) if p.source == "V")));
+    }
+
+    #[tokio::test]
+    async fn regime_holds_while_volatility_is_stale() {
+        let mut engine = SignalEngine::new_with_clock(test_config(), Arc::new(EventClock::new(0)));
+        engine.run_to_end(&mut VecSource::new(rally_then_crash())).await.unwrap();
+        let before = engine.state("btcusdt").unwrap().regime;
+        assert!(before >= VolRegime::Elevated);
+
+        // 断流 60 秒后的第一笔成交：窗口已清空，波动率为 fallback (50%)，不应据此降档
+        let resume = T0 + 70_000;
+        let outputs = engine.on_event(&trade(1_000, resume, 49_700.0, false)).unwrap();
+        assert!(transitions(&outputs).is_empty(), "{:?}", transitions(&outputs));
+        assert_eq!(engine.state("btcusdt").unwrap().regime, before);
+        assert!(outputs.iter().any(|o| matches!(o, SignalOutput::Telemetry(p) if p.source == "V")));
+
+        // 窗口重新就绪后按真实波动率降档
+        let mut events = Vec::new();
+        for i in 1..=30u64 {
+            events.push(trade(1_000 + i, resume + i * 100, 49_700.0 + (i % 2) as f64 * 0.1, i % 2 == 0));
+        }
+        let outputs = engine.run_to_end(&mut VecSource::new(events)).await.unwrap();
+        assert_eq!(transitions(&outputs), vec![Transition::RegimeCalm]);
+        assert_eq!(engine.state("btcusdt").unwrap().regime, VolRegime::Calm);
+    }
+
+    #[tokio::test]
+    async fn diff_sequence_gap_requests_resync() {
+        let mut cfg = test_config();
+        cfg.order_book.mode = DepthMode::Diff;
+        let diff = |first: u64, last: u64, prev: u64| BinanceEvent::from_frame(&format!(
+            r#"{{"e":"depthUpdate","s":"BTCUSDT","T":{},"U":{},"u":{},"pu":{},"b":[["50000.00","2.0"]],"a":[]}}"#,
+            T0 + last, first, last, prev,
+        )).unwrap();
+        let snapshot = BinanceEvent::Snapshot(DepthSnapshot {
+            symbol: "BTCUSDT".to_string(),
+            trans_time: T0,
+            last_update_id: 100,
+            bids: vec![("50000.00".to_string(), "1.0".to_string())],
+            asks: vec![("50000.10".to_string(), "1.0".to_string())],
+        });
+        let resyncs = |outputs: &[SignalOutput]| outputs.iter()
+            .filter(|o| matches!(o, SignalOutput::ResyncRequired { symbol } if symbol == "btcusdt"))
+            .count();
+
+        let mut engine = SignalEngine::new_with_clock(cfg, Arc::new(EventClock::new(0)));
+        let events = vec![diff(90, 95, 89), snapshot, diff(96, 100, 95), diff(99, 104, 98), diff(105, 110, 104)];
+        let outputs = engine.run_to_end(&mut VecSource::new(events)).await.unwrap();
+        assert_eq!(resyncs(&outputs), 0);
+
+        let outputs = engine.on_event(&diff(115, 120, 112)).unwrap();
+        assert_eq!(resyncs(&outputs), 1);
+        // 失步后等待新快照，后续增量不再请求重新同步
+        let outputs = engine.on_event(&diff(121, 125, 120)).unwrap();
+        assert_eq!(resyncs(&outputs), 0);
+    }
+
+    #[tokio::test]
+    async fn replay_is_deterministic() {
+        let run = || async {
+            let mut engine = SignalEngine::new_with_clock(test_config(), Arc::new(EventClock::new(0)));
+            let outputs = engine.run_to_end(&mut VecSource::new(rally_then_crash())).await.unwrap();
+            outputs.iter()
+                .filter_map(|o| match o {
+                    SignalOutput::Telemetry(p) => Some((p.timestamp, p.source.clone(), p.event, p.ask_adjust.to_bits(), p.bid_adjust.to_bits())),
+                    _ => None,
+                })
+                .collect::<Vec<_>>()
+        };
+        assert_eq!(run().await, run().await);
+    }
+
+    #[test]
+    fn reload_keeps_restart_only_fields_and_applies_hot_fields() {
+        let mut engine = SignalEngine::new_with_clock(test_config(), Arc::new(EventClock::new(T0)));
+        let old_bind = engine.config().telemetry.bind_address.clone();
+
+        let mut new = test_config();
+        new.symbols.push("ethusdt".to_string());
+        new.telemetry.bind_address = "0.0.0.0".to_string();
+        new.trend.cooldown_secs = 7.0;
+
+        let (kept, ignored) = engine.keep_restart_only_fields(new.clone());
+        assert_eq!(ignored, vec!["symbols", "telemetry"]);
+        assert_eq!(kept.symbols, vec!["btcusdt"]);
+        assert_eq!(kept.telemetry.bind_address, old_bind);
+        assert_eq!(kept.trend.cooldown_secs, 7.0);
+
+        // 变更列表只包含热生效的字段
+        let changes = engine.apply_config(new);
+        assert_eq!(changes, vec!["trend.cooldown_secs: 1.0 → 7.0"]);
+        assert_eq!(engine.config().symbols, vec!["btcusdt"]);
+        assert_eq!(engine.config().telemetry.bind_address, old_bind);
+        assert_eq!(engine.config().trend.cooldown_secs, 7.0);
+        assert_eq!(engine.pipelines().len(), 1);
+        assert_eq!(engine.symbol_cfgs[0].trend.cooldown_secs, 7.0);
+
+        // 只改了重启字段时没有有效变更
+        let mut restart_only = engine.config().clone();
+        restart_only.order_book.snapshot_limit = 500;
+        assert!(engine.apply_config(restart_only).is_empty());
+        assert_eq!(engine.config().order_book.snapshot_limit, test_config().order_book.snapshot_limit);
+    }
+}
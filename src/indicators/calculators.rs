@@ -178,6 +178,19 @@ impl DepthCalculator {
         }
     }
 
+    /// 清空 OFI 状态 (数据断档后调用)
+    ///
+    /// 丢弃上一次订单簿、OFI 累积缓冲区与成交缓冲区，
+    /// 下一次深度更新只保存状态而不计算 OFI，避免把断档当作真实的挂单变化。
+    /// `last_update_id` 保留，继续丢弃乱序的旧数据。
+    pub fn reset_ofi(&mut self) {
+        self.prev_bids.clear();
+        self.prev_asks.clear();
+        self.ofi_buffer.clear();
+        self.trade_buffer.clear();
+        self.last_depth_ts_ms = 0;
+    }
+
     /// 添加成交数据（用于后续 OFI 计算）
     pub fn add_trade(&mut self, timestamp_ms: u64, price: f64, qty: f64, is_buyer_maker: bool) {
         self.trade_buffer.push_back((timestamp_ms, price, qty, is_buyer_maker));
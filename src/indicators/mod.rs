@@ -1,11 +1,17 @@
 //! 指标计算模块
 //!
 //! - `vol`: 瞬时波动率计算
+//! - `term_structure`: 多周期波动率期限结构
+//! - `vol_regime`: 波动率状态分类 (带滞回)
 //! - `calculators`: VWAP、OFI、价格拟合
 //! - `trend_state`: 趋势状态机
+//! - `order_book`: 完整深度本地订单簿 (快照 + 增量)
 //! - `base`: 基础指标 trait
 
 pub mod base;
 pub mod vol;
+pub mod term_structure;
+pub mod vol_regime;
 pub mod calculators;
-pub mod trend_state;
\ No newline at end of file
+pub mod trend_state;
+pub mod order_book;
\ No newline at end of file
@@ -0,0 +1,249 @@
+//! 本地订单簿
+//!
+//! 基于 REST 快照 + `@depth@100ms` 增量维护完整深度，替代只有 20 档的 depth20 快照。
+//!
+//! # 同步流程 (币安 U 本位合约)
+//! 1. 订阅增量流，获取 REST 快照 (`lastUpdateId`)
+//! 2. 丢弃 `u <= lastUpdateId` 的增量 (其变更已包含在快照中)
+//! 3. 第一条处理的增量需跨越 `lastUpdateId + 1`，即 `U <= lastUpdateId + 1 <= u`
+//! 4. 之后每条增量的 `pu` 必须等于上一条的 `u`，否则视为断档，需要重新获取快照
+//!
+//! 价格以 1e-8 为单位转为整数作为键，避免浮点比较问题，且适用于任意 tick size。
+
+use std::collections::BTreeMap;
+
+/// 一组 (价格, 数量) 档位
+pub type Levels = Vec<(f64, f64)>;
+
+/// 价格 -> 整数键的缩放系数
+const PRICE_SCALE: f64 = 1e8;
+
+fn price_key(price: f64) -> u64 {
+    (price * PRICE_SCALE).round() as u64
+}
+
+fn key_price(key: u64) -> f64 {
+    key as f64 / PRICE_SCALE
+}
+
+/// 增量应用结果
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum DiffOutcome {
+    /// 已应用，订单簿处于同步状态
+    Applied,
+    /// 早于快照的旧增量，已丢弃
+    Stale,
+    /// 尚未同步 (等待快照)，已丢弃
+    NotSynced,
+    /// 序号不连续：`expected` 为期望的 `pu` (首条增量为 `U` 的上限)，`got` 为实际的 `pu`/`U`
+    Gap { expected: u64, got: u64 },
+}
+
+/// 订单簿同步状态
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum SyncState {
+    /// 没有可用快照
+    AwaitingSnapshot,
+    /// 已加载快照，等待第一条跨越 `lastUpdateId + 1` 的增量
+    AwaitingFirstDiff,
+    /// 同步中
+    Synced,
+}
+
+/// 完整深度订单簿
+pub struct OrderBook {
+    bids: BTreeMap<u64, f64>,   // price key -> qty
+    asks: BTreeMap<u64, f64>,
+    last_update_id: u64,        // 快照的 lastUpdateId 或最后应用增量的 u
+    state: SyncState,
+}
+
+impl Default for OrderBook {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl OrderBook {
+    pub fn new() -> Self {
+        Self {
+            bids: BTreeMap::new(),
+            asks: BTreeMap::new(),
+            last_update_id: 0,
+            state: SyncState::AwaitingSnapshot,
+        }
+    }
+
+    /// 用 REST 快照重建订单簿
+    pub fn apply_snapshot(&mut self, last_update_id: u64, bids: &[(f64, f64)], asks: &[(f64, f64)]) {
+        self.bids = bids.iter().filter(|(_, q)| *q > 0.0).map(|(p, q)| (price_key(*p), *q)).collect();
+        self.asks = asks.iter().filter(|(_, q)| *q > 0.0).map(|(p, q)| (price_key(*p), *q)).collect();
+        self.last_update_id = last_update_id;
+        self.state = SyncState::AwaitingFirstDiff;
+    }
+
+    /// 应用一条增量
+    ///
+    /// # 参数
+    /// - `first_id`: `U`
+    /// - `final_id`: `u`
+    /// - `prev_final_id`: `pu`
+    pub fn apply_diff(
+        &mut self,
+        first_id: u64,
+        final_id: u64,
+        prev_final_id: u64,
+        bids: &[(f64, f64)],
+        asks: &[(f64, f64)],
+    ) -> DiffOutcome {
+        match self.state {
+            SyncState::AwaitingSnapshot => return DiffOutcome::NotSynced,
+            SyncState::AwaitingFirstDiff => {
+                if final_id <= self.last_update_id {
+                    return DiffOutcome::Stale;
+                }
+                if first_id > self.last_update_id + 1 {
+                    // 快照与第一条增量之间有缺口
+                    let expected = self.last_update_id + 1;
+                    self.invalidate();
+                    return DiffOutcome::Gap { expected, got: first_id };
+                }
+            }
+            SyncState::Synced => {
+                if final_id <= self.last_update_id {
+                    return DiffOutcome::Stale;
+                }
+                if prev_final_id != self.last_update_id {
+                    let expected = self.last_update_id;
+                    self.invalidate();
+                    return DiffOutcome::Gap { expected, got: prev_final_id };
+                }
+            }
+        }
+
+        Self::apply_levels(&mut self.bids, bids);
+        Self::apply_levels(&mut self.asks, asks);
+        self.last_update_id = final_id;
+        self.state = SyncState::Synced;
+        DiffOutcome::Applied
+    }
+
+    fn apply_levels(book: &mut BTreeMap<u64, f64>, levels: &[(f64, f64)]) {
+        for (p, q) in levels {
+            let key = price_key(*p);
+            if *q <= 0.0 {
+                book.remove(&key);
+            } else {
+                book.insert(key, *q);
+            }
+        }
+    }
+
+    /// 标记为失步，等待新快照
+    pub fn invalidate(&mut self) {
+        self.state = SyncState::AwaitingSnapshot;
+    }
+
+    pub fn is_synced(&self) -> bool {
+        self.state == SyncState::Synced
+    }
+
+    /// 是否需要 (重新) 获取快照
+    pub fn needs_snapshot(&self) -> bool {
+        self.state == SyncState::AwaitingSnapshot
+    }
+
+    pub fn last_update_id(&self) -> u64 {
+        self.last_update_id
+    }
+
+    pub fn best_bid(&self) -> Option<(f64, f64)> {
+        self.bids.iter().next_back().map(|(k, q)| (key_price(*k), *q))
+    }
+
+    pub fn best_ask(&self) -> Option<(f64, f64)> {
+        self.asks.iter().next().map(|(k, q)| (key_price(*k), *q))
+    }
+
+    pub fn mid_price(&self) -> Option<f64> {
+        Some((self.best_bid()?.0 + self.best_ask()?.0) / 2.0)
+    }
+
+    /// 买单档位，价格从高到低
+    pub fn bids(&self) -> impl Iterator<Item = (f64, f64)> + '_ {
+        self.bids.iter().rev().map(|(k, q)| (key_price(*k), *q))
+    }
+
+    /// 卖单档位，价格从低到高
+    pub fn asks(&self) -> impl Iterator<Item = (f64, f64)> + '_ {
+        self.asks.iter().map(|(k, q)| (key_price(*k), *q))
+    }
+
+    /// 全部深度 (bids 降序, asks 升序)
+    pub fn levels(&self) -> (Levels, Levels) {
+        (self.bids().collect(), self.asks().collect())
+    }
+
+    pub fn depth(&self) -> (usize, usize) {
+        (self.bids.len(), self.asks.len())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn synced_book(last_update_id: u64) -> OrderBook {
+        let mut book = OrderBook::new();
+        book.apply_snapshot(last_update_id, &[(100.0, 1.0), (99.9, 2.0)], &[(100.1, 1.5), (100.2, 3.0)]);
+        book
+    }
+
+    #[test]
+    fn diff_before_snapshot_is_not_synced() {
+        let mut book = OrderBook::new();
+        assert_eq!(book.apply_diff(1, 2, 0, &[(100.0, 1.0)], &[]), DiffOutcome::NotSynced);
+        assert!(book.needs_snapshot());
+    }
+
+    #[test]
+    fn diff_covered_by_snapshot_is_stale() {
+        let mut book = synced_book(100);
+        assert_eq!(book.apply_diff(90, 99, 89, &[(100.0, 9.0)], &[]), DiffOutcome::Stale);
+        assert_eq!(book.apply_diff(95, 100, 94, &[(100.0, 9.0)], &[]), DiffOutcome::Stale);
+        assert!(!book.is_synced());
+        assert_eq!(book.best_bid(), Some((100.0, 1.0)));
+    }
+
+    #[test]
+    fn first_diff_must_straddle_snapshot_id() {
+        let mut book = synced_book(100);
+        assert_eq!(book.apply_diff(98, 103, 97, &[(100.0, 5.0)], &[(100.1, 0.0)]), DiffOutcome::Applied);
+        assert!(book.is_synced());
+        assert_eq!(book.last_update_id(), 103);
+        assert_eq!(book.best_bid(), Some((100.0, 5.0)));
+        assert_eq!(book.best_ask(), Some((100.2, 3.0)));
+
+        let mut book = synced_book(100);
+        assert_eq!(book.apply_diff(101, 101, 100, &[], &[]), DiffOutcome::Applied);
+
+        let mut book = synced_book(100);
+        assert_eq!(book.apply_diff(102, 105, 101, &[], &[]), DiffOutcome::Gap { expected: 101, got: 102 });
+        assert!(book.needs_snapshot());
+    }
+
+    #[test]
+    fn pu_gap_invalidates_book() {
+        let mut book = synced_book(100);
+        assert_eq!(book.apply_diff(99, 105, 98, &[], &[]), DiffOutcome::Applied);
+        assert_eq!(book.apply_diff(106, 110, 105, &[], &[]), DiffOutcome::Applied);
+        assert_eq!(book.apply_diff(106, 110, 105, &[], &[]), DiffOutcome::Stale);
+        assert_eq!(book.apply_diff(115, 120, 112, &[], &[]), DiffOutcome::Gap { expected: 110, got: 112 });
+        assert!(book.needs_snapshot());
+        assert_eq!(book.apply_diff(121, 125, 120, &[], &[]), DiffOutcome::NotSynced);
+
+        book.apply_snapshot(122, &[(100.0, 1.0)], &[(100.1, 1.0)]);
+        assert_eq!(book.apply_diff(121, 125, 120, &[], &[]), DiffOutcome::Applied);
+        assert!(book.is_synced());
+    }
+}
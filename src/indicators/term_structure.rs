@@ -0,0 +1,340 @@
+//! 多周期波动率期限结构
+//!
+//! 对同一交易对同时维护多个时间周期 (例如 1s / 10s / 1m / 5m / 1h) 的已实现波动率。
+//!
+//! # 算法原理 (增量累加)
+//! 逐笔对数收益率的平方 r_i² 按成交时间累加到 `bucket_ms` 宽的桶中；
+//! 每个周期维护自己覆盖的桶的 Σr_i² 与笔数，新桶加入时累加，桶滑出周期时扣除，
+//! 单次更新的均摊复杂度为 O(周期数)。只保留最长周期所需的桶，
+//! 因此 1 小时周期在 100ms 桶宽下最多 36000 个桶，与成交频率无关。
+//!
+//! 年化: vol = sqrt(Σr_i² * seconds_in_year / 覆盖时长)，覆盖时长为周期长度；自第一笔成交起
+//! 还不足一个周期时为实际经过的时长。长时间无成交后不会因最早的桶离最新成交很近而放大。
+//! 周期内成交不足 `min_samples` 笔时为 None。
+
+use std::collections::VecDeque;
+
+use serde::{Deserialize, Serialize};
+
+use super::vol::annualize;
+use crate::common::units::Fraction;
+
+/// 单个周期的已实现波动率
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct TermPoint {
+    pub horizon_secs: f64,
+    pub vol: Option<Fraction>,  // 年化波动率，样本不足时为 None
+    pub samples: usize,         // 周期内的收益率笔数
+}
+
+/// 时间桶
+struct Bucket {
+    index: u64,     // timestamp_ms / bucket_ms
+    sum_sq: f64,    // 桶内 Σr_i²
+    count: usize,
+}
+
+/// 单个周期的累加状态
+struct Horizon {
+    window_ms: u64,
+    sum_sq: f64,
+    count: usize,
+    start: u64,     // 周期内最早的桶的序号 (全局递增)
+}
+
+pub struct TermStructure {
+    bucket_ms: u64,
+    min_samples: usize,
+    buckets: VecDeque<Bucket>,
+    first_seq: u64,                 // buckets[0] 的序号
+    horizons: Vec<Horizon>,         // 按配置顺序
+    last_ln_price: Option<f64>,
+    first_ts_ms: Option<u64>,
+    latest_ts_ms: u64,
+}
+
+impl TermStructure {
+    /// # 参数
+    /// - `horizons_secs`: 各周期长度 (秒)
+    /// - `bucket_ms`: 时间桶宽度 (毫秒)，决定短周期的边界精度
+    /// - `min_samples`: 周期内最少收益率笔数
+    pub fn new(horizons_secs: &[f64], bucket_ms: u64, min_samples: usize) -> Self {
+        Self {
+            bucket_ms: bucket_ms.max(1),
+            min_samples,
+            buckets: VecDeque::new(),
+            first_seq: 0,
+            horizons: horizons_secs
+                .iter()
+                .map(|&secs| Horizon {
+                    window_ms: (secs * 1000.0).round() as u64,
+                    sum_sq: 0.0,
+                    count: 0,
+                    start: 0,
+                })
+                .collect(),
+            last_ln_price: None,
+            first_ts_ms: None,
+            latest_ts_ms: 0,
+        }
+    }
+
+    /// 添加一笔成交
+    pub fn update(&mut self, price: f64, trade_time_ms: u64) {
+        let ln_price = price.ln();
+        self.first_ts_ms.get_or_insert(trade_time_ms);
+        // 乱序成交归入当前桶，避免桶序号倒退
+        self.latest_ts_ms = self.latest_ts_ms.max(trade_time_ms);
+
+        if let Some(prev) = self.last_ln_price.replace(ln_price) {
+            let r2 = (ln_price - prev).powi(2);
+            let index = self.latest_ts_ms / self.bucket_ms;
+            match self.buckets.back_mut() {
+                Some(bucket) if bucket.index == index => {
+                    bucket.sum_sq += r2;
+                    bucket.count += 1;
+                }
+                _ => self.buckets.push_back(Bucket { index, sum_sq: r2, count: 1 }),
+            }
+            // 新收益率总是位于所有周期之内
+            for h in self.horizons.iter_mut() {
+                h.sum_sq += r2;
+                h.count += 1;
+            }
+        }
+
+        self.expire();
+    }
+
+    /// 扣除滑出各周期的桶，并丢弃所有周期都不再需要的桶
+    fn expire(&mut self) {
+        let end_seq = self.first_seq + self.buckets.len() as u64;
+        for h in self.horizons.iter_mut() {
+            let cutoff = self.latest_ts_ms.saturating_sub(h.window_ms) / self.bucket_ms;
+            while h.start < end_seq {
+                let bucket = &self.buckets[(h.start - self.first_seq) as usize];
+                if bucket.index >= cutoff {
+                    break;
+                }
+                h.sum_sq -= bucket.sum_sq;
+                h.count -= bucket.count;
+                h.start += 1;
+            }
+            if h.count == 0 {
+                h.sum_sq = 0.0;  // 消除浮点累积误差
+            }
+        }
+
+        let keep_from = self.horizons.iter().map(|h| h.start).min().unwrap_or(end_seq);
+        while self.first_seq < keep_from {
+            self.buckets.pop_front();
+            self.first_seq += 1;
+        }
+    }
+
+    /// 各周期的当前波动率，顺序与构造时的 `horizons_secs` 一致
+    pub fn points(&self) -> Vec<TermPoint> {
+        self.horizons
+            .iter()
+            .map(|h| {
+                let vol = match self.first_ts_ms {
+                    Some(first_ts) if h.count >= self.min_samples.max(1) => {
+                        let span_ms = h.window_ms.min(self.latest_ts_ms.saturating_sub(first_ts));
+                        Some(annualize(h.sum_sq, span_ms as f64 / 1000.0))
+                    }
+                    _ => None,
+                };
+                TermPoint { horizon_secs: h.window_ms as f64 / 1000.0, vol, samples: h.count }
+            })
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// 逐笔保存 (桶序号, r²)，每次查询时重新求和的参考实现
+    struct BruteForce {
+        bucket_ms: u64,
+        min_samples: usize,
+        windows_ms: Vec<u64>,
+        returns: Vec<(u64, f64)>,
+        last_ln_price: Option<f64>,
+        first_ts_ms: Option<u64>,
+        latest_ts_ms: u64,
+    }
+
+    impl BruteForce {
+        fn new(horizons_secs: &[f64], bucket_ms: u64, min_samples: usize) -> Self {
+            Self {
+                bucket_ms,
+                min_samples,
+                windows_ms: horizons_secs.iter().map(|s| (s * 1000.0).round() as u64).collect(),
+                returns: Vec::new(),
+                last_ln_price: None,
+                first_ts_ms: None,
+                latest_ts_ms: 0,
+            }
+        }
+
+        fn update(&mut self, price: f64, ts: u64) {
+            self.first_ts_ms.get_or_insert(ts);
+            self.latest_ts_ms = self.latest_ts_ms.max(ts);
+            if let Some(prev) = self.last_ln_price.replace(price.ln()) {
+                // 乱序成交计入最新成交所在的桶
+                self.returns.push((self.latest_ts_ms / self.bucket_ms, (price.ln() - prev).powi(2)));
+            }
+        }
+
+        fn points(&self) -> Vec<(usize, Option<f64>)> {
+            self.windows_ms
+                .iter()
+                .map(|&window_ms| {
+                    let cutoff = self.latest_ts_ms.saturating_sub(window_ms) / self.bucket_ms;
+                    let inside: Vec<f64> = self.returns.iter().filter(|(b, _)| *b >= cutoff).map(|(_, r2)| *r2).collect();
+                    let vol = (inside.len() >= self.min_samples.max(1)).then(|| {
+                        let span_ms = window_ms.min(self.latest_ts_ms - self.first_ts_ms.unwrap());
+                        annualize(inside.iter().sum(), span_ms as f64 / 1000.0).0
+                    });
+                    (inside.len(), vol)
+                })
+                .collect()
+        }
+    }
+
+    fn assert_matches(term: &TermStructure, reference: &BruteForce, context: &str) {
+        let expected = reference.points();
+        let got = term.points();
+        assert_eq!(got.len(), expected.len());
+        for (p, (samples, vol)) in got.iter().zip(expected) {
+            assert_eq!(p.samples, samples, "{} horizon {}s", context, p.horizon_secs);
+            match (p.vol, vol) {
+                (Some(a), Some(b)) => {
+                    assert!((a.0 - b).abs() <= b * 1e-9 + 1e-12, "{} horizon {}s: {} vs {}", context, p.horizon_secs, a.0, b)
+                }
+                (None, None) => {}
+                (a, b) => panic!("{} horizon {}s: {:?} vs {:?}", context, p.horizon_secs, a, b),
+            }
+        }
+    }
+
+    fn assert_close(got: Option<Fraction>, expected: Fraction) {
+        let got = got.expect("vol").0;
+        assert!((got - expected.0).abs() <= expected.0 * 1e-12, "got {}, expected {}", got, expected.0);
+    }
+
+    /// 线性同余伪随机数，保证测试可复现
+    struct Lcg(u64);
+
+    impl Lcg {
+        fn next(&mut self, n: u64) -> u64 {
+            self.0 = self.0.wrapping_mul(6364136223846793005).wrapping_add(1442695040888963407);
+            (self.0 >> 33) % n
+        }
+    }
+
+    #[test]
+    fn incremental_sums_match_brute_force() {
+        let horizons = [1.0, 10.0, 60.0];
+        let mut term = TermStructure::new(&horizons, 100, 5);
+        let mut reference = BruteForce::new(&horizons, 100, 5);
+        let mut rng = Lcg(7);
+        let (mut ts, mut price) = (1_700_000_000_000u64, 50_000.0);
+
+        for i in 0..5_000 {
+            ts = match rng.next(100) {
+                0 => ts + 5_000 + rng.next(90_000),     // 长时间无成交
+                1..=4 => ts.saturating_sub(rng.next(500)), // 乱序
+                _ => ts + rng.next(250),
+            };
+            price *= 1.0 + (rng.next(2001) as f64 - 1000.0) * 1e-6;
+            term.update(price, ts);
+            reference.update(price, ts);
+            assert_matches(&term, &reference, &format!("trade #{}", i));
+        }
+        // 只保留最长周期所需的桶
+        assert!(term.buckets.len() <= 60_000 / 100 + 2, "{} buckets", term.buckets.len());
+    }
+
+    #[test]
+    fn hand_computed_points() {
+        let mut term = TermStructure::new(&[1.0, 10.0], 100, 1);
+        for (ts, price) in [(0, 100.0), (500, 101.0), (1_500, 100.0), (1_550, 102.0)] {
+            term.update(price, ts);
+        }
+        let r2 = |a: f64, b: f64| (b / a).ln().powi(2);
+        let points = term.points();
+
+        // 1s 周期: 截止桶 (1550 - 1000) / 100 = 5，包含 500ms 所在的桶 5
+        assert_eq!(points[0].samples, 3);
+        let sum = r2(100.0, 101.0) + r2(101.0, 100.0) + r2(100.0, 102.0);
+        assert_close(points[0].vol, annualize(sum, 1.0));
+        // 10s 周期: 自第一笔成交仅经过 1.55s
+        assert_eq!(points[1].samples, 3);
+        assert_close(points[1].vol, annualize(sum, 1.55));
+    }
+
+    #[test]
+    fn quiet_gap_expires_every_bucket() {
+        let horizons = [1.0, 10.0, 60.0];
+        let mut term = TermStructure::new(&horizons, 100, 2);
+        let mut reference = BruteForce::new(&horizons, 100, 2);
+        for i in 0..20u64 {
+            let price = 100.0 + (i % 2) as f64 * 0.1;
+            term.update(price, i * 100);
+            reference.update(price, i * 100);
+        }
+        assert!(term.points().iter().all(|p| p.vol.is_some()));
+
+        // 2 小时后的一笔：之前的桶全部滑出，只剩跨越空档的这一笔收益率
+        term.update(100.2, 7_200_000);
+        reference.update(100.2, 7_200_000);
+        assert_eq!(term.buckets.len(), 1);
+        assert!(term.points().iter().all(|p| p.samples == 1 && p.vol.is_none()));
+        assert_matches(&term, &reference, "after gap");
+
+        // 恢复成交后按周期长度年化，而不是按最早的桶到最新成交的几百毫秒
+        term.update(100.1, 7_200_300);
+        reference.update(100.1, 7_200_300);
+        assert_matches(&term, &reference, "after recovery");
+        let sum = 2.0 * (100.2f64 / 100.1).ln().powi(2);
+        assert_close(term.points()[2].vol, annualize(sum, 60.0));
+    }
+
+    #[test]
+    fn min_samples_gates_each_horizon() {
+        let mut term = TermStructure::new(&[1.0, 10.0], 100, 5);
+        for i in 0..5u64 {
+            term.update(100.0 + i as f64, i * 100);
+        }
+        // 5 笔成交只有 4 个收益率
+        assert!(term.points().iter().all(|p| p.samples == 4 && p.vol.is_none()));
+
+        term.update(99.0, 500);
+        assert!(term.points().iter().all(|p| p.samples == 5 && p.vol.is_some()));
+
+        // 1s 周期滑出 0.1s、0.2s 的桶后重新低于 min_samples
+        term.update(99.5, 1_350);
+        let points = term.points();
+        assert_eq!((points[0].samples, points[0].vol), (4, None));
+        assert_eq!(points[1].samples, 6);
+        assert!(points[1].vol.is_some());
+    }
+
+    #[test]
+    fn out_of_order_trade_is_counted_in_the_latest_bucket() {
+        let mut term = TermStructure::new(&[1.0, 10.0], 100, 1);
+        let mut reference = BruteForce::new(&[1.0, 10.0], 100, 1);
+        for (ts, price) in [(1_000, 100.0), (2_500, 101.0), (1_200, 100.5), (3_400, 100.0)] {
+            term.update(price, ts);
+            reference.update(price, ts);
+            assert_matches(&term, &reference, &format!("t={}", ts));
+        }
+        assert_eq!(term.latest_ts_ms, 3_400);
+        // 1.2s 的乱序成交计入 2.5s 的桶，在 3.4s 时仍位于 1s 周期内 (截止桶 24)
+        let points = term.points();
+        assert_eq!(points[0].samples, 3);
+        assert_eq!(points[1].samples, 3);
+    }
+}
@@ -4,10 +4,13 @@
 
 use std::collections::VecDeque;
 
+use serde::{Deserialize, Serialize};
+
 use super::calculators::FitResult;
+use crate::config::TrendConfig;
 
 /// 趋势方向
-#[derive(Debug, Clone, Copy, PartialEq)]
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
 pub enum TrendDirection {
     Long = 1,    // 看涨
     Short = -1,  // 看跌
@@ -15,13 +18,35 @@ pub enum TrendDirection {
 }
 
 /// 策略状态
-#[derive(Debug, Clone, Copy, PartialEq)]
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
 pub enum StrategyState {
     Cooldown = -1, // 冷却期（退出后等待）
     Scanning = 0,  // 扫描中（寻找入场信号）
     Holding = 1,   // 持仓中（监控退出条件）
 }
 
+/// 状态机转换事件，由 `update()` 返回
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub enum TrendEvent {
+    /// 进入持仓
+    Entered { direction: TrendDirection, slope: f64, ofi: f64 },
+    /// 退出持仓并进入冷却期
+    /// - `pnl_estimate`: 按方向计算的价格变动 ($)，以入场/退出时的 VWAP 估算
+    /// - `held_secs`: 持仓时长 (秒)
+    Exited { reason: ExitReason, pnl_estimate: f64, held_secs: f64 },
+    /// 冷却期结束，恢复扫描
+    CooldownEnded,
+}
+
+/// 退出原因
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub enum ExitReason {
+    PriceFallback, // 价格相对入场拟合线回落超过阈值
+    SlopeReversal, // 斜率持续走弱
+    MaxHolding,    // 持仓超过 max_holding_secs
+    TrailingStop,  // 自最有利价格回撤超过 trailing_stop
+}
+
 pub struct TrendStateMachine {
     state: StrategyState,
     direction: TrendDirection,
@@ -30,36 +55,17 @@ pub struct TrendStateMachine {
     entry_slope: f64,
     entry_intercept: f64,
     entry_ts_sec: f64,
+    entry_price: f64,       // 入场时的最新 VWAP，用于估算盈亏
+    peak_price: f64,        // 入场以来最有利的价格 (多头最高 / 空头最低)
     
     // 冷却期
     cooldown_start_ts: f64,
-    cooldown_secs: f64,
-    
-    // 阈值参数
-    slope_threshold: f64,
-    ofi_confirm_threshold: f64,
-    
-    // 退出参数
-    slope_threshold_ratio: f64,
-    min_price_fallback: f64,
-    max_price_fallback: f64,
-    entry_protection_secs: f64,
-    
+
     // 斜率历史（用于斜率反转退出）
     slope_history: VecDeque<f64>,
-    slope_weak_threshold: f64,
-}
 
-#[derive(Debug, Clone)]
-pub struct TrendConfig {
-    pub slope_threshold: f64,
-    pub ofi_confirm_threshold: f64,
-    pub cooldown_secs: f64,
-    pub slope_threshold_ratio: f64,
-    pub min_price_fallback: f64,
-    pub max_price_fallback: f64,
-    pub entry_protection_secs: f64,
-    pub slope_weak_threshold: f64,
+    // 阈值与退出参数
+    config: TrendConfig,
 }
 
 impl TrendStateMachine {
@@ -70,22 +76,18 @@ impl TrendStateMachine {
             entry_slope: 0.0,
             entry_intercept: 0.0,
             entry_ts_sec: 0.0,
+            entry_price: 0.0,
+            peak_price: 0.0,
             cooldown_start_ts: 0.0,
-            cooldown_secs: config.cooldown_secs,
-            slope_threshold: config.slope_threshold,
-            ofi_confirm_threshold: config.ofi_confirm_threshold,
-            slope_threshold_ratio: config.slope_threshold_ratio,
-            min_price_fallback: config.min_price_fallback,
-            max_price_fallback: config.max_price_fallback,
-            entry_protection_secs: config.entry_protection_secs,
-            slope_history: VecDeque::with_capacity(10),
-            slope_weak_threshold: config.slope_weak_threshold,
+            slope_history: VecDeque::with_capacity(config.slope_history_len),
+            config,
         }
     }
 
     /// 更新状态机
     /// 
     /// 根据拟合结果和 OFI 更新趋势方向。
+    /// 发生状态转换时返回对应的 `TrendEvent`；
     /// 调用者通过 `get_direction()` 获取当前趋势。
     pub fn update(
         &mut self,
@@ -93,50 +95,73 @@ impl TrendStateMachine {
         fit_5s: Option<&FitResult>,
         cum_ofi: f64,
         latest_price: f64,
-    ) {
+    ) -> Option<TrendEvent> {
         match self.state {
             StrategyState::Cooldown => {
                 // 冷却期结束后恢复扫描
-                if current_ts_sec - self.cooldown_start_ts >= self.cooldown_secs {
+                if current_ts_sec - self.cooldown_start_ts >= self.config.cooldown_secs {
                     self.state = StrategyState::Scanning;
+                    return Some(TrendEvent::CooldownEnded);
                 }
+                None
             }
 
             StrategyState::Scanning => {
                 let fit = match fit_5s {
                     Some(f) if f.is_valid => f,
-                    _ => return,
+                    _ => return None,
                 };
 
                 // 多头信号: slope > threshold && ofi > confirm_threshold
-                if fit.slope > self.slope_threshold && cum_ofi > self.ofi_confirm_threshold {
-                    self.enter_position(TrendDirection::Long, fit, current_ts_sec);
+                if fit.slope > self.config.slope_threshold && cum_ofi > self.config.ofi_confirm_threshold {
+                    Some(self.enter_position(TrendDirection::Long, fit, current_ts_sec, cum_ofi, latest_price))
                 }
                 // 空头信号: slope < -threshold && ofi < -confirm_threshold
-                else if fit.slope < -self.slope_threshold && cum_ofi < -self.ofi_confirm_threshold {
-                    self.enter_position(TrendDirection::Short, fit, current_ts_sec);
+                else if fit.slope < -self.config.slope_threshold && cum_ofi < -self.config.ofi_confirm_threshold {
+                    Some(self.enter_position(TrendDirection::Short, fit, current_ts_sec, cum_ofi, latest_price))
+                } else {
+                    None
                 }
             }
 
             StrategyState::Holding => {
-                let fit = match fit_5s {
-                    Some(f) => f,
-                    None => return,
+                let time_elapsed = current_ts_sec - self.entry_ts_sec;
+
+                // 最有利价格
+                self.peak_price = match self.direction {
+                    TrendDirection::Long => self.peak_price.max(latest_price),
+                    TrendDirection::Short => self.peak_price.min(latest_price),
+                    TrendDirection::Neutral => self.peak_price,
                 };
 
+                // 持仓超时退出 (不依赖拟合结果)
+                if let Some(max_secs) = self.config.max_holding_secs
+                    && time_elapsed >= max_secs
+                {
+                    return Some(self.exit_position(current_ts_sec, latest_price, ExitReason::MaxHolding));
+                }
+
+                // 移动止损退出（入场保护期后）
+                if let Some(stop) = self.config.trailing_stop
+                    && time_elapsed >= self.config.entry_protection_secs
+                    && (self.peak_price - latest_price).abs() >= stop
+                {
+                    return Some(self.exit_position(current_ts_sec, latest_price, ExitReason::TrailingStop));
+                }
+
+                let fit = fit_5s?;
+
                 // 记录斜率历史
                 self.slope_history.push_back(fit.slope);
-                if self.slope_history.len() > 10 {
+                while self.slope_history.len() > self.config.slope_history_len {
                     self.slope_history.pop_front();
                 }
 
-                let time_elapsed = current_ts_sec - self.entry_ts_sec;
-                
                 // 检查退出条件（入场保护期后）
-                if time_elapsed >= self.entry_protection_secs {
+                if time_elapsed >= self.config.entry_protection_secs {
                     let fitted_price = self.entry_intercept + self.entry_slope * time_elapsed;
-                    let raw_threshold = (1.0 - self.slope_threshold_ratio) * self.entry_slope.abs() * time_elapsed;
-                    let threshold = raw_threshold.clamp(self.min_price_fallback, self.max_price_fallback);
+                    let raw_threshold = (1.0 - self.config.slope_threshold_ratio) * self.entry_slope.abs() * time_elapsed;
+                    let threshold = raw_threshold.clamp(self.config.min_price_fallback, self.config.max_price_fallback);
 
                     let should_exit = match self.direction {
                         TrendDirection::Long => latest_price < fitted_price - threshold,
@@ -145,41 +170,64 @@ impl TrendStateMachine {
                     };
 
                     if should_exit {
-                        self.exit_position(current_ts_sec);
-                        return;
+                        return Some(self.exit_position(current_ts_sec, latest_price, ExitReason::PriceFallback));
                     }
                 }
 
                 // 斜率反转退出
-                if time_elapsed >= 5.0 && self.slope_history.len() >= 10 {
+                if time_elapsed >= self.config.slope_reversal_min_secs && self.slope_history.len() >= self.config.slope_history_len {
                     let weak_count = match self.direction {
-                        TrendDirection::Long => self.slope_history.iter().filter(|&&s| s < self.slope_weak_threshold).count(),
-                        TrendDirection::Short => self.slope_history.iter().filter(|&&s| s > -self.slope_weak_threshold).count(),
+                        TrendDirection::Long => self.slope_history.iter().filter(|&&s| s < self.config.slope_weak_threshold).count(),
+                        TrendDirection::Short => self.slope_history.iter().filter(|&&s| s > -self.config.slope_weak_threshold).count(),
                         TrendDirection::Neutral => 0,
                     };
 
-                    if weak_count > 5 {
-                        self.exit_position(current_ts_sec);
+                    if weak_count > self.config.slope_reversal_weak_count {
+                        return Some(self.exit_position(current_ts_sec, latest_price, ExitReason::SlopeReversal));
                     }
                 }
+                None
             }
         }
     }
 
-    fn enter_position(&mut self, direction: TrendDirection, fit: &FitResult, ts_sec: f64) {
+    fn enter_position(
+        &mut self,
+        direction: TrendDirection,
+        fit: &FitResult,
+        ts_sec: f64,
+        cum_ofi: f64,
+        price: f64,
+    ) -> TrendEvent {
         self.state = StrategyState::Holding;
         self.direction = direction;
         self.entry_slope = fit.slope;
         self.entry_intercept = fit.current_price;
         self.entry_ts_sec = ts_sec;
+        self.entry_price = price;
+        self.peak_price = price;
         self.slope_history.clear();
+        TrendEvent::Entered { direction, slope: fit.slope, ofi: cum_ofi }
     }
 
-    fn exit_position(&mut self, ts_sec: f64) {
+    fn exit_position(&mut self, ts_sec: f64, price: f64, reason: ExitReason) -> TrendEvent {
+        let pnl_estimate = match self.direction {
+            TrendDirection::Long => price - self.entry_price,
+            TrendDirection::Short => self.entry_price - price,
+            TrendDirection::Neutral => 0.0,
+        };
+        let held_secs = ts_sec - self.entry_ts_sec;
+
         self.state = StrategyState::Cooldown;
         self.cooldown_start_ts = ts_sec;
         self.direction = TrendDirection::Neutral;
         self.slope_history.clear();
+        TrendEvent::Exited { reason, pnl_estimate, held_secs }
+    }
+
+    /// 替换参数 (热加载)，保留当前状态与持仓；新阈值从下一次 `update()` 起生效
+    pub fn set_config(&mut self, config: TrendConfig) {
+        self.config = config;
     }
 
     pub fn get_state(&self) -> StrategyState {
@@ -190,7 +238,217 @@ impl TrendStateMachine {
         self.direction
     }
 
+    /// 入场时的拟合斜率 (仅持仓中有意义)
+    pub fn entry_slope(&self) -> f64 {
+        self.entry_slope
+    }
+
     pub fn is_holding(&self) -> bool {
         self.state == StrategyState::Holding
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::MonitorConfig;
+
+    fn config() -> TrendConfig {
+        let cfg: MonitorConfig = serde_yaml::from_str(include_str!("../../config.example.yaml"))
+            .expect("config.example.yaml parses");
+        cfg.trend
+    }
+
+    /// 当前价格恰好落在拟合线上的有效拟合
+    fn fit(slope: f64, price: f64) -> FitResult {
+        FitResult { slope, intercept: price, r_squared: 0.95, is_valid: true, current_price: price }
+    }
+
+    /// 在 t=0、价格 100 入场
+    fn entered(config: TrendConfig, direction: TrendDirection) -> TrendStateMachine {
+        let mut sm = TrendStateMachine::new(config);
+        let (slope, ofi) = match direction {
+            TrendDirection::Short => (-5.0, -2.0),
+            _ => (5.0, 2.0),
+        };
+        assert!(matches!(sm.update(0.0, Some(&fit(slope, 100.0)), ofi, 100.0), Some(TrendEvent::Entered { .. })));
+        sm
+    }
+
+    fn exit_reason(event: Option<TrendEvent>) -> Option<ExitReason> {
+        match event {
+            Some(TrendEvent::Exited { reason, .. }) => Some(reason),
+            _ => None,
+        }
+    }
+
+    #[test]
+    fn enters_only_with_a_valid_fit_and_confirming_ofi() {
+        let mut sm = TrendStateMachine::new(config());
+        let mut invalid = fit(5.0, 100.0);
+        invalid.is_valid = false;
+        assert_eq!(sm.update(0.0, Some(&invalid), 2.0, 100.0), None);
+        assert_eq!(sm.update(0.1, None, 2.0, 100.0), None);
+        // OFI 方向不一致
+        assert_eq!(sm.update(0.2, Some(&fit(5.0, 100.0)), -2.0, 100.0), None);
+        assert_eq!(sm.get_state(), StrategyState::Scanning);
+
+        assert_eq!(
+            sm.update(0.3, Some(&fit(5.0, 100.0)), 2.0, 100.0),
+            Some(TrendEvent::Entered { direction: TrendDirection::Long, slope: 5.0, ofi: 2.0 }),
+        );
+        assert!(sm.is_holding());
+        assert_eq!(sm.get_direction(), TrendDirection::Long);
+        assert_eq!(sm.entry_slope(), 5.0);
+    }
+
+    #[test]
+    fn short_round_trip_emits_entered_exited_and_cooldown_ended() {
+        let mut trend = config();
+        trend.max_holding_secs = Some(3.0);
+        trend.cooldown_secs = 1.0;
+        let mut sm = TrendStateMachine::new(trend);
+
+        assert_eq!(
+            sm.update(10.0, Some(&fit(-5.0, 100.0)), -2.0, 100.0),
+            Some(TrendEvent::Entered { direction: TrendDirection::Short, slope: -5.0, ofi: -2.0 }),
+        );
+        assert_eq!(sm.update(11.0, None, -2.0, 98.0), None);
+        // 空头在价格下跌时盈利：pnl = 入场价 - 退出价
+        assert_eq!(
+            sm.update(13.0, None, -2.0, 97.0),
+            Some(TrendEvent::Exited { reason: ExitReason::MaxHolding, pnl_estimate: 3.0, held_secs: 3.0 }),
+        );
+        assert_eq!(sm.get_state(), StrategyState::Cooldown);
+        assert_eq!(sm.get_direction(), TrendDirection::Neutral);
+
+        assert_eq!(sm.update(13.5, Some(&fit(-5.0, 97.0)), -2.0, 97.0), None);
+        assert_eq!(sm.update(14.0, None, 0.0, 97.0), Some(TrendEvent::CooldownEnded));
+        assert_eq!(sm.get_state(), StrategyState::Scanning);
+    }
+
+    #[test]
+    fn short_loss_has_negative_pnl() {
+        let mut trend = config();
+        trend.max_holding_secs = Some(2.0);
+        let mut sm = entered(trend, TrendDirection::Short);
+        assert_eq!(
+            sm.update(2.5, None, 0.0, 104.0),
+            Some(TrendEvent::Exited { reason: ExitReason::MaxHolding, pnl_estimate: -4.0, held_secs: 2.5 }),
+        );
+    }
+
+    #[test]
+    fn max_holding_fires_at_the_configured_time() {
+        let mut trend = config();
+        trend.max_holding_secs = Some(3.0);
+        let mut sm = entered(trend, TrendDirection::Long);
+        assert_eq!(sm.update(2.999, None, 0.0, 100.0), None);
+        assert_eq!(exit_reason(sm.update(3.0, None, 0.0, 100.0)), Some(ExitReason::MaxHolding));
+    }
+
+    #[test]
+    fn trailing_stop_for_long_measures_drawdown_from_the_high() {
+        let mut trend = config();
+        trend.trailing_stop = Some(2.0);
+        trend.entry_protection_secs = 1.0;
+        let mut sm = entered(trend, TrendDirection::Long);
+
+        assert_eq!(sm.update(0.5, None, 0.0, 105.0), None);
+        // 保护期内回撤不触发
+        assert_eq!(sm.update(0.9, None, 0.0, 102.5), None);
+        assert_eq!(sm.update(1.0, None, 0.0, 103.5), None);
+        assert_eq!(
+            sm.update(1.1, None, 0.0, 103.0),
+            Some(TrendEvent::Exited { reason: ExitReason::TrailingStop, pnl_estimate: 3.0, held_secs: 1.1 }),
+        );
+    }
+
+    #[test]
+    fn trailing_stop_for_short_measures_bounce_from_the_low() {
+        let mut trend = config();
+        trend.trailing_stop = Some(2.0);
+        trend.entry_protection_secs = 1.0;
+        let mut sm = entered(trend, TrendDirection::Short);
+
+        assert_eq!(sm.update(1.0, None, 0.0, 95.0), None);
+        // 继续下跌刷新最低价，而不是被当作回撤
+        assert_eq!(sm.update(1.1, None, 0.0, 94.0), None);
+        assert_eq!(sm.update(1.2, None, 0.0, 95.5), None);
+        assert_eq!(
+            sm.update(1.3, None, 0.0, 96.0),
+            Some(TrendEvent::Exited { reason: ExitReason::TrailingStop, pnl_estimate: 4.0, held_secs: 1.3 }),
+        );
+    }
+
+    #[test]
+    fn max_holding_and_trailing_stop_are_off_when_unset() {
+        let mut trend = config();
+        trend.max_holding_secs = None;
+        trend.trailing_stop = None;
+        let mut long = entered(trend.clone(), TrendDirection::Long);
+        let mut short = entered(trend, TrendDirection::Short);
+
+        // 没有拟合结果时只有这两种退出可能触发
+        for (ts, price) in [(1.0, 150.0), (60.0, 50.0), (3600.0, 150.0)] {
+            assert_eq!(long.update(ts, None, 0.0, price), None);
+            assert_eq!(short.update(ts, None, 0.0, 200.0 - price), None);
+        }
+        assert!(long.is_holding());
+        assert!(short.is_holding());
+    }
+
+    #[test]
+    fn slope_reversal_uses_configured_history_and_weak_count() {
+        let mut trend = config();
+        trend.slope_history_len = 4;
+        trend.slope_reversal_min_secs = 2.0;
+        trend.slope_reversal_weak_count = 2;
+        let mut sm = entered(trend, TrendDirection::Long);
+
+        // 价格沿入场拟合线 (100 + 5t) 运行，不触发价格回落退出
+        let mut step = |ts: f64, slope: f64| sm.update(ts, Some(&fit(slope, 100.0 + 5.0 * ts)), 0.0, 100.0 + 5.0 * ts);
+        let weak = 0.1;
+        let strong = 5.0;
+        assert_eq!(step(1.0, weak), None);
+        assert_eq!(step(1.5, strong), None);
+        assert_eq!(step(2.0, weak), None);
+        // 4 个样本中 2 个走弱，未超过 weak_count
+        assert_eq!(step(2.5, strong), None);
+        assert_eq!(step(3.0, weak), None);
+        // 最近 4 个样本 [weak, strong, weak, weak]：3 > 2
+        assert_eq!(exit_reason(step(3.5, weak)), Some(ExitReason::SlopeReversal));
+    }
+
+    #[test]
+    fn slope_reversal_waits_for_min_secs() {
+        let mut trend = config();
+        trend.slope_history_len = 3;
+        trend.slope_reversal_min_secs = 4.0;
+        trend.slope_reversal_weak_count = 1;
+        let mut sm = entered(trend, TrendDirection::Short);
+
+        let mut step = |ts: f64| sm.update(ts, Some(&fit(-0.1, 100.0 - 5.0 * ts)), 0.0, 100.0 - 5.0 * ts);
+        for ts in [1.0, 2.0, 3.0, 3.9] {
+            assert_eq!(step(ts), None, "t={}", ts);
+        }
+        assert_eq!(exit_reason(step(4.0)), Some(ExitReason::SlopeReversal));
+    }
+
+    #[test]
+    fn set_config_applies_to_an_open_position() {
+        let mut trend = config();
+        trend.max_holding_secs = None;
+        let mut sm = entered(trend.clone(), TrendDirection::Long);
+        assert_eq!(sm.update(5.0, None, 0.0, 101.0), None);
+
+        trend.max_holding_secs = Some(4.0);
+        sm.set_config(trend);
+        // 持仓与入场时间保留，新阈值从下一次 update 生效
+        assert!(sm.is_holding());
+        assert_eq!(
+            sm.update(5.1, None, 0.0, 102.0),
+            Some(TrendEvent::Exited { reason: ExitReason::MaxHolding, pnl_estimate: 2.0, held_secs: 5.1 }),
+        );
+    }
+}
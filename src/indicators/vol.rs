@@ -1,89 +1,622 @@
 //! 瞬时波动率计算器
 //!
 //! 基于对数收益率的 RMS (均方根) 计算瞬时波动率，并年化。
-//! 
+//!
 //! # 算法原理
 //! 1. 对每笔成交价格取自然对数: ln(price)
 //! 2. 计算相邻对数价格的差值 (对数收益率): r_i = ln(p_i) - ln(p_{i-1})
 //! 3. 计算 RMS: raw_vol = sqrt(Σr_i² / n)
 //! 4. 年化: annualized = raw_vol * sqrt(seconds_in_year / dt)
+//!
+//! # 窗口模式 (`VolWindow`)
+//! - `Count`: 最近 N 笔成交。成交密集时窗口只有几十毫秒，稀疏时长达数秒，
+//!   年化系数随 dt 大幅波动
+//! - `Time`: 最近 T 秒内的成交，样本数不足 `min_samples` 时视为不可用。
+//!   该模式下 `rms` 按已实现方差 Σr_i² / dt 年化，结果与成交频率无关，报警阈值含义稳定
+//!
+//! 两种模式都增量维护 Σr_i²，`rms` 与原始 RMS 的计算为 O(1)。
+//!
+//! # 估计器
+//! 逐笔 RMS 在 BTC 永续上主要反映买卖价差来回跳动 (bid-ask bounce)。
+//! `VolatilityEstimator` 在同一个成交窗口上提供其他估计方法：
+//!
+//! | 配置名         | 方法                                             |
+//! |----------------|--------------------------------------------------|
+//! | `rms`          | 逐笔对数收益率 RMS (默认，即上文算法)            |
+//! | `ewma`         | 按时间衰减的指数加权方差率                       |
+//! | `bar_rv`       | 固定时间 K 线收盘价的已实现波动率                |
+//! | `parkinson`    | K 线高低价区间 (Parkinson)                       |
+//! | `garman_klass` | K 线 OHLC (Garman-Klass)                         |
+//! | `bipower`      | K 线收益率的双幂次变差，对跳跃稳健               |
+//! | `tsrv`         | 逐笔双尺度已实现方差 (TSRV)，对微观结构噪声稳健  |
+//!
+//! `volatility.estimator` 选择驱动报警与直方图的估计器，
+//! `volatility.report_estimators` 中的估计器同时计算，结果并列放在 `VolatilityResult::estimates`。
 
 use std::collections::VecDeque;
-use std::time::{SystemTime, UNIX_EPOCH};
+use std::f64::consts::{LN_2, PI};
+use std::sync::Arc;
+
+use serde::{Deserialize, Serialize};
+use tracing::warn;
+
+use crate::common::clock::{Clock, WallClock};
+use crate::common::units::Fraction;
+use crate::config::VolatilityConfig;
+
+/// 一年的秒数，用于年化
+pub const SECONDS_IN_YEAR: f64 = 31536000.0; // 365 * 24 * 3600
 
 /// 价格数据点，存储对数价格和时间戳
-struct PriceData {
-    ln_price: f64,      // 价格的自然对数
-    timestamp_ms: u64,  // 成交时间戳 (毫秒)
+#[derive(Debug, Clone, Copy)]
+pub struct PriceData {
+    pub ln_price: f64,      // 价格的自然对数
+    pub timestamp_ms: u64,  // 成交时间戳 (毫秒)
+}
+
+/// 波动率窗口
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum VolWindow {
+    /// 最近 N 笔成交
+    Count(usize),
+    /// 最近 `window_ms` 毫秒内的成交 (按成交时间)，至少 `min_samples` 笔才计算
+    Time { window_ms: u64, min_samples: usize },
+}
+
+/// 窗口配置模式 (配置中使用小写名称)
+#[derive(Debug, Clone, Copy, Default, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum WindowMode {
+    #[default]
+    Count,
+    Time,
+}
+
+/// 增量维护平方和时，每移除这么多个点重新完整求和一次，消除浮点累积误差
+const RESUM_INTERVAL: usize = 4096;
+
+/// 成交窗口：按时间升序的价格点，并增量维护相邻对数收益率的平方和
+#[derive(Debug, Default)]
+pub struct PriceWindow {
+    prices: VecDeque<PriceData>,    // 价格缓冲区 (VecDeque 支持高效的头尾操作)
+    sum_sq_returns: f64,            // Σ r_i²
+    removals: usize,                // 自上次完整求和以来移除的点数
+}
+
+impl PriceWindow {
+    pub fn with_capacity(capacity: usize) -> Self {
+        Self { prices: VecDeque::with_capacity(capacity), ..Default::default() }
+    }
+
+    pub fn push(&mut self, point: PriceData) {
+        if let Some(last) = self.prices.back() {
+            self.sum_sq_returns += (point.ln_price - last.ln_price).powi(2);
+        }
+        self.prices.push_back(point);
+    }
+
+    pub fn pop_front(&mut self) -> Option<PriceData> {
+        let front = self.prices.pop_front()?;
+        if let Some(next) = self.prices.front() {
+            self.sum_sq_returns -= (next.ln_price - front.ln_price).powi(2);
+        }
+        self.removals += 1;
+        if self.prices.len() < 2 {
+            self.sum_sq_returns = 0.0;
+            self.removals = 0;
+        } else if self.removals >= RESUM_INTERVAL {
+            self.sum_sq_returns = self.returns().map(|r| r * r).sum();
+            self.removals = 0;
+        }
+        Some(front)
+    }
+
+    pub fn len(&self) -> usize {
+        self.prices.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.prices.is_empty()
+    }
+
+    pub fn front(&self) -> Option<&PriceData> {
+        self.prices.front()
+    }
+
+    pub fn back(&self) -> Option<&PriceData> {
+        self.prices.back()
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = &PriceData> + '_ {
+        self.prices.iter()
+    }
+
+    /// 相邻对数收益率 r_i = ln(p_i) - ln(p_{i-1})
+    pub fn returns(&self) -> impl Iterator<Item = f64> + '_ {
+        self.prices.iter().zip(self.prices.iter().skip(1)).map(|(a, b)| b.ln_price - a.ln_price)
+    }
+
+    /// Σ r_i² (增量维护)
+    pub fn sum_sq_returns(&self) -> f64 {
+        self.sum_sq_returns.max(0.0)
+    }
+
+    /// 首尾成交的时间跨度 (秒)
+    pub fn span_secs(&self) -> f64 {
+        match (self.prices.front(), self.prices.back()) {
+            (Some(first), Some(last)) => last.timestamp_ms.saturating_sub(first.timestamp_ms) as f64 / 1000.0,
+            _ => 0.0,
+        }
+    }
 }
 
 /// 波动率计算结果
-#[derive(Debug, Clone, Copy)]
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
 pub struct VolatilityResult {
-    pub annualized: f64,   // 年化波动率 (1.0 = 100%)
+    pub annualized: Fraction, // 年化波动率 (1.0 = 100%)，由 `estimator` 计算
     pub raw_vol: f64,      // 原始 RMS 波动率
     pub dt_secs: f64,      // 数据窗口时长 (秒)
     pub duration_ms: u64,  // 数据窗口时长 (毫秒)
     pub is_stale: bool,    // 数据是否过期 (市场中断)
+    #[serde(default)]
+    pub estimator: EstimatorKind,        // 计算 `annualized` 的估计器
+    #[serde(default)]
+    pub estimates: VolatilityEstimates,  // 各估计器的年化波动率 (并列对比)
+}
+
+/// 估计器类型 (配置中使用 snake_case 名称)
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum EstimatorKind {
+    #[default]
+    Rms,
+    Ewma,
+    BarRv,
+    Parkinson,
+    GarmanKlass,
+    Bipower,
+    Tsrv,
+}
+
+impl EstimatorKind {
+    pub const ALL: [EstimatorKind; 7] = [
+        EstimatorKind::Rms,
+        EstimatorKind::Ewma,
+        EstimatorKind::BarRv,
+        EstimatorKind::Parkinson,
+        EstimatorKind::GarmanKlass,
+        EstimatorKind::Bipower,
+        EstimatorKind::Tsrv,
+    ];
+
+    pub fn name(self) -> &'static str {
+        match self {
+            EstimatorKind::Rms => "rms",
+            EstimatorKind::Ewma => "ewma",
+            EstimatorKind::BarRv => "bar_rv",
+            EstimatorKind::Parkinson => "parkinson",
+            EstimatorKind::GarmanKlass => "garman_klass",
+            EstimatorKind::Bipower => "bipower",
+            EstimatorKind::Tsrv => "tsrv",
+        }
+    }
+
+    /// 基于固定时间 K 线的估计器：需要时间窗口，且窗口内至少容纳 `MIN_BARS_PER_WINDOW` 根 K 线
+    pub fn is_bar_based(self) -> bool {
+        matches!(
+            self,
+            EstimatorKind::BarRv | EstimatorKind::Parkinson | EstimatorKind::GarmanKlass | EstimatorKind::Bipower
+        )
+    }
+
+    /// 按配置参数创建估计器
+    pub fn build(self, cfg: &VolatilityConfig) -> Box<dyn VolatilityEstimator> {
+        match self {
+            EstimatorKind::Rms => Box::new(RmsEstimator {
+                window_secs: (cfg.window_mode == WindowMode::Time).then_some(cfg.window_secs),
+            }),
+            EstimatorKind::Ewma => Box::new(EwmaEstimator { half_life_secs: cfg.ewma_half_life_secs }),
+            EstimatorKind::BarRv => Box::new(BarRvEstimator { bar_ms: cfg.bar_ms }),
+            EstimatorKind::Parkinson => Box::new(ParkinsonEstimator { bar_ms: cfg.bar_ms }),
+            EstimatorKind::GarmanKlass => Box::new(GarmanKlassEstimator { bar_ms: cfg.bar_ms }),
+            EstimatorKind::Bipower => Box::new(BipowerEstimator { bar_ms: cfg.bar_ms }),
+            EstimatorKind::Tsrv => Box::new(TsrvEstimator { slow_ticks: cfg.tsrv_slow_ticks }),
+        }
+    }
+}
+
+/// 各估计器的年化波动率；未启用或样本不足时为 None
+#[derive(Debug, Clone, Copy, Default, PartialEq, Serialize, Deserialize)]
+pub struct VolatilityEstimates {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub rms: Option<Fraction>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub ewma: Option<Fraction>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub bar_rv: Option<Fraction>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub parkinson: Option<Fraction>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub garman_klass: Option<Fraction>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub bipower: Option<Fraction>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub tsrv: Option<Fraction>,
+}
+
+impl VolatilityEstimates {
+    pub fn get(&self, kind: EstimatorKind) -> Option<Fraction> {
+        match kind {
+            EstimatorKind::Rms => self.rms,
+            EstimatorKind::Ewma => self.ewma,
+            EstimatorKind::BarRv => self.bar_rv,
+            EstimatorKind::Parkinson => self.parkinson,
+            EstimatorKind::GarmanKlass => self.garman_klass,
+            EstimatorKind::Bipower => self.bipower,
+            EstimatorKind::Tsrv => self.tsrv,
+        }
+    }
+
+    pub fn set(&mut self, kind: EstimatorKind, value: Option<Fraction>) {
+        let slot = match kind {
+            EstimatorKind::Rms => &mut self.rms,
+            EstimatorKind::Ewma => &mut self.ewma,
+            EstimatorKind::BarRv => &mut self.bar_rv,
+            EstimatorKind::Parkinson => &mut self.parkinson,
+            EstimatorKind::GarmanKlass => &mut self.garman_klass,
+            EstimatorKind::Bipower => &mut self.bipower,
+            EstimatorKind::Tsrv => &mut self.tsrv,
+        };
+        *slot = value;
+    }
+
+    /// 已计算出的 (估计器, 年化波动率)，按 `EstimatorKind::ALL` 顺序
+    pub fn iter(&self) -> impl Iterator<Item = (EstimatorKind, Fraction)> + '_ {
+        EstimatorKind::ALL.into_iter().filter_map(|k| self.get(k).map(|v| (k, v)))
+    }
+}
+
+/// 波动率估计器
+///
+/// 输入为成交窗口 (窗口大小与过期规则由 `InstantVolatilityIndicator` 统一维护)，
+/// 输出年化波动率；样本不足以给出估计时返回 None。
+pub trait VolatilityEstimator: Send {
+    fn kind(&self) -> EstimatorKind;
+
+    fn estimate(&self, window: &PriceWindow) -> Option<Fraction>;
+}
+
+/// 将时长为 `span_secs` 的方差年化为波动率
+pub fn annualize(variance: f64, span_secs: f64) -> Fraction {
+    // max(0.01): 防止除零
+    Fraction((variance.max(0.0) * SECONDS_IN_YEAR / span_secs.max(0.01)).sqrt())
+}
+
+/// 逐笔对数收益率 RMS
+///
+/// - `window_secs = None` (计数窗口): annualized = raw_vol * sqrt(年秒数 / 首尾成交跨度秒数)
+/// - `window_secs = Some(T)` (时间窗口): 已实现方差 Σr_i² 按配置的窗口长度 T 年化，
+///   不随成交笔数与成交分布变化；成交稀疏时不会因首尾跨度很短而放大。
+///   启动后窗口尚未填满时结果偏低
+pub struct RmsEstimator {
+    pub window_secs: Option<f64>,
+}
+
+impl VolatilityEstimator for RmsEstimator {
+    fn kind(&self) -> EstimatorKind {
+        EstimatorKind::Rms
+    }
+
+    fn estimate(&self, window: &PriceWindow) -> Option<Fraction> {
+        if let Some(window_secs) = self.window_secs {
+            if window.len() < 2 {
+                return None;
+            }
+            return Some(annualize(window.sum_sq_returns(), window_secs));
+        }
+        let raw_vol = raw_rms(window)?;
+        // 年化波动率 = raw_vol * sqrt(年秒数 / 窗口秒数)
+        Some(annualize(raw_vol.powi(2), window.span_secs()))
+    }
+}
+
+/// 逐笔对数收益率的 RMS，至少需要 2 个数据点
+fn raw_rms(window: &PriceWindow) -> Option<f64> {
+    if window.len() < 2 {
+        return None;
+    }
+    let count = window.len() - 1;  // 收益率数量 = 价格数量 - 1
+    Some((window.sum_sq_returns() / count as f64).sqrt())
+}
+
+/// 指数加权方差率
+///
+/// 平方收益率与经过时间使用同一衰减系数 `0.5^(Δt / half_life)` 累加，
+/// 二者之比为单位时间方差；同一毫秒内的多笔成交不会导致除零。
+pub struct EwmaEstimator {
+    pub half_life_secs: f64,
+}
+
+impl VolatilityEstimator for EwmaEstimator {
+    fn kind(&self) -> EstimatorKind {
+        EstimatorKind::Ewma
+    }
+
+    fn estimate(&self, window: &PriceWindow) -> Option<Fraction> {
+        let (mut sq_sum, mut time_sum) = (0.0, 0.0);
+        for (a, b) in window.iter().zip(window.iter().skip(1)) {
+            let dt = b.timestamp_ms.saturating_sub(a.timestamp_ms) as f64 / 1000.0;
+            let decay = (-LN_2 * dt / self.half_life_secs).exp();
+            sq_sum = sq_sum * decay + (b.ln_price - a.ln_price).powi(2);
+            time_sum = time_sum * decay + dt;
+        }
+        if time_sum <= 0.0 {
+            return None;
+        }
+        Some(annualize(sq_sum / time_sum, 1.0))
+    }
+}
+
+/// 固定时间 K 线 (对数价格)
+#[derive(Debug, Clone, Copy)]
+struct Bar {
+    index: u64,  // timestamp_ms / bar_ms
+    open: f64,
+    high: f64,
+    low: f64,
+    close: f64,
+}
+
+/// K 线类估计器的窗口最少需要容纳的 K 线根数 (最后一根仍在形成中，剩余两根才能得到一个收益率)
+pub const MIN_BARS_PER_WINDOW: u64 = 3;
+
+/// 将成交窗口切分为 K 线；最后一根仍在形成中，不参与计算。无成交的时间段没有 K 线。
+fn build_bars(window: &PriceWindow, bar_ms: u64) -> Vec<Bar> {
+    let mut bars: Vec<Bar> = Vec::new();
+    for p in window.iter() {
+        let index = p.timestamp_ms / bar_ms.max(1);
+        match bars.last_mut() {
+            Some(bar) if bar.index == index => {
+                bar.high = bar.high.max(p.ln_price);
+                bar.low = bar.low.min(p.ln_price);
+                bar.close = p.ln_price;
+            }
+            _ => bars.push(Bar { index, open: p.ln_price, high: p.ln_price, low: p.ln_price, close: p.ln_price }),
+        }
+    }
+    bars.pop();
+    bars
+}
+
+/// K 线收盘价收益率及其覆盖的时长 (秒)
+fn bar_returns(bars: &[Bar], bar_ms: u64) -> Option<(Vec<f64>, f64)> {
+    if bars.len() < 2 {
+        return None;
+    }
+    let returns = bars.windows(2).map(|w| w[1].close - w[0].close).collect();
+    let span = (bars[bars.len() - 1].index - bars[0].index) as f64 * bar_ms as f64 / 1000.0;
+    Some((returns, span))
+}
+
+/// K 线覆盖的时长 (秒)，包含首尾两根
+fn bars_span(bars: &[Bar], bar_ms: u64) -> f64 {
+    (bars[bars.len() - 1].index - bars[0].index + 1) as f64 * bar_ms as f64 / 1000.0
+}
+
+/// K 线收盘价已实现波动率
+pub struct BarRvEstimator {
+    pub bar_ms: u64,
+}
+
+impl VolatilityEstimator for BarRvEstimator {
+    fn kind(&self) -> EstimatorKind {
+        EstimatorKind::BarRv
+    }
+
+    fn estimate(&self, window: &PriceWindow) -> Option<Fraction> {
+        let (returns, span) = bar_returns(&build_bars(window, self.bar_ms), self.bar_ms)?;
+        let rv: f64 = returns.iter().map(|r| r * r).sum();
+        Some(annualize(rv, span))
+    }
+}
+
+/// Parkinson 区间估计：σ² = Σ (ln H/L)² / (4 ln 2)
+pub struct ParkinsonEstimator {
+    pub bar_ms: u64,
+}
+
+impl VolatilityEstimator for ParkinsonEstimator {
+    fn kind(&self) -> EstimatorKind {
+        EstimatorKind::Parkinson
+    }
+
+    fn estimate(&self, window: &PriceWindow) -> Option<Fraction> {
+        let bars = build_bars(window, self.bar_ms);
+        if bars.is_empty() {
+            return None;
+        }
+        let variance: f64 = bars.iter().map(|b| (b.high - b.low).powi(2)).sum::<f64>() / (4.0 * LN_2);
+        Some(annualize(variance, bars_span(&bars, self.bar_ms)))
+    }
+}
+
+/// Garman-Klass 估计：σ² = Σ [0.5 (ln H/L)² - (2 ln 2 - 1) (ln C/O)²]
+pub struct GarmanKlassEstimator {
+    pub bar_ms: u64,
+}
+
+impl VolatilityEstimator for GarmanKlassEstimator {
+    fn kind(&self) -> EstimatorKind {
+        EstimatorKind::GarmanKlass
+    }
+
+    fn estimate(&self, window: &PriceWindow) -> Option<Fraction> {
+        let bars = build_bars(window, self.bar_ms);
+        if bars.is_empty() {
+            return None;
+        }
+        let variance: f64 = bars
+            .iter()
+            .map(|b| 0.5 * (b.high - b.low).powi(2) - (2.0 * LN_2 - 1.0) * (b.close - b.open).powi(2))
+            .sum();
+        Some(annualize(variance, bars_span(&bars, self.bar_ms)))
+    }
+}
+
+/// 双幂次变差：BV = (π/2) · n/(n-1) · Σ |r_i| |r_{i-1}|
+///
+/// 单根 K 线中的跳跃只出现在一个乘积项中，因此对跳跃不敏感。
+pub struct BipowerEstimator {
+    pub bar_ms: u64,
+}
+
+impl VolatilityEstimator for BipowerEstimator {
+    fn kind(&self) -> EstimatorKind {
+        EstimatorKind::Bipower
+    }
+
+    fn estimate(&self, window: &PriceWindow) -> Option<Fraction> {
+        let (returns, span) = bar_returns(&build_bars(window, self.bar_ms), self.bar_ms)?;
+        let n = returns.len();
+        if n < 2 {
+            return None;
+        }
+        let sum: f64 = returns.windows(2).map(|w| w[0].abs() * w[1].abs()).sum();
+        let bv = PI / 2.0 * (n as f64 / (n - 1) as f64) * sum;
+        Some(annualize(bv, span))
+    }
+}
+
+/// 双尺度已实现方差 (Zhang, Mykland & Aït-Sahalia 2005)
+///
+/// 慢尺度 (每 K 笔) 的平均 RV 减去按比例缩放的逐笔 RV，抵消微观结构噪声带来的偏差：
+/// TSRV = (RV_K - (n̄/n) · RV_all) / (1 - n̄/n)，n̄ = (n - K + 1) / K
+pub struct TsrvEstimator {
+    pub slow_ticks: usize,
+}
+
+impl VolatilityEstimator for TsrvEstimator {
+    fn kind(&self) -> EstimatorKind {
+        EstimatorKind::Tsrv
+    }
+
+    fn estimate(&self, window: &PriceWindow) -> Option<Fraction> {
+        let k = self.slow_ticks;
+        let n = window.len().saturating_sub(1);
+        if k < 2 || n <= k {
+            return None;
+        }
+        let rv_all = window.sum_sq_returns();
+        let rv_slow: f64 = window
+            .iter()
+            .zip(window.iter().skip(k))
+            .map(|(a, b)| (b.ln_price - a.ln_price).powi(2))
+            .sum::<f64>()
+            / k as f64;
+        let n_bar = (n - k + 1) as f64 / k as f64;
+        let ratio = n_bar / n as f64;
+        let tsrv = (rv_slow - ratio * rv_all) / (1.0 - ratio);
+        Some(annualize(tsrv, window.span_secs()))
+    }
 }
 
 /// 瞬时波动率指标计算器
 /// 
 /// # 使用方式
 /// ```ignore
-/// let mut vol = InstantVolatilityIndicator::new(100, 5000, 0.5, 10000);
+/// let mut vol = InstantVolatilityIndicator::new(VolWindow::Count(100), 5000, Fraction(0.5), 10000);
 /// vol.update(price, timestamp_ms);
 /// let result = vol.get_volatility();
 /// ```
 pub struct InstantVolatilityIndicator {
-    window_size: usize,              // 滑动窗口大小 (数据点数量)
-    prices: VecDeque<PriceData>,     // 价格缓冲区 (VecDeque 支持高效的头尾操作)
-    seconds_in_year: f64,            // 一年的秒数，用于年化
+    window: VolWindow,               // 窗口模式 (笔数 / 时间)
+    prices: PriceWindow,             // 价格缓冲区
     stale_threshold_ms: u64,         // 数据过期阈值 (毫秒)，超过则认为市场中断
-    fallback_volatility: f64,        // 数据过期时返回的防御性波动率
+    fallback_volatility: Fraction,   // 数据过期时返回的防御性波动率
     expire_threshold_ms: u64,        // 清除过期数据的阈值 (毫秒)
+    clock: Arc<dyn Clock>,           // 过期判断使用的时钟 (实盘为系统时钟，回放为事件时钟)
+    estimator: Box<dyn VolatilityEstimator>,        // 计算 `annualized` 的估计器
+    report_estimators: Vec<Box<dyn VolatilityEstimator>>, // 同时计算、并列输出的估计器
 }
 
 impl InstantVolatilityIndicator {
     /// 创建新的波动率计算器
     /// 
     /// # 参数
-    /// - `window_size`: 滑动窗口大小
+    /// - `window`: 窗口模式与大小
     /// - `stale_threshold_ms`: 数据过期阈值
     /// - `fallback_volatility`: 过期时的防御性波动率
     /// - `expire_threshold_ms`: 清除过期数据的阈值
     pub fn new(
-        window_size: usize, 
+        window: VolWindow,
         stale_threshold_ms: u64, 
-        fallback_volatility: f64,
+        fallback_volatility: Fraction,
         expire_threshold_ms: u64,
+    ) -> Self {
+        Self::new_with_clock(
+            window,
+            stale_threshold_ms,
+            fallback_volatility,
+            expire_threshold_ms,
+            Arc::new(WallClock),
+        )
+    }
+
+    /// 使用指定时钟创建计算器 (回放/回测传入 `EventClock`)
+    pub fn new_with_clock(
+        window: VolWindow,
+        stale_threshold_ms: u64, 
+        fallback_volatility: Fraction,
+        expire_threshold_ms: u64,
+        clock: Arc<dyn Clock>,
     ) -> Self {
         Self {
-            window_size,
-            prices: VecDeque::with_capacity(window_size),
-            seconds_in_year: 31536000.0,  // 365 * 24 * 3600
+            window,
+            prices: PriceWindow::with_capacity(match window {
+                VolWindow::Count(n) => n,
+                VolWindow::Time { min_samples, .. } => min_samples,
+            }),
             stale_threshold_ms,
             fallback_volatility,
             expire_threshold_ms,
+            clock,
+            estimator: Box::new(RmsEstimator {
+                window_secs: match window {
+                    VolWindow::Count(_) => None,
+                    VolWindow::Time { window_ms, .. } => Some(window_ms as f64 / 1000.0),
+                },
+            }),
+            report_estimators: Vec::new(),
         }
     }
 
+    /// 替换估计器 (默认仅 `RmsEstimator`)；价格窗口保留，下一次 `get_volatility()` 起生效
+    pub fn set_estimators(
+        &mut self,
+        estimator: Box<dyn VolatilityEstimator>,
+        report_estimators: Vec<Box<dyn VolatilityEstimator>>,
+    ) {
+        self.estimator = estimator;
+        self.report_estimators = report_estimators;
+    }
+
     /// 添加新的价格数据点
     /// 
     /// # 参数
     /// - `price`: 成交价格
     /// - `trade_time_ms`: 成交时间戳 (毫秒)
     pub fn update(&mut self, price: f64, trade_time_ms: u64) {
-        // 获取当前系统时间，用于判断数据是否过期
-        let now_ms = SystemTime::now()
-            .duration_since(UNIX_EPOCH)
-            .unwrap()
-            .as_millis() as u64;
-        
-        // 清除过期数据 (从队列头部开始检查)
+        // 获取当前时间，用于判断数据是否过期
+        let now_ms = self.clock.now_ms();
+
+        // 清除过期数据 (从队列头部开始检查)；时间窗口模式下不短于窗口长度
+        let expire_ms = match self.window {
+            VolWindow::Count(_) => self.expire_threshold_ms,
+            VolWindow::Time { window_ms, .. } => self.expire_threshold_ms.max(window_ms),
+        };
         // saturating_sub: 防止时间戳回退导致的下溢
         while let Some(front) = self.prices.front() {
-            if now_ms.saturating_sub(front.timestamp_ms) > self.expire_threshold_ms {
+            if now_ms.saturating_sub(front.timestamp_ms) > expire_ms {
                 self.prices.pop_front();
             } else {
                 break;  // 队列按时间排序，遇到未过期的就停止
@@ -91,14 +624,33 @@ impl InstantVolatilityIndicator {
         }
 
         // 添加新数据点 (存储对数价格以便后续计算)
-        self.prices.push_back(PriceData { 
+        self.prices.push(PriceData { 
             ln_price: price.ln(), 
             timestamp_ms: trade_time_ms 
         });
 
         // 保持窗口大小 (VecDeque 不会自动弹出，需手动维护)
-        if self.prices.len() > self.window_size {
-            self.prices.pop_front();
+        match self.window {
+            VolWindow::Count(window_size) => {
+                if self.prices.len() > window_size {
+                    self.prices.pop_front();
+                }
+            }
+            VolWindow::Time { window_ms, .. } => {
+                // 按成交时间滑动，保留 (最新成交 - window_ms, 最新成交] 内的数据
+                let cutoff = trade_time_ms.saturating_sub(window_ms);
+                while self.prices.front().is_some_and(|front| front.timestamp_ms < cutoff) {
+                    self.prices.pop_front();
+                }
+            }
+        }
+    }
+
+    /// 计算所需的最少数据点：计数模式 2 个，时间模式 `min_samples` 个
+    fn min_samples(&self) -> usize {
+        match self.window {
+            VolWindow::Count(_) => 2,
+            VolWindow::Time { min_samples, .. } => min_samples.max(2),
         }
     }
 
@@ -114,67 +666,168 @@ impl InstantVolatilityIndicator {
             dt_secs: 0.0, 
             duration_ms: 0, 
             is_stale: true,
+            estimator: self.estimator.kind(),
+            estimates: VolatilityEstimates::default(),
         };
 
-        // 至少需要 2 个数据点才能计算收益率
-        if self.prices.len() < 2 { 
+        // 至少需要 2 个数据点才能计算收益率 (时间窗口模式要求 min_samples 个)
+        if self.prices.len() < self.min_samples() { 
             return stale_result; 
         }
 
         // 检查最新数据是否过期 (市场可能中断)
-        let now_ms = SystemTime::now()
-            .duration_since(UNIX_EPOCH)
-            .unwrap()
-            .as_millis() as u64;
+        let now_ms = self.clock.now_ms();
         let latest_ts = self.prices.back().unwrap().timestamp_ms;
         if now_ms.saturating_sub(latest_ts) > self.stale_threshold_ms {
-            println!("⚠️ 警告: 市场行情中断! 上次成交: {}ms 前", now_ms - latest_ts);
-            return stale_result;
-        }
-
-        // 提取所有对数价格
-        let ln_prices: Vec<f64> = self.prices.iter().map(|p| p.ln_price).collect();
-        let count = ln_prices.len() - 1;  // 收益率数量 = 价格数量 - 1
-        
-        // 计算对数收益率的平方和
-        // windows(2): 滑动窗口，每次取相邻两个元素
-        let diff_sq_sum: f64 = ln_prices
-            .windows(2)
-            .map(|w| (w[1] - w[0]).powi(2))  // powi(2): 整数次幂，比 powf 快
-            .sum();
-        
-        // RMS (均方根) 波动率
-        let raw_vol = if count > 0 { 
-            (diff_sq_sum / count as f64).sqrt() 
-        } else { 
-            0.0 
+            warn!("⚠️ Market data interrupted! Last trade {}ms ago", now_ms - latest_ts);
+            return stale_result; 
+        }
+
+        // 所选估计器样本不足 (例如还没有完整的 K 线) 时同样视为不可用
+        let Some(annualized) = self.estimator.estimate(&self.prices) else {
+            return stale_result; 
         };
 
+        let mut estimates = VolatilityEstimates::default();
+        for est in &self.report_estimators {
+            estimates.set(est.kind(), est.estimate(&self.prices));
+        }
+        estimates.set(self.estimator.kind(), Some(annualized));
+
         // 计算时间窗口长度
         let first_ts = self.prices.front().unwrap().timestamp_ms;
         let duration_ms = latest_ts.saturating_sub(first_ts);
-        let dt_secs = duration_ms as f64 / 1000.0;
-        
-        // 年化波动率 = raw_vol * sqrt(年秒数 / 窗口秒数)
-        // max(0.01): 防止除零
-        let annualized = raw_vol * (self.seconds_in_year / dt_secs.max(0.01)).sqrt();
 
         VolatilityResult { 
             annualized, 
-            raw_vol, 
-            dt_secs, 
+            raw_vol: raw_rms(&self.prices).unwrap_or(0.0),
+            dt_secs: duration_ms as f64 / 1000.0,
             duration_ms, 
-            is_stale: false 
+            is_stale: false,
+            estimator: self.estimator.kind(),
+            estimates,
         }
     }
 
     /// 检查是否有足够数据进行可靠计算
     pub fn is_ready(&self) -> bool { 
-        self.prices.len() >= self.window_size 
+        match self.window {
+            VolWindow::Count(window_size) => self.prices.len() >= window_size,
+            VolWindow::Time { .. } => self.prices.len() >= self.min_samples(),
+        }
     }
-    
-    /// 检查是否可以进行基本计算 (至少 2 个数据点)
+
+    /// 检查是否可以进行基本计算 (至少 2 个数据点，时间窗口模式为 `min_samples` 个)
     pub fn can_calculate(&self) -> bool { 
-        self.prices.len() >= 2 
+        self.prices.len() >= self.min_samples() 
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// 由 (成交时间毫秒, 对数价格) 构造成交窗口
+    fn window(points: &[(u64, f64)]) -> PriceWindow {
+        let mut w = PriceWindow::default();
+        for &(timestamp_ms, ln_price) in points {
+            w.push(PriceData { ln_price, timestamp_ms });
+        }
+        w
     }
-}
\ No newline at end of file
+
+    fn example_volatility() -> VolatilityConfig {
+        let cfg: crate::config::MonitorConfig = serde_yaml::from_str(include_str!("../../config.example.yaml")).unwrap();
+        cfg.volatility
+    }
+
+    fn assert_annualized(got: Option<Fraction>, variance: f64, span_secs: f64) {
+        let expected = (variance * SECONDS_IN_YEAR / span_secs).sqrt();
+        let got = got.expect("estimate").0;
+        assert!((got - expected).abs() <= expected * 1e-9, "got {}, expected {}", got, expected);
+    }
+
+    /// 两根完整 K 线 (1s)：
+    /// bar0: O 0.00 H 0.02 L -0.01 C 0.01；bar1: O 0.01 H 0.05 L 0.01 C 0.03；最后一笔开启形成中的 bar2
+    fn ohlc_window() -> PriceWindow {
+        window(&[
+            (0, 0.00), (300, 0.02), (600, -0.01), (900, 0.01),
+            (1_000, 0.01), (1_500, 0.05), (1_900, 0.03),
+            (2_000, 0.00),
+        ])
+    }
+
+    #[test]
+    fn rms_realized_uses_configured_window_length() {
+        let w = window(&[(0, 0.0), (500, 0.01), (1_000, -0.01), (2_000, 0.0)]);
+        // Σr² = 0.0001 + 0.0004 + 0.0001，按 10s 窗口年化
+        assert_annualized(RmsEstimator { window_secs: Some(10.0) }.estimate(&w), 0.0006, 10.0);
+        // 计数窗口：RMS² = Σr² / 3，按首尾跨度年化
+        assert_annualized(RmsEstimator { window_secs: None }.estimate(&w), 0.0002, 2.0);
+        assert_eq!(RmsEstimator { window_secs: Some(10.0) }.estimate(&window(&[(0, 0.0)])), None);
+    }
+
+    #[test]
+    fn rms_realized_is_not_inflated_by_sparse_trades() {
+        // 60s 窗口内只有 3 笔、相隔 100ms 的成交：按首尾跨度 (0.2s) 年化会放大 sqrt(300) 倍
+        let mut indicator = InstantVolatilityIndicator::new_with_clock(
+            VolWindow::Time { window_ms: 60_000, min_samples: 3 },
+            5_000,
+            Fraction(0.5),
+            5_000,
+            Arc::new(crate::common::clock::EventClock::new(10_200)),
+        );
+        for (ts, price) in [(10_000, 100.0), (10_100, 100.1), (10_200, 100.0)] {
+            indicator.update(price, ts);
+        }
+        let sum_sq = (100.1f64 / 100.0).ln().powi(2) + (100.0f64 / 100.1).ln().powi(2);
+        let result = indicator.get_volatility();
+        assert!(!result.is_stale);
+        assert_annualized(Some(result.annualized), sum_sq, 60.0);
+    }
+
+    #[test]
+    fn bar_rv_uses_completed_bar_closes() {
+        // 收盘价 0.01 -> 0.03，形成中的 bar2 不参与
+        assert_annualized(BarRvEstimator { bar_ms: 1_000 }.estimate(&ohlc_window()), 0.0004, 1.0);
+
+        let w = window(&[(0, 0.0), (1_000, 0.01), (2_000, 0.03), (3_000, 0.0)]);
+        assert_annualized(BarRvEstimator { bar_ms: 1_000 }.estimate(&w), 0.0001 + 0.0004, 2.0);
+    }
+
+    #[test]
+    fn parkinson_uses_high_low_range() {
+        let variance = (0.03f64.powi(2) + 0.04f64.powi(2)) / (4.0 * LN_2);
+        assert_annualized(ParkinsonEstimator { bar_ms: 1_000 }.estimate(&ohlc_window()), variance, 2.0);
+    }
+
+    #[test]
+    fn garman_klass_uses_ohlc() {
+        let variance = 0.5 * (0.03f64.powi(2) + 0.04f64.powi(2)) - (2.0 * LN_2 - 1.0) * (0.01f64.powi(2) + 0.02f64.powi(2));
+        assert_annualized(GarmanKlassEstimator { bar_ms: 1_000 }.estimate(&ohlc_window()), variance, 2.0);
+    }
+
+    #[test]
+    fn bipower_multiplies_adjacent_absolute_returns() {
+        // 收盘价 0, 0.01, -0.01, 0.02 -> 收益率 0.01, -0.02, 0.03
+        let w = window(&[(0, 0.0), (1_000, 0.01), (2_000, -0.01), (3_000, 0.02), (4_000, 0.0)]);
+        let bv = PI / 2.0 * (3.0 / 2.0) * (0.01 * 0.02 + 0.02 * 0.03);
+        assert_annualized(BipowerEstimator { bar_ms: 1_000 }.estimate(&w), bv, 3.0);
+    }
+
+    #[test]
+    fn bar_estimators_need_completed_bars() {
+        // 只有一根完整 K 线：没有 K 线收益率，区间类估计器仍可计算
+        let one_bar = window(&[(0, 0.0), (400, 0.02), (1_000, 0.01)]);
+        assert_eq!(BarRvEstimator { bar_ms: 1_000 }.estimate(&one_bar), None);
+        assert_eq!(BipowerEstimator { bar_ms: 1_000 }.estimate(&one_bar), None);
+        assert_annualized(ParkinsonEstimator { bar_ms: 1_000 }.estimate(&one_bar), 0.0004 / (4.0 * LN_2), 1.0);
+
+        // 窗口短于一根 K 线 (计数窗口成交密集时的常态)
+        let burst = window(&[(0, 0.0), (20, 0.01), (40, -0.01), (60, 0.0)]);
+        let cfg = VolatilityConfig { bar_ms: 1_000, ..example_volatility() };
+        for kind in EstimatorKind::ALL.into_iter().filter(|k| k.is_bar_based()) {
+            assert_eq!(kind.build(&cfg).estimate(&burst), None, "{}", kind.name());
+        }
+    }
+}
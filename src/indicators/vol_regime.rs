@@ -0,0 +1,256 @@
+//! 波动率状态分类器
+//!
+//! 将年化波动率划分为 Calm / Normal / Elevated / Extreme 四档，替代逐笔 `annualized >= threshold` 比较，
+//! 避免波动率在阈值附近来回穿越时 "V" 信号闪烁。
+//!
+//! # 规则
+//! - 升档：波动率达到更高档位的进入阈值时立即升档 (可跨档)，尽快保护报价
+//! - 降档：波动率低于当前档位的退出阈值时降到能停留的最高档位，
+//!   且距上次切换至少 `min_dwell_ms`，否则保持当前档位。
+//!   退出阈值可按档位单独配置，默认为进入阈值 × (1 - hysteresis)
+//!
+//! 分类器只接收可用的波动率：窗口未就绪或断流 (`is_stale`) 时由调用方跳过更新，
+//! 档位保持不变，而不是被 fallback 值拉低或抬高。
+//!
+//! 每次切换返回一个 `RegimeChange`，由调用方下发 Telemetry 转换事件与 Slack 通知。
+
+use serde::{Deserialize, Serialize};
+
+use crate::common::units::Fraction;
+
+/// 波动率状态 (按从低到高排序)
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum VolRegime {
+    #[default]
+    Calm,
+    Normal,
+    Elevated,
+    Extreme,
+}
+
+impl VolRegime {
+    pub const ALL: [VolRegime; 4] = [VolRegime::Calm, VolRegime::Normal, VolRegime::Elevated, VolRegime::Extreme];
+
+    /// 在 `ALL` 中的位置
+    pub fn index(self) -> usize {
+        self as usize
+    }
+
+    pub fn name(self) -> &'static str {
+        match self {
+            VolRegime::Calm => "calm",
+            VolRegime::Normal => "normal",
+            VolRegime::Elevated => "elevated",
+            VolRegime::Extreme => "extreme",
+        }
+    }
+}
+
+/// 分类阈值
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct RegimeThresholds {
+    pub normal: Fraction,        // 进入 Normal
+    pub elevated: Fraction,      // 进入 Elevated
+    pub extreme: Fraction,       // 进入 Extreme
+    pub normal_exit: Fraction,   // 低于该值离开 Normal
+    pub elevated_exit: Fraction, // 低于该值离开 Elevated
+    pub extreme_exit: Fraction,  // 低于该值离开 Extreme
+    pub min_dwell_ms: u64,       // 降档前的最短停留时间
+}
+
+impl RegimeThresholds {
+    /// 按统一的滞回比例 (例如 0.15) 生成各档退出阈值
+    pub fn with_hysteresis(
+        normal: Fraction,
+        elevated: Fraction,
+        extreme: Fraction,
+        hysteresis: f64,
+        min_dwell_ms: u64,
+    ) -> Self {
+        let exit = |enter: Fraction| Fraction(enter.0 * (1.0 - hysteresis));
+        Self {
+            normal,
+            elevated,
+            extreme,
+            normal_exit: exit(normal),
+            elevated_exit: exit(elevated),
+            extreme_exit: exit(extreme),
+            min_dwell_ms,
+        }
+    }
+
+    /// 进入阈值 (Calm 为 0)
+    pub fn enter(&self, regime: VolRegime) -> Fraction {
+        match regime {
+            VolRegime::Calm => Fraction(0.0),
+            VolRegime::Normal => self.normal,
+            VolRegime::Elevated => self.elevated,
+            VolRegime::Extreme => self.extreme,
+        }
+    }
+
+    /// 停留阈值：低于该值时离开此档位 (Calm 为 0)
+    pub fn exit(&self, regime: VolRegime) -> Fraction {
+        match regime {
+            VolRegime::Calm => Fraction(0.0),
+            VolRegime::Normal => self.normal_exit,
+            VolRegime::Elevated => self.elevated_exit,
+            VolRegime::Extreme => self.extreme_exit,
+        }
+    }
+}
+
+/// 档位切换事件
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct RegimeChange {
+    pub from: VolRegime,
+    pub to: VolRegime,
+    pub vol: Fraction,        // 触发切换的年化波动率
+    pub dwell_secs: f64,      // 在 `from` 档位停留的时长 (秒)
+}
+
+impl RegimeChange {
+    pub fn is_upgrade(&self) -> bool {
+        self.to > self.from
+    }
+}
+
+pub struct VolRegimeClassifier {
+    regime: VolRegime,
+    since_ms: Option<u64>,    // 进入当前档位的时间，首次更新时初始化
+    thresholds: RegimeThresholds,
+}
+
+impl VolRegimeClassifier {
+    pub fn new(thresholds: RegimeThresholds) -> Self {
+        Self { regime: VolRegime::Calm, since_ms: None, thresholds }
+    }
+
+    /// 输入一次波动率计算结果，发生切换时返回 `RegimeChange`
+    pub fn update(&mut self, vol: Fraction, timestamp_ms: u64) -> Option<RegimeChange> {
+        let since = *self.since_ms.get_or_insert(timestamp_ms);
+        let target = self.target(vol);
+        if target == self.regime {
+            return None;
+        }
+        let dwell_ms = timestamp_ms.saturating_sub(since);
+        if target < self.regime && dwell_ms < self.thresholds.min_dwell_ms {
+            return None;
+        }
+
+        let change = RegimeChange { from: self.regime, to: target, vol, dwell_secs: dwell_ms as f64 / 1000.0 };
+        self.regime = target;
+        self.since_ms = Some(timestamp_ms);
+        Some(change)
+    }
+
+    /// 不考虑停留时间时应处的档位
+    fn target(&self, vol: Fraction) -> VolRegime {
+        let t = &self.thresholds;
+        // 升档：达到进入阈值的最高档位
+        if let Some(up) = VolRegime::ALL.into_iter().rev().find(|&r| r > self.regime && vol >= t.enter(r)) {
+            return up;
+        }
+        // 保持或降档：不高于当前档位、且仍满足停留阈值的最高档位
+        VolRegime::ALL
+            .into_iter()
+            .rev()
+            .find(|&r| r <= self.regime && (r == VolRegime::Calm || vol >= t.exit(r)))
+            .unwrap_or(VolRegime::Calm)
+    }
+
+    /// 替换阈值 (热加载)，保留当前档位；新阈值从下一次 `update()` 起生效
+    pub fn set_thresholds(&mut self, thresholds: RegimeThresholds) {
+        self.thresholds = thresholds;
+    }
+
+    pub fn thresholds(&self) -> &RegimeThresholds {
+        &self.thresholds
+    }
+
+    pub fn regime(&self) -> VolRegime {
+        self.regime
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// 20% / 60% / 120% 进入，滞回 15% (退出 17% / 51% / 102%)，降档需停留 5 秒
+    fn thresholds() -> RegimeThresholds {
+        RegimeThresholds::with_hysteresis(Fraction(0.2), Fraction(0.6), Fraction(1.2), 0.15, 5_000)
+    }
+
+    #[test]
+    fn default_exit_thresholds_apply_hysteresis() {
+        let t = thresholds();
+        assert_eq!(t.exit(VolRegime::Calm), Fraction(0.0));
+        assert!((t.exit(VolRegime::Normal).0 - 0.17).abs() < 1e-12);
+        assert!((t.exit(VolRegime::Elevated).0 - 0.51).abs() < 1e-12);
+        assert!((t.exit(VolRegime::Extreme).0 - 1.02).abs() < 1e-12);
+    }
+
+    #[test]
+    fn upgrades_immediately_and_can_skip_levels() {
+        let mut c = VolRegimeClassifier::new(thresholds());
+        assert_eq!(c.update(Fraction(0.1), 0), None);
+        let change = c.update(Fraction(1.3), 10).unwrap();
+        assert_eq!((change.from, change.to), (VolRegime::Calm, VolRegime::Extreme));
+        assert!(change.is_upgrade());
+        assert_eq!(c.regime(), VolRegime::Extreme);
+    }
+
+    #[test]
+    fn hysteresis_holds_regime_between_exit_and_enter() {
+        let mut c = VolRegimeClassifier::new(thresholds());
+        assert_eq!(c.update(Fraction(0.6), 0).unwrap().to, VolRegime::Elevated);
+        // 在退出阈值 (51%) 与进入阈值 (60%) 之间来回波动不切换
+        for (i, vol) in [0.55, 0.52, 0.59, 0.511, 0.58].into_iter().enumerate() {
+            assert_eq!(c.update(Fraction(vol), 10_000 + i as u64 * 1_000), None, "vol {}", vol);
+        }
+        assert_eq!(c.regime(), VolRegime::Elevated);
+
+        let change = c.update(Fraction(0.5), 20_000).unwrap();
+        assert_eq!((change.from, change.to), (VolRegime::Elevated, VolRegime::Normal));
+        assert_eq!(change.dwell_secs, 20.0);
+        // 回到 Normal 后需重新达到 60% 才升档
+        assert_eq!(c.update(Fraction(0.59), 30_000), None);
+        assert_eq!(c.update(Fraction(0.6), 31_000).unwrap().to, VolRegime::Elevated);
+    }
+
+    #[test]
+    fn downgrade_waits_for_min_dwell() {
+        let mut c = VolRegimeClassifier::new(thresholds());
+        c.update(Fraction(0.7), 1_000);
+        assert_eq!(c.update(Fraction(0.1), 2_000), None);
+        assert_eq!(c.update(Fraction(0.1), 5_999), None);
+        assert_eq!(c.regime(), VolRegime::Elevated);
+
+        // 停留满 5 秒后直接降到能停留的最高档位
+        let change = c.update(Fraction(0.1), 6_000).unwrap();
+        assert_eq!((change.from, change.to), (VolRegime::Elevated, VolRegime::Calm));
+        assert_eq!(change.dwell_secs, 5.0);
+    }
+
+    #[test]
+    fn upgrade_is_not_delayed_by_dwell_and_resets_it() {
+        let mut c = VolRegimeClassifier::new(thresholds());
+        c.update(Fraction(0.7), 0);
+        assert_eq!(c.update(Fraction(1.2), 100).unwrap().to, VolRegime::Extreme);
+        // 停留时间从升档时刻重新计算
+        assert_eq!(c.update(Fraction(0.3), 5_050), None);
+        assert_eq!(c.update(Fraction(0.3), 5_100).unwrap().to, VolRegime::Normal);
+    }
+
+    #[test]
+    fn explicit_exit_threshold_overrides_hysteresis() {
+        let mut t = thresholds();
+        t.elevated_exit = Fraction(0.4);
+        let mut c = VolRegimeClassifier::new(t);
+        c.update(Fraction(0.6), 0);
+        assert_eq!(c.update(Fraction(0.45), 10_000), None);
+        assert_eq!(c.update(Fraction(0.39), 11_000).unwrap().to, VolRegime::Normal);
+    }
+}
@@ -1,20 +1,25 @@
 //! BN_Vol - 币安波动率与趋势监控系统
 //!
-//! 本项目实时监控 BTC/USDT 的：
+//! 本项目实时监控多个 USDT 永续合约 (默认 BTC/USDT) 的：
 //! 1. **瞬时波动率**: 基于 aggTrade 计算年化波动率
 //! 2. **趋势信号**: 基于 VWAP 拟合 + OFI 判断价格趋势
 //!
 //! # 数据流
 //! ```text
 //! Binance WebSocket
+//!  (combined stream, 按交易对分发到各自的 SymbolPipeline)
 //!     ├── aggTrade ──> 波动率计算 ──> 趋势拟合 ──> Telemetry 推送
-//!     └── depth20@100ms ──> OFI 计算 (辅助趋势判断)
+//!     └── depth@100ms (+ REST 快照) ──> 本地订单簿 ──> OFI / 冲击价格 (辅助趋势判断)
 //! ```
 //!
 //! # 输出
-//! - Telemetry WebSocket (端口 9001): 实时价差调整信号
-//! - Slack 通知: 波动率直方图报告
+//! - Telemetry WebSocket (默认 127.0.0.1:9001，见 `telemetry` 配置): 实时价差调整信号
+//! - Slack 通知: 波动率状态切换、期限结构形态与直方图报告
 //! - 日志: 详细运行状态
+//! - 录制文件 (可选): 原始帧 gzip 归档，可通过 `run_replay` 离线回放
+//!
+//! # 结构
+//! `MarketDataSource` (实盘 / 回放 / 内存) ──> `SignalEngine` (纯计算) ──> `dispatch` (Telemetry / Slack)
 
 pub mod common;
 pub mod indicators;
@@ -23,229 +28,160 @@ pub mod stats;
 pub mod models;
 pub mod notifier;
 pub mod telemetry;
+pub mod pipeline;
+pub mod recorder;
+pub mod source;
+pub mod engine;
+pub mod backtest;
+pub mod sweep;
+pub mod reload;
 
-use crate::indicators::vol::InstantVolatilityIndicator;
-use crate::indicators::calculators::{VwapCalculator, DepthCalculator, PriceFitter};
-use crate::indicators::trend_state::{TrendStateMachine, TrendDirection};
-use crate::indicators::trend_state::TrendConfig as TrendStateConfig;
 use crate::config::MonitorConfig;
-use crate::stats::VolatilityStats;
-use crate::models::BinanceEvent;
-use crate::telemetry::{TelemetryServer, TelemetryPacket};
-
-use futures_util::{SinkExt, StreamExt};
-use tokio::time::Instant;
-use tokio_tungstenite::{connect_async, tungstenite::protocol::Message};
-use tracing::{info, error};
-use chrono::Local;
-pub async fn run_connection(
-    vol_calc: &mut InstantVolatilityIndicator,
-    cfg: &MonitorConfig,
-) -> Result<(), Box<dyn std::error::Error>> {
-    let telemetry = TelemetryServer::new(true, 9001);
-    let mut stats = VolatilityStats::new(cfg.histogram.step, cfg.histogram.buckets);
-
-    let mut last_hist_time = Instant::now();
-
-    // 趋势计算器
-    let mut vwap_calc = VwapCalculator::new(cfg.trend.vwap_window_ms, cfg.trend.vwap_series_max_len);
-    let mut depth_calc = DepthCalculator::new(cfg.trend.ofi_cum_window_secs, cfg.trend.ofi_decay);
-    let fitter_5s = PriceFitter::new(cfg.trend.fit_window_secs, cfg.trend.fit_min_points, cfg.trend.fit_min_r2);
-    let fitter_2s = PriceFitter::new(cfg.trend.fit_window_2s, cfg.trend.fit_min_points / 2, cfg.trend.fit_min_r2);
-    
-    let trend_state_cfg = TrendStateConfig {
-        slope_threshold: cfg.trend.slope_threshold,
-        ofi_confirm_threshold: cfg.trend.ofi_confirm_threshold,
-        cooldown_secs: cfg.trend.cooldown_secs,
-        slope_threshold_ratio: cfg.trend.slope_threshold_ratio,
-        min_price_fallback: cfg.trend.min_price_fallback,
-        max_price_fallback: cfg.trend.max_price_fallback,
-        entry_protection_secs: cfg.trend.entry_protection_secs,
-        slope_weak_threshold: cfg.trend.slope_weak_threshold,
-    };
-    let mut trend_sm = TrendStateMachine::new(trend_state_cfg);
-    
-    let mut current_cum_ofi = 0.0;
-    let mut last_fit_2s: Option<crate::indicators::calculators::FitResult> = None;
-    let mut last_vol_alert_time: Option<Instant> = None;
-    let mut last_agg_id: u64 = 0;  // 用于检测重复的 aggTrade 消息
-
-    let url = "wss://fstream.binance.com/stream?streams=btcusdt@aggTrade/btcusdt@depth20@100ms";
-    let (ws_stream, _) = connect_async(url).await?;
-    let (mut write, mut read) = ws_stream.split();
-
-    info!("✅ Connected. Threshold: {:.1}%", cfg.threshold);
-
-    while let Some(message) = read.next().await {
-        if last_hist_time.elapsed().as_secs() >= cfg.histogram.interval {
-            let report = stats.generate_report(cfg.histogram.interval / 60);
+use crate::engine::{SignalEngine, SignalOutput};
+use crate::recorder::FrameRecorder;
+use crate::source::{BinanceWsSource, MarketDataSource, ReplaySource};
+use crate::telemetry::TelemetryServer;
+
+use std::sync::Arc;
+
+use chrono::{Local, TimeZone};
+use tokio::sync::watch;
+use tracing::info;
+
+/// 投递引擎输出：Telemetry 推送、Slack 报警与直方图报告
+pub fn dispatch(output: SignalOutput, cfg: &MonitorConfig, telemetry: &TelemetryServer) {
+    match output {
+        SignalOutput::Telemetry(packet) => telemetry.send(packet),
+        SignalOutput::VolAlert(alert) => {
+            if !cfg.slack_enabled {
+                return;
+            }
+            let time_str = Local.timestamp_millis_opt(alert.timestamp_ms as i64)
+                .single()
+                .unwrap_or_else(Local::now)
+                .format("%H:%M:%S")
+                .to_string();
+            notifier::send_slack_alert(cfg.slack_webhook_url.clone(), &alert, time_str);
+        }
+        SignalOutput::TermAlert(alert) => {
+            if !cfg.slack_enabled {
+                return;
+            }
+            let time_str = Local.timestamp_millis_opt(alert.timestamp_ms as i64)
+                .single()
+                .unwrap_or_else(Local::now)
+                .format("%H:%M:%S")
+                .to_string();
+            notifier::send_term_structure_alert(cfg.slack_webhook_url.clone(), &alert, time_str);
+        }
+        SignalOutput::HistogramReport { symbol, report } => {
+            if !cfg.slack_enabled {
+                return;
+            }
             notifier::send_histogram_report(cfg.slack_webhook_url.clone(), report);
-            info!("📊 Histogram report sent.");
-            stats = VolatilityStats::new(cfg.histogram.step, cfg.histogram.buckets);
-            last_hist_time = Instant::now();
+            info!("📊 [{}] Histogram report sent.", symbol);
         }
+        // 由 run_pipeline 转交数据源处理
+        SignalOutput::ResyncRequired { .. } => {}
+    }
+}
 
-        let msg = match message {
-            Ok(m) => m,
-            Err(e) => { error!("WS Error: {:?}", e); return Err(Box::new(e)); }
-        };
-
-        match msg {
-            Message::Text(text_bytes) => {
-                let text = text_bytes.as_str();
-                let json_val: serde_json::Value = match serde_json::from_str(text) {
-                    Ok(v) => v,
-                    Err(_) => continue,
-                };
-                let event_data = json_val.get("data").unwrap_or(&json_val);
-                let event: BinanceEvent = match serde_json::from_value(event_data.clone()) {
-                    Ok(e) => e,
-                    Err(_) => continue,
-                };
-
-                match event {
-                    BinanceEvent::Trade(trade) => {
-                        // 检测重复消息
-                        if trade.agg_id <= last_agg_id {
-                            continue;
-                        }
-                        last_agg_id = trade.agg_id;
-
-                        let p: f64 = trade.price.parse()?;
-                        let q: f64 = trade.quantity.parse()?;
-                        let trade_ms = trade.trade_time as u64;
-
-                        // 波动率计算
-                        vol_calc.update(p, trade_ms);
-                        let vol_res = vol_calc.get_volatility();
-
-                        // OFI 计算器添加成交
-                        depth_calc.add_trade(trade_ms, p, q, trade.is_buyer_maker);
-
-                        // VWAP 计算 + 拟合 + 状态机更新
-                        if let Some(_vwap_point) = vwap_calc.add_trade(p, q, trade_ms) {
-                            let current_ts_sec = trade_ms as f64 / 1000.0;
-                            let fit_5s = fitter_5s.fit(vwap_calc.get_series(), trade_ms);
-                            let fit_2s = fitter_2s.fit(vwap_calc.get_series(), trade_ms);
-                            
-                            // 保存 fit_2s 用于后续价差计算
-                            last_fit_2s = fit_2s;
-                            
-                            let latest_price = vwap_calc.get_series().back()
-                                .map(|pt| pt.price)
-                                .unwrap_or(p);
-
-                            // 状态机更新
-                            trend_sm.update(
-                                current_ts_sec,
-                                fit_5s.as_ref(),
-                                current_cum_ofi,
-                                latest_price,
-                            );
-                        }
-
-                        // 波动率统计
-                        if vol_calc.is_ready() && !vol_res.is_stale {
-                            stats.record(vol_res.annualized);
-                        }
-
-                        // 获取冲击价格
-                        let impact_price = depth_calc.get_impact_price();
-
-                        // 决定信号来源和价差调整
-                        let spread_adj = cfg.volatility.spread_adjust;
-                        
-                        // 高波动率处理 (>= 100%)
-                        if vol_res.annualized >= cfg.threshold {
-                            // 发送 Slack 警报（带冷却）
-                            let now = Instant::now();
-                            let should_alert = last_vol_alert_time
-                                .map(|t| now.duration_since(t).as_secs() >= cfg.cooldown_secs)
-                                .unwrap_or(true);
-                            
-                            if should_alert && cfg.slack_enabled {
-                                let time_str = Local::now().format("%H:%M:%S").to_string();
-                                notifier::send_slack_alert(
-                                    cfg.slack_webhook_url.clone(),
-                                    vol_res.annualized,
-                                    cfg.threshold,
-                                    vol_res.raw_vol,
-                                    vol_res.dt_secs,
-                                    p,
-                                    time_str,
-                                );
-                                last_vol_alert_time = Some(now);
-                            }
-                            
-                            // 发送 Telemetry
-                            telemetry.send(TelemetryPacket {
-                                timestamp: trade_ms,
-                                source: "V".to_string(),
-                                ask_adjust: spread_adj,
-                                bid_adjust: -spread_adj,
-                            });
-                        } else {
-                            // 检查趋势
-                            let direction = trend_sm.get_direction();
-                            if direction != TrendDirection::Neutral {
-                                // 计算预测价格与冲击价格的偏差
-                                let price_diff = if let Some(ref fit) = last_fit_2s {
-                                    if fit.is_valid && impact_price > 0.0 {
-                                        let predicted = fitter_2s.predict(fit, 1.0);
-                                        (predicted - impact_price).abs()
-                                    } else {
-                                        spread_adj
-                                    }
-                                } else {
-                                    spread_adj
-                                };
-
-                                // 根据趋势方向设置价差调整
-                                let (source, ask_adj, bid_adj) = match direction {
-                                    TrendDirection::Long => ("U", price_diff, 0.0),
-                                    TrendDirection::Short => ("D", 0.0, -price_diff),
-                                    TrendDirection::Neutral => unreachable!(),
-                                };
-
-                                telemetry.send(TelemetryPacket {
-                                    timestamp: trade_ms,
-                                    source: source.to_string(),
-                                    ask_adjust: ask_adj,
-                                    bid_adjust: bid_adj,
-                                });
-                            }
-                        }
-                    }
-
-                    BinanceEvent::Depth(depth) => {
-                        // 解析订单簿
-                        let bids: Vec<(f64, f64)> = depth.bids.iter()
-                            .filter_map(|(p, q)| Some((p.parse().ok()?, q.parse().ok()?)))
-                            .collect();
-                        let asks: Vec<(f64, f64)> = depth.asks.iter()
-                            .filter_map(|(p, q)| Some((p.parse().ok()?, q.parse().ok()?)))
-                            .collect();
-
-                        // 更新 OFI 状态
-                        if let Some((_raw_ofi, cum_ofi, _mid_price)) = depth_calc.update_depth(
-                            depth.update_id,
-                            depth.trans_time,
-                            &bids,
-                            &asks,
-                        ) {
-                            current_cum_ofi = cum_ofi;
-                        }
-                        
-                        // 计算冲击价格 (1 BTC)
-                        depth_calc.calculate_impact_price(&bids, &asks, 1.0);
-                    }
+/// 热加载配置的接收端 (见 `reload::spawn_config_watcher`)
+pub type ConfigUpdates = watch::Receiver<Arc<MonitorConfig>>;
 
-                }
+/// 应用热加载的新配置，记录变更并通知 Slack
+pub fn apply_reload(engine: &mut SignalEngine, new: MonitorConfig) {
+    let changes = engine.apply_config(new);
+    if changes.is_empty() {
+        info!("🔄 Config reloaded: no effective changes.");
+        return;
+    }
+    for change in &changes {
+        info!("🔄 Config changed: {}", change);
+    }
+    let cfg = engine.config();
+    if cfg.slack_enabled {
+        let text = format!(
+            "🔄 *Config reloaded* ({} change{})\n{}",
+            changes.len(),
+            if changes.len() == 1 { "" } else { "s" },
+            changes.iter().map(|c| format!("• `{}`", c)).collect::<Vec<_>>().join("\n"),
+        );
+        notifier::send_config_reload(cfg.slack_webhook_url.clone(), text);
+    }
+}
+
+/// 从任意数据源驱动引擎，直到数据源结束或出错
+///
+/// 传入 `updates` 时，每处理完一个事件检查一次新配置，保证替换发生在两个事件之间。
+pub async fn run_pipeline<S: MarketDataSource>(
+    source: &mut S,
+    engine: &mut SignalEngine,
+    telemetry: &TelemetryServer,
+    mut updates: Option<&mut ConfigUpdates>,
+) -> Result<(), Box<dyn std::error::Error>> {
+    while let Some(event) = source.next_event().await? {
+        for output in engine.on_event(&event)? {
+            if let SignalOutput::ResyncRequired { symbol } = &output {
+                source.resync(symbol).await?;
             }
-            Message::Ping(payload) => { write.send(Message::Pong(payload)).await?; }
-            Message::Close(_) => { break; }
-            _ => (),
+            dispatch(output, engine.config(), telemetry);
+        }
+        // 刷新该交易对的状态，供新连接的 Telemetry 客户端获取快照
+        if telemetry.is_enabled()
+            && let Some(state) = engine.state(event.symbol())
+        {
+            telemetry.update_state(state);
+        }
+        if let Some(rx) = updates.as_deref_mut()
+            && rx.has_changed().unwrap_or(false)
+        {
+            let new = (**rx.borrow_and_update()).clone();
+            apply_reload(engine, new);
         }
     }
     Ok(())
-}
\ No newline at end of file
+}
+
+/// 建立一次实盘连接并运行到断开
+///
+/// `telemetry` 由调用方持有，跨重连复用，避免重复绑定端口；客户端保持连接，
+/// 连接建立后广播 "L" (feed up)，断开 (正常关闭或出错) 时广播 "X" (feed down)。
+pub async fn run_connection(
+    engine: &mut SignalEngine,
+    telemetry: &TelemetryServer,
+    recorder: Option<&mut FrameRecorder>,
+    updates: Option<&mut ConfigUpdates>,
+) -> Result<(), Box<dyn std::error::Error>> {
+    engine.reset_session();
+
+    let cfg = engine.config();
+    let mut source = BinanceWsSource::connect(cfg, recorder).await?;
+
+    info!(
+        "✅ Connected. Symbols: {:?} | Depth: {:?} | Threshold: {:.1}",
+        cfg.symbols, cfg.order_book.mode, cfg.threshold_pct,
+    );
+
+    telemetry.send_feed_status(&cfg.symbols, true);
+
+    let result = run_pipeline(&mut source, engine, telemetry, updates).await;
+    telemetry.send_feed_status(&engine.config().symbols, false);
+    result
+}
+
+/// 离线回放录制文件
+///
+/// 与实盘共用 `BinanceEvent` 解析与 `SignalEngine`，只收集输出而不投递，
+/// 因此不会发送 Slack 也不需要 Telemetry 服务。
+/// 引擎应使用 `EventClock` 创建，否则历史数据会被当作过期数据。
+pub async fn run_replay<P: AsRef<std::path::Path>>(
+    path: P,
+    engine: &mut SignalEngine,
+) -> Result<Vec<SignalOutput>, Box<dyn std::error::Error>> {
+    let mut source = ReplaySource::open(path)?;
+    engine.reset_session();
+    let outputs = engine.run_to_end(&mut source).await?;
+    info!("⏪ Replay finished: {} outputs", outputs.len());
+    Ok(outputs)
+}
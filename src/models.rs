@@ -10,7 +10,8 @@ use serde::Deserialize;
 /// 使用 `#[serde(tag = "e")]` 根据 JSON 中的 "e" 字段自动选择变体：
 /// - "aggTrade" -> Trade(AggTrade)
 /// - "depthUpdate" -> Depth(DepthUpdate)
-#[derive(Debug, Deserialize)]
+/// - "depthSnapshot" -> Snapshot(DepthSnapshot) (本地合成，见 `DepthSnapshot`)
+#[derive(Debug, Clone, Deserialize)]
 #[serde(tag = "e")]
 pub enum BinanceEvent {
     #[serde(rename = "aggTrade")]
@@ -18,6 +19,38 @@ pub enum BinanceEvent {
 
     #[serde(rename = "depthUpdate")]
     Depth(DepthUpdate),
+
+    #[serde(rename = "depthSnapshot")]
+    Snapshot(DepthSnapshot),
+}
+
+impl BinanceEvent {
+    /// 解析一帧原始文本，兼容 combined-stream 包装 (`{"stream":..,"data":{..}}`) 与裸事件
+    ///
+    /// 非行情事件 (如订阅回执) 或格式错误返回 `None`。
+    pub fn from_frame(text: &str) -> Option<Self> {
+        let json_val: serde_json::Value = serde_json::from_str(text).ok()?;
+        let event_data = json_val.get("data").unwrap_or(&json_val);
+        serde_json::from_value(event_data.clone()).ok()
+    }
+
+    /// 事件时间戳 (毫秒)：成交时间或撮合引擎事务时间
+    pub fn timestamp_ms(&self) -> u64 {
+        match self {
+            BinanceEvent::Trade(t) => t.trade_time,
+            BinanceEvent::Depth(d) => d.trans_time,
+            BinanceEvent::Snapshot(s) => s.trans_time,
+        }
+    }
+
+    /// 事件所属交易对 (币安返回大写，例如 "BTCUSDT")
+    pub fn symbol(&self) -> &str {
+        match self {
+            BinanceEvent::Trade(t) => &t.symbol,
+            BinanceEvent::Depth(d) => &d.symbol,
+            BinanceEvent::Snapshot(s) => &s.symbol,
+        }
+    }
 }
 
 /// 聚合成交数据 (aggTrade)
@@ -25,13 +58,16 @@ pub enum BinanceEvent {
 /// 币安将同一价格、同一方向的连续成交聚合为一条记录。
 /// 
 /// # 字段
+/// - `symbol`: 交易对 (大写)
 /// - `agg_id`: 聚合成交 ID，用于检测重复消息
 /// - `trade_time`: 成交时间戳 (毫秒)
 /// - `price`: 成交价格 (字符串，需解析为 f64)
 /// - `quantity`: 成交数量
 /// - `is_buyer_maker`: true = 卖单主动成交 (价格下跌方向)
-#[derive(Debug, Deserialize)]
+#[derive(Debug, Clone, Deserialize)]
 pub struct AggTrade {
+    #[serde(rename = "s")]
+    pub symbol: String,
     #[serde(rename = "a")]
     pub agg_id: u64,
     #[serde(rename = "T")]
@@ -44,23 +80,74 @@ pub struct AggTrade {
     pub is_buyer_maker: bool,
 }
 
-/// 深度更新数据 (depth20@100ms)
+/// 深度更新数据 (depth20@100ms 或 depth@100ms)
 /// 
-/// 每 100ms 推送一次订单簿快照，包含买卖各 20 档。
+/// - depth20: 每 100ms 推送一次订单簿快照，包含买卖各 20 档。
+/// - depth: 每 100ms 推送一次增量，数量为 0 表示删除该档位。
 /// 
 /// # 字段
+/// - `symbol`: 交易对 (大写)
 /// - `trans_time`: 事务时间戳 (毫秒)
-/// - `update_id`: 更新序号，用于检测数据连续性
+/// - `first_update_id`: 本次增量的首个更新序号 `U`
+/// - `update_id`: 本次增量的最后更新序号 `u`，用于检测数据连续性
+/// - `prev_update_id`: 上一条增量的 `u` (`pu`)，用于检测断档
 /// - `bids`: 买单列表 [(价格, 数量), ...]，按价格降序
 /// - `asks`: 卖单列表 [(价格, 数量), ...]，按价格升序
-#[derive(Debug, Deserialize)]
+#[derive(Debug, Clone, Deserialize)]
 pub struct DepthUpdate {
+    #[serde(rename = "s")]
+    pub symbol: String,
     #[serde(rename = "T")]
     pub trans_time: u64,
+    #[serde(rename = "U", default)]
+    pub first_update_id: u64,
     #[serde(rename = "u")]
     pub update_id: u64,
+    #[serde(rename = "pu", default)]
+    pub prev_update_id: u64,
     #[serde(rename = "b")]
     pub bids: Vec<(String, String)>,
     #[serde(rename = "a")]
     pub asks: Vec<(String, String)>,
+}
+
+/// 订单簿 REST 快照
+///
+/// REST 接口 (`/fapi/v1/depth`) 的响应不带事件类型与交易对，
+/// 数据源获取快照后会补上 `e = "depthSnapshot"` 与 `s`，作为普通帧写入录制文件，
+/// 回放时即可按原始顺序重建本地订单簿。
+///
+/// # 字段
+/// - `symbol`: 交易对 (大写)
+/// - `trans_time`: 事务时间戳 (毫秒)
+/// - `last_update_id`: 快照对应的更新序号
+/// - `bids` / `asks`: 全部档位
+#[derive(Debug, Clone, Deserialize)]
+pub struct DepthSnapshot {
+    #[serde(rename = "s", default)]
+    pub symbol: String,
+    #[serde(rename = "T", default)]
+    pub trans_time: u64,
+    #[serde(rename = "lastUpdateId")]
+    pub last_update_id: u64,
+    pub bids: Vec<(String, String)>,
+    pub asks: Vec<(String, String)>,
+}
+
+impl DepthSnapshot {
+    /// 编码为 combined-stream 格式的帧文本，可被 `BinanceEvent::from_frame` 解析
+    pub fn to_frame(&self) -> String {
+        serde_json::json!({
+            "stream": format!("{}@depthSnapshot", self.symbol.to_lowercase()),
+            "data": {
+                "e": "depthSnapshot",
+                "s": self.symbol,
+                "T": self.trans_time,
+                "lastUpdateId": self.last_update_id,
+                "bids": self.bids,
+                "asks": self.asks,
+            }
+        })
+        .to_string()
+    }
 }
\ No newline at end of file
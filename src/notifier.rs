@@ -1,38 +1,47 @@
 use serde_json::json;
 use tracing::{info, error};
 
-/// 发送高波动率警报到 Slack
+use crate::engine::{TermAlert, TermAlertKind, VolAlert};
+
+/// 发送波动率状态切换警报到 Slack
 /// 
+/// 升档 (进入 Elevated / Extreme) 为高波动率警报，降档 (离开 Elevated / Extreme) 为回落通知。
+///
 /// # 参数
-/// - `vol`: 年化波动率 (1.0 = 100%)
-/// - `threshold`: 触发阈值 (%)
-/// - `raw_vol`: 原始 RMS 波动率
-/// - `dt_secs`: 计算窗口时长 (秒)
-/// - `current_price`: 当前价格
+/// - `alert`: 状态切换、波动率计算结果 (年化值、原始 RMS、窗口时长及各估计器的并列结果) 与成交价
 /// - `signal_time`: 信号时间字符串
-pub fn send_slack_alert(
-    webhook_url: String,
-    vol: f64,
-    threshold: f64,
-    raw_vol: f64,
-    dt_secs: f64,
-    current_price: f64,
-    signal_time: String,
-) {
+pub fn send_slack_alert(webhook_url: String, alert: &VolAlert, signal_time: String) {
     let client = reqwest::Client::new();
+    let vol = &alert.volatility;
 
-    let message = format!(
-        "🚨 *BTC High Volatility Alert* 🚨\n\
+    let (headline, threshold_label) = if alert.to > alert.from {
+        (format!("🚨 *{} High Volatility Alert* 🚨", alert.symbol.to_uppercase()), "进入阈值")
+    } else {
+        (format!("✅ *{} Volatility Easing*", alert.symbol.to_uppercase()), "退出阈值")
+    };
+    let mut message = format!(
+        "{}\n\
         > *时间*: `{}`\n\
-        > *波动率*: *{:.2}%* (阈值: {}%)\n\
+        > *状态*: `{}` → `{}`\n\
+        > *波动率*: *{:.2}* ({}, {}: {:.1})\n\
         > *当前价*: `${:.2}`\n\
         > *原始 RMS*: `{:.6}` | *窗口*: `{:.3}s`",
+        headline,
         signal_time,
-        vol * 100.0, threshold,
-        current_price,
-        raw_vol, dt_secs,
+        alert.from.name(), alert.to.name(),
+        vol.annualized, vol.estimator.name(), threshold_label, alert.threshold,
+        alert.price,
+        vol.raw_vol, vol.dt_secs,
     );
 
+    // 启用了多个估计器时并列展示
+    let estimates: Vec<String> = vol.estimates.iter()
+        .map(|(kind, v)| format!("`{} {:.2}`", kind.name(), v))
+        .collect();
+    if estimates.len() > 1 {
+        message.push_str(&format!("\n> *估计器*: {}", estimates.join(" | ")));
+    }
+
     tokio::spawn(async move {
         match client.post(webhook_url).json(&json!({"text": message})).send().await {
             Ok(_) => info!("🚀 Slack alert delivered successfully."),
@@ -41,6 +50,46 @@ pub fn send_slack_alert(
     });
 }
 
+/// 发送波动率期限结构形态警报到 Slack
+pub fn send_term_structure_alert(webhook_url: String, alert: &TermAlert, signal_time: String) {
+    let client = reqwest::Client::new();
+
+    let headline = match alert.kind {
+        TermAlertKind::Spike { ratio } => format!(
+            "短周期波动率飙升: {}s / {}s = *{:.2}x*",
+            alert.short_secs, alert.long_secs, ratio,
+        ),
+        TermAlertKind::VolOfVolJump { change } => format!(
+            "{}s 波动率跳变: *{:+.0}%* (vol-of-vol)",
+            alert.short_secs, (change.exp() - 1.0) * 100.0,
+        ),
+    };
+    let term: Vec<String> = alert.term.iter()
+        .map(|p| match p.vol {
+            Some(v) => format!("`{}s {:.1}`", p.horizon_secs, v),
+            None => format!("`{}s -`", p.horizon_secs),
+        })
+        .collect();
+
+    let message = format!(
+        "📐 *{} Vol Term Structure Alert*\n\
+        > *时间*: `{}`\n\
+        > {}\n\
+        > *期限结构*: {}",
+        alert.symbol.to_uppercase(),
+        signal_time,
+        headline,
+        term.join(" | "),
+    );
+
+    tokio::spawn(async move {
+        match client.post(webhook_url).json(&json!({"text": message})).send().await {
+            Ok(_) => info!("📐 Term structure alert delivered successfully."),
+            Err(e) => error!("❌ Failed to send term structure alert: {:?}", e),
+        }
+    });
+}
+
 pub fn send_histogram_report(webhook_url: String, report: String) {
     let client = reqwest::Client::new();
     tokio::spawn(async move {
@@ -51,6 +100,16 @@ pub fn send_histogram_report(webhook_url: String, report: String) {
     });
 }
 
+pub fn send_config_reload(webhook_url: String, summary: String) {
+    let client = reqwest::Client::new();
+    tokio::spawn(async move {
+        match client.post(webhook_url).json(&json!({"text": summary})).send().await {
+            Ok(_) => info!("🔄 Config reload notice delivered successfully."),
+            Err(e) => error!("❌ Failed to send config reload notice: {:?}", e),
+        }
+    });
+}
+
 // Sends a trend alert to Slack based on Order Flow Imbalance + VWAP analysis.
 // pub fn send_trend_alert(
 //     webhook_url: String,
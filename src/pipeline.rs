@@ -0,0 +1,904 @@
+//! 单交易对信号管线
+//!
+//! 每个交易对拥有独立的波动率指标、波动率状态分类器、趋势计算器、直方图统计与报警冷却，
+//! 由 `SignalEngine` 按事件的 `s` 字段分发。各方法接收的 `cfg` 是该交易对的配置
+//! (已合并 `symbol_overrides`，见 `MonitorConfig::for_symbol`)。
+//!
+//! 管线本身不做任何 IO：Telemetry、Slack 报警与直方图报告
+//! 都以 `SignalOutput` 的形式返回，由调用方决定如何投递。
+
+use crate::indicators::vol::{InstantVolatilityIndicator, VolatilityResult};
+use crate::indicators::term_structure::TermStructure;
+use crate::indicators::vol_regime::{RegimeChange, VolRegime, VolRegimeClassifier};
+use crate::indicators::calculators::{VwapCalculator, DepthCalculator, PriceFitter, FitResult};
+use crate::indicators::trend_state::{TrendStateMachine, TrendDirection, TrendEvent, ExitReason};
+use crate::indicators::order_book::{OrderBook, DiffOutcome};
+use crate::config::{MonitorConfig, DepthMode, VolatilityConfig, TermStructureConfig};
+use crate::stats::{RegimeStats, VolatilityStats};
+use crate::models::{AggTrade, DepthUpdate, DepthSnapshot};
+use crate::engine::{SignalOutput, TermAlert, TermAlertKind, VolAlert};
+use crate::telemetry::{SymbolState, TelemetryPacket, Transition};
+use crate::common::clock::Clock;
+use crate::common::units::Fraction;
+
+use std::sync::Arc;
+use tracing::{info, warn};
+
+/// 序号断档统计 (累计值，跨重连保留)
+#[derive(Debug, Default, Clone, Copy)]
+pub struct GapStats {
+    pub depth_gaps: u64,      // Diff 模式增量 `pu`/`U` 不连续次数
+    pub trade_gaps: u64,      // aggTrade `a` 跳号次数
+    pub missed_trades: u64,   // 跳号累计缺失的 aggTrade 数量
+}
+
+/// 趋势退出统计 (累计值，跨重连保留)
+#[derive(Debug, Default, Clone, Copy)]
+pub struct ExitStats {
+    pub price_fallback: u64,  // 价格回落退出次数
+    pub slope_reversal: u64,  // 斜率走弱退出次数
+    pub max_holding: u64,     // 持仓超时退出次数
+    pub trailing_stop: u64,   // 移动止损退出次数
+    pub total_pnl: f64,       // 累计估算盈亏 ($)
+    pub total_held_secs: f64, // 累计持仓时长 (秒)
+}
+
+impl ExitStats {
+    fn record(&mut self, reason: ExitReason, pnl_estimate: f64, held_secs: f64) {
+        match reason {
+            ExitReason::PriceFallback => self.price_fallback += 1,
+            ExitReason::SlopeReversal => self.slope_reversal += 1,
+            ExitReason::MaxHolding => self.max_holding += 1,
+            ExitReason::TrailingStop => self.trailing_stop += 1,
+        }
+        self.total_pnl += pnl_estimate;
+        self.total_held_secs += held_secs;
+    }
+
+    pub fn count(&self) -> u64 {
+        self.price_fallback + self.slope_reversal + self.max_holding + self.trailing_stop
+    }
+}
+
+/// 单个交易对的完整信号管线
+///
+/// 所有 "当前时间" 都来自注入的 `Clock`，回放时与实盘行为一致。
+/// 波动率指标与直方图统计跨重连保留；订单簿、VWAP 与趋势状态
+/// 在每次连接建立时通过 `reset_session()` 重建。
+pub struct SymbolPipeline {
+    symbol: String,              // 交易对 (小写，例如 btcusdt)
+
+    clock: Arc<dyn Clock>,
+    vol_calc: InstantVolatilityIndicator,
+    stats: VolatilityStats,
+    last_hist_ms: Option<u64>,   // 本轮直方图统计开始时间，首次调用时初始化
+    last_vol: Option<VolatilityResult>,
+    last_trade_ms: Option<u64>,
+
+    // 波动率状态 (驱动 "V" 信号、价差与 Slack 报警)
+    regime: VolRegimeClassifier,
+    regime_stats: RegimeStats,   // 本轮直方图周期内各状态的停留时间
+
+    // 波动率期限结构
+    term: TermStructure,
+    last_term_publish_ms: Option<u64>, // 上次随 Telemetry 包下发期限结构的成交时间
+    last_term_check_ms: Option<u64>,   // 上次检查形态报警的成交时间
+    last_short_vol: Option<Fraction>,  // 上次检查时的短周期波动率 (vol-of-vol)
+    last_term_alert_ms: Option<u64>,
+
+    // 趋势计算器
+    vwap_calc: VwapCalculator,
+    depth_calc: DepthCalculator,
+    order_book: OrderBook,       // 完整深度 (仅 Diff 模式)
+    fitter_5s: PriceFitter,
+    fitter_2s: PriceFitter,
+    trend_sm: TrendStateMachine,
+
+    current_cum_ofi: f64,
+    last_fit_2s: Option<FitResult>,
+    last_vol_alert_ms: Option<u64>,
+    last_agg_id: u64,            // 用于检测重复与跳号的 aggTrade 消息
+    last_depth_id: u64,          // 上一条 depth20 推送的 u，用于丢弃乱序推送
+
+    gaps: GapStats,
+    exits: ExitStats,
+    last_gap_ms: Option<u64>,    // 最近一次断档时间，用于降级标志
+}
+
+/// 按配置创建波动率计算器 (含所选估计器)
+fn volatility_indicator(cfg: &VolatilityConfig, clock: Arc<dyn Clock>) -> InstantVolatilityIndicator {
+    let mut vol_calc = InstantVolatilityIndicator::new_with_clock(
+        cfg.window(),
+        cfg.stale_threshold_ms,
+        cfg.fallback_volatility_pct.to_fraction(),
+        cfg.expire_threshold_ms,
+        clock,
+    );
+    vol_calc.set_estimators(cfg.estimator.build(cfg), cfg.report_estimators.iter().map(|k| k.build(cfg)).collect());
+    vol_calc
+}
+
+fn term_structure(cfg: &TermStructureConfig) -> TermStructure {
+    TermStructure::new(&cfg.horizons_secs, cfg.bucket_ms, cfg.min_samples)
+}
+
+impl SymbolPipeline {
+    pub fn new(symbol: &str, cfg: &MonitorConfig, clock: Arc<dyn Clock>) -> Self {
+        Self {
+            symbol: symbol.to_lowercase(),
+            vol_calc: volatility_indicator(&cfg.volatility, clock.clone()),
+            clock,
+            stats: VolatilityStats::new(cfg.histogram.step_pct.to_fraction(), cfg.histogram.buckets),
+            last_hist_ms: None,
+            last_vol: None,
+            last_trade_ms: None,
+            regime: VolRegimeClassifier::new(cfg.regime_thresholds()),
+            regime_stats: RegimeStats::default(),
+            term: term_structure(&cfg.term_structure),
+            last_term_publish_ms: None,
+            last_term_check_ms: None,
+            last_short_vol: None,
+            last_term_alert_ms: None,
+            vwap_calc: VwapCalculator::new(cfg.trend.vwap_window_ms, cfg.trend.vwap_series_max_len),
+            depth_calc: DepthCalculator::new(cfg.trend.ofi_cum_window_secs, cfg.trend.ofi_decay),
+            order_book: OrderBook::new(),
+            fitter_5s: PriceFitter::new(cfg.trend.fit_window_secs, cfg.trend.fit_min_points, cfg.trend.fit_min_r2),
+            fitter_2s: PriceFitter::new(cfg.trend.fit_window_2s, cfg.trend.fit_min_points / 2, cfg.trend.fit_min_r2),
+            trend_sm: TrendStateMachine::new(cfg.trend.clone()),
+            current_cum_ofi: 0.0,
+            last_fit_2s: None,
+            last_vol_alert_ms: None,
+            last_agg_id: 0,
+            last_depth_id: 0,
+            gaps: GapStats::default(),
+            exits: ExitStats::default(),
+            last_gap_ms: None,
+        }
+    }
+
+    /// 热加载新配置
+    ///
+    /// 趋势状态机、拟合器与波动率状态分类器直接换用新参数 (保留持仓状态与当前档位)；报警冷却每次读取配置，
+    /// 无需处理。窗口类参数变化时重建对应计算器，其窗口数据会重新积累。
+    pub fn apply_config(&mut self, old: &MonitorConfig, new: &MonitorConfig) {
+        self.trend_sm.set_config(new.trend.clone());
+        self.regime.set_thresholds(new.regime_thresholds());
+        self.fitter_5s = PriceFitter::new(new.trend.fit_window_secs, new.trend.fit_min_points, new.trend.fit_min_r2);
+        self.fitter_2s = PriceFitter::new(new.trend.fit_window_2s, new.trend.fit_min_points / 2, new.trend.fit_min_r2);
+
+        let (o, n) = (&old.trend, &new.trend);
+        if o.vwap_window_ms != n.vwap_window_ms || o.vwap_series_max_len != n.vwap_series_max_len {
+            self.vwap_calc = VwapCalculator::new(n.vwap_window_ms, n.vwap_series_max_len);
+            self.last_fit_2s = None;
+            warn!("⚠️ [{}] VWAP parameters changed, VWAP series reset.", self.symbol);
+        }
+        if o.ofi_cum_window_secs != n.ofi_cum_window_secs || o.ofi_decay != n.ofi_decay {
+            self.depth_calc = DepthCalculator::new(n.ofi_cum_window_secs, n.ofi_decay);
+            self.current_cum_ofi = 0.0;
+            warn!("⚠️ [{}] OFI parameters changed, OFI state reset.", self.symbol);
+        }
+
+        let (o, n) = (&old.volatility, &new.volatility);
+        if o.window() != n.window()
+            || o.stale_threshold_ms != n.stale_threshold_ms
+            || o.fallback_volatility_pct != n.fallback_volatility_pct
+            || o.expire_threshold_ms != n.expire_threshold_ms
+        {
+            self.vol_calc = volatility_indicator(n, self.clock.clone());
+            warn!("⚠️ [{}] Volatility window parameters changed, window reset.", self.symbol);
+        } else {
+            // 估计器不持有窗口数据，直接替换
+            self.vol_calc.set_estimators(n.estimator.build(n), n.report_estimators.iter().map(|k| k.build(n)).collect());
+        }
+        // histogram.step_pct / buckets 在下一个报告周期生效
+
+        let (o, n) = (&old.term_structure, &new.term_structure);
+        if o.horizons_secs != n.horizons_secs || o.bucket_ms != n.bucket_ms || o.min_samples != n.min_samples {
+            self.term = term_structure(n);
+            self.last_short_vol = None;
+            warn!("⚠️ [{}] Term structure horizons changed, term structure reset.", self.symbol);
+        }
+    }
+
+    /// 新连接建立时重置订单簿与趋势状态（波动率窗口与统计保留）
+    pub fn reset_session(&mut self, cfg: &MonitorConfig) {
+        self.vwap_calc = VwapCalculator::new(cfg.trend.vwap_window_ms, cfg.trend.vwap_series_max_len);
+        self.depth_calc = DepthCalculator::new(cfg.trend.ofi_cum_window_secs, cfg.trend.ofi_decay);
+        self.order_book = OrderBook::new();
+        self.trend_sm = TrendStateMachine::new(cfg.trend.clone());
+        self.current_cum_ofi = 0.0;
+        self.last_fit_2s = None;
+        self.last_agg_id = 0;
+        self.last_depth_id = 0;
+        self.last_gap_ms = None;
+    }
+
+    pub fn symbol(&self) -> &str {
+        &self.symbol
+    }
+
+    /// 判断事件中的交易对 (币安为大写) 是否属于本管线
+    pub fn matches(&self, symbol: &str) -> bool {
+        self.symbol.eq_ignore_ascii_case(symbol)
+    }
+
+    pub fn gap_stats(&self) -> GapStats {
+        self.gaps
+    }
+
+    pub fn exit_stats(&self) -> ExitStats {
+        self.exits
+    }
+
+    /// 当前信号状态 (供 Telemetry 快照)
+    pub fn state(&self, cfg: &MonitorConfig) -> SymbolState {
+        let impact_price = self.depth_calc.get_impact_price();
+        SymbolState {
+            symbol: self.symbol.to_uppercase(),
+            last_trade_ms: self.last_trade_ms,
+            volatility: self.last_vol,
+            trend_state: self.trend_sm.get_state(),
+            direction: self.trend_sm.get_direction(),
+            entry_slope: self.trend_sm.is_holding().then(|| self.trend_sm.entry_slope()),
+            cum_ofi: self.current_cum_ofi,
+            impact_price: (impact_price > 0.0).then_some(impact_price),
+            degraded: self.is_degraded(cfg),
+            term_structure: if cfg.term_structure.enabled { self.term.points() } else { Vec::new() },
+            regime: self.regime.regime(),
+        }
+    }
+
+    /// 记录一次断档：清空 OFI 状态并进入降级
+    fn mark_gap(&mut self) {
+        self.depth_calc.reset_ofi();
+        self.current_cum_ofi = 0.0;
+        self.last_gap_ms = Some(self.clock.now_ms());
+    }
+
+    /// 信号是否处于降级状态
+    ///
+    /// - 断档后一个 OFI 累积窗口内 (OFI 仍在重新积累)
+    /// - Diff 模式下本地订单簿未同步
+    pub fn is_degraded(&self, cfg: &MonitorConfig) -> bool {
+        if cfg.order_book.mode == DepthMode::Diff && !self.order_book.is_synced() {
+            return true;
+        }
+        let window_ms = (cfg.trend.ofi_cum_window_secs * 1000.0) as u64;
+        self.last_gap_ms
+            .map(|t| self.clock.now_ms().saturating_sub(t) < window_ms)
+            .unwrap_or(false)
+    }
+
+    /// 到达报告周期时生成直方图报告并重置统计
+    pub fn maybe_report_histogram(&mut self, cfg: &MonitorConfig, out: &mut Vec<SignalOutput>) {
+        let now_ms = self.clock.now_ms();
+        let start_ms = *self.last_hist_ms.get_or_insert(now_ms);
+        if now_ms.saturating_sub(start_ms) / 1000 < cfg.histogram.interval {
+            return;
+        }
+        let report = self.stats.generate_report(cfg.histogram.interval / 60);
+        let exits = self.exits;
+        let n = exits.count().max(1) as f64;
+        out.push(SignalOutput::HistogramReport {
+            symbol: self.symbol.clone(),
+            report: format!(
+                "*{}*\n{}\n{}\nℹ️ Sequence gaps (total): depth `{}` | aggTrade `{}` (missed `{}` trades)\n\
+                 ℹ️ Trend exits (total): price fallback `{}` | slope reversal `{}` | max holding `{}` | trailing stop `{}` | \
+                 avg pnl `${:.2}` | avg hold `{:.1}s`",
+                self.symbol.to_uppercase(), report, self.regime_stats.summary(),
+                self.gaps.depth_gaps, self.gaps.trade_gaps, self.gaps.missed_trades,
+                exits.price_fallback, exits.slope_reversal, exits.max_holding, exits.trailing_stop,
+                exits.total_pnl / n, exits.total_held_secs / n,
+            ),
+        });
+        self.stats = VolatilityStats::new(cfg.histogram.step_pct.to_fraction(), cfg.histogram.buckets);
+        self.regime_stats.reset();
+        self.last_hist_ms = Some(now_ms);
+    }
+
+    /// 处理一笔 aggTrade，产生的信号追加到 `out`
+    pub fn on_trade(
+        &mut self,
+        trade: &AggTrade,
+        cfg: &MonitorConfig,
+        out: &mut Vec<SignalOutput>,
+    ) -> Result<(), Box<dyn std::error::Error>> {
+        // 检测重复消息
+        if trade.agg_id <= self.last_agg_id {
+            return Ok(());
+        }
+        // 检测跳号 (丢失的 aggTrade)
+        if self.last_agg_id != 0 && trade.agg_id > self.last_agg_id + 1 {
+            let missed = trade.agg_id - self.last_agg_id - 1;
+            self.gaps.trade_gaps += 1;
+            self.gaps.missed_trades += missed;
+            warn!(
+                "⚠️ [{}] aggTrade gap: {} -> {} ({} missed, {} gaps total). OFI reset.",
+                self.symbol, self.last_agg_id, trade.agg_id, missed, self.gaps.trade_gaps,
+            );
+            self.mark_gap();
+        }
+        self.last_agg_id = trade.agg_id;
+
+        let p: f64 = trade.price.parse()?;
+        let q: f64 = trade.quantity.parse()?;
+        let trade_ms = trade.trade_time;
+        let first_output = out.len();
+
+        // 波动率计算
+        self.vol_calc.update(p, trade_ms);
+        let vol_res = self.vol_calc.get_volatility();
+        self.last_vol = Some(vol_res);
+        self.last_trade_ms = Some(trade_ms);
+        if cfg.term_structure.enabled {
+            self.term.update(p, trade_ms);
+        }
+
+        // OFI 计算器添加成交
+        self.depth_calc.add_trade(trade_ms, p, q, trade.is_buyer_maker);
+
+        // VWAP 计算 + 拟合 + 状态机更新
+        let mut transitions = Vec::new();
+        if let Some(_vwap_point) = self.vwap_calc.add_trade(p, q, trade_ms) {
+            let current_ts_sec = trade_ms as f64 / 1000.0;
+            let fit_5s = self.fitter_5s.fit(self.vwap_calc.get_series(), trade_ms);
+            let fit_2s = self.fitter_2s.fit(self.vwap_calc.get_series(), trade_ms);
+
+            // 保存 fit_2s 用于后续价差计算
+            self.last_fit_2s = fit_2s;
+
+            let latest_price = self.vwap_calc.get_series().back()
+                .map(|pt| pt.price)
+                .unwrap_or(p);
+
+            // 状态机更新
+            if let Some(event) = self.trend_sm.update(
+                current_ts_sec,
+                fit_5s.as_ref(),
+                self.current_cum_ofi,
+                latest_price,
+            ) {
+                self.log_trend_event(&event, trade_ms, latest_price);
+                if let TrendEvent::Exited { reason, pnl_estimate, held_secs } = event {
+                    self.exits.record(reason, pnl_estimate, held_secs);
+                }
+                transitions = Self::transitions(&event);
+            }
+        }
+
+        // 波动率统计
+        if self.vol_calc.is_ready() && !vol_res.is_stale {
+            self.stats.record(vol_res.annualized);
+        }
+
+        // 波动率状态分类 (带滞回)；窗口未就绪或断流时保持当前档位，不使用 fallback 值
+        let regime_change = if self.vol_calc.is_ready() && !vol_res.is_stale {
+            self.regime.update(vol_res.annualized, trade_ms)
+        } else {
+            None
+        };
+        let regime = self.regime.regime();
+        self.regime_stats.record(regime, trade_ms);
+
+        // 获取冲击价格
+        let impact_price = self.depth_calc.get_impact_price();
+        let degraded = self.is_degraded(cfg);
+
+        // 转换事件包先于本笔成交的信号包发出，客户端以最后一个包为准
+        if let Some(change) = regime_change {
+            self.log_regime_change(&change, trade_ms);
+            out.push(SignalOutput::Telemetry(TelemetryPacket {
+                timestamp: trade_ms,
+                symbol: self.symbol.to_uppercase(),
+                source: "N".to_string(),
+                ask_adjust: 0.0,
+                bid_adjust: 0.0,
+                degraded,
+                event: Some(regime_transition(change.to)),
+                term_vols: None,
+            }));
+            if let Some(alert) = self.regime_alert(&change, vol_res, p, trade_ms, cfg) {
+                out.push(SignalOutput::VolAlert(alert));
+            }
+        }
+
+        let mut entered = false;
+        for t in transitions {
+            let (ask_adj, bid_adj) = match t {
+                Transition::EnterLong | Transition::EnterShort => {
+                    entered = true;
+                    self.trend_adjust(self.trend_sm.get_direction(), impact_price, cfg)
+                }
+                _ => (0.0, 0.0),
+            };
+            out.push(SignalOutput::Telemetry(TelemetryPacket {
+                timestamp: trade_ms,
+                symbol: self.symbol.to_uppercase(),
+                source: t.source().to_string(),
+                ask_adjust: ask_adj,
+                bid_adjust: bid_adj,
+                degraded,
+                event: Some(t),
+                term_vols: None,
+            }));
+        }
+
+        // 高波动率处理 (Elevated / Extreme)
+        if regime >= VolRegime::Elevated {
+            let spread_adj = if regime == VolRegime::Extreme {
+                cfg.regime.extreme_spread_adjust
+            } else {
+                cfg.volatility.spread_adjust
+            };
+            out.push(SignalOutput::Telemetry(TelemetryPacket {
+                timestamp: trade_ms,
+                symbol: self.symbol.to_uppercase(),
+                source: "V".to_string(),
+                ask_adjust: spread_adj,
+                bid_adjust: -spread_adj,
+                degraded,
+                event: None,
+                term_vols: None,
+            }));
+        } else {
+            // 检查趋势 (刚入场时已由转换事件包发出)
+            let direction = self.trend_sm.get_direction();
+            if direction != TrendDirection::Neutral && !entered {
+                let (ask_adj, bid_adj) = self.trend_adjust(direction, impact_price, cfg);
+                let source = if direction == TrendDirection::Long { "U" } else { "D" };
+
+                out.push(SignalOutput::Telemetry(TelemetryPacket {
+                    timestamp: trade_ms,
+                    symbol: self.symbol.to_uppercase(),
+                    source: source.to_string(),
+                    ask_adjust: ask_adj,
+                    bid_adjust: bid_adj,
+                    degraded,
+                    event: None,
+                    term_vols: None,
+                }));
+            }
+        }
+
+        if cfg.term_structure.enabled {
+            self.publish_term_structure(trade_ms, &mut out[first_output..], cfg);
+            self.check_term_alerts(trade_ms, cfg, out);
+        }
+
+        Ok(())
+    }
+
+    /// 状态切换对应的 Slack 报警
+    ///
+    /// - 升档进入 Elevated / Extreme: 高波动率报警 (带冷却)
+    /// - 降档离开 Elevated / Extreme: 回落通知 (不受冷却限制)
+    fn regime_alert(
+        &mut self,
+        change: &RegimeChange,
+        vol_res: VolatilityResult,
+        price: f64,
+        trade_ms: u64,
+        cfg: &MonitorConfig,
+    ) -> Option<VolAlert> {
+        let thresholds = self.regime.thresholds();
+        let threshold = if change.is_upgrade() {
+            if change.to < VolRegime::Elevated {
+                return None;
+            }
+            let now_ms = self.clock.now_ms();
+            let should_alert = self.last_vol_alert_ms
+                .map(|t| now_ms.saturating_sub(t) / 1000 >= cfg.cooldown_secs)
+                .unwrap_or(true);
+            if !should_alert {
+                return None;
+            }
+            self.last_vol_alert_ms = Some(now_ms);
+            thresholds.enter(change.to)
+        } else {
+            if change.from < VolRegime::Elevated {
+                return None;
+            }
+            thresholds.exit(change.from)
+        };
+
+        Some(VolAlert {
+            symbol: self.symbol.clone(),
+            timestamp_ms: trade_ms,
+            volatility: vol_res,
+            price,
+            from: change.from,
+            to: change.to,
+            threshold,
+        })
+    }
+
+    /// 距上次下发超过 `publish_interval_ms` 时，将期限结构附加到本笔成交的最后一个 Telemetry 包
+    ///
+    /// 没有信号包时不下发，由 Telemetry 心跳包携带。
+    fn publish_term_structure(&mut self, trade_ms: u64, outputs: &mut [SignalOutput], cfg: &MonitorConfig) {
+        let interval = cfg.term_structure.publish_interval_ms;
+        if self.last_term_publish_ms.is_some_and(|t| trade_ms.saturating_sub(t) < interval) {
+            return;
+        }
+        let packet = outputs.iter_mut().rev().find_map(|o| match o {
+            SignalOutput::Telemetry(packet) => Some(packet),
+            _ => None,
+        });
+        if let Some(packet) = packet {
+            packet.term_vols = Some(self.term.points().iter().map(|p| p.vol).collect());
+            self.last_term_publish_ms = Some(trade_ms);
+        }
+    }
+
+    /// 每个 `publish_interval_ms` 检查一次期限结构形态 (带冷却)
+    ///
+    /// - 短周期 / 长周期波动率 ≥ `spike_ratio`
+    /// - 短周期波动率相对上次检查的 |ln(σ_t / σ_t-1)| ≥ `vol_of_vol_jump`
+    fn check_term_alerts(&mut self, trade_ms: u64, cfg: &MonitorConfig, out: &mut Vec<SignalOutput>) {
+        let ts = &cfg.term_structure;
+        if self.last_term_check_ms.is_some_and(|t| trade_ms.saturating_sub(t) < ts.publish_interval_ms) {
+            return;
+        }
+        self.last_term_check_ms = Some(trade_ms);
+
+        let points = self.term.points();
+        let vol_at = |secs: f64| ts.horizon_index(secs).and_then(|i| points.get(i)).and_then(|p| p.vol);
+        let Some(short) = vol_at(ts.short_secs) else {
+            self.last_short_vol = None;
+            return;
+        };
+
+        let mut kinds = Vec::new();
+        if let (Some(threshold), Some(long)) = (ts.spike_ratio, vol_at(ts.long_secs))
+            && long.0 > 0.0
+            && short.0 / long.0 >= threshold
+        {
+            kinds.push(TermAlertKind::Spike { ratio: short.0 / long.0 });
+        }
+        if let (Some(threshold), Some(prev)) = (ts.vol_of_vol_jump, self.last_short_vol)
+            && prev.0 > 0.0
+            && short.0 > 0.0
+            && (short.0 / prev.0).ln().abs() >= threshold
+        {
+            kinds.push(TermAlertKind::VolOfVolJump { change: (short.0 / prev.0).ln() });
+        }
+        self.last_short_vol = Some(short);
+        if kinds.is_empty() {
+            return;
+        }
+
+        let now_ms = self.clock.now_ms();
+        let should_alert = self.last_term_alert_ms
+            .map(|t| now_ms.saturating_sub(t) / 1000 >= cfg.cooldown_secs)
+            .unwrap_or(true);
+        if !should_alert {
+            return;
+        }
+        self.last_term_alert_ms = Some(now_ms);
+        for kind in kinds {
+            warn!("⚠️ [{}] Term structure alert: {:?}", self.symbol, kind);
+            out.push(SignalOutput::TermAlert(TermAlert {
+                symbol: self.symbol.clone(),
+                timestamp_ms: trade_ms,
+                kind,
+                short_secs: ts.short_secs,
+                long_secs: ts.long_secs,
+                term: points.clone(),
+            }));
+        }
+    }
+
+    /// 趋势方向对应的 (ask, bid) 价差调整：预测价格与冲击价格的偏差
+    fn trend_adjust(&self, direction: TrendDirection, impact_price: f64, cfg: &MonitorConfig) -> (f64, f64) {
+        let price_diff = match self.last_fit_2s {
+            Some(ref fit) if fit.is_valid && impact_price > 0.0 => {
+                let predicted = self.fitter_2s.predict(fit, cfg.trend.predict_horizon_secs);
+                (predicted - impact_price).abs()
+            }
+            _ => cfg.volatility.spread_adjust,
+        };
+
+        match direction {
+            TrendDirection::Long => (price_diff, 0.0),
+            TrendDirection::Short => (0.0, -price_diff),
+            TrendDirection::Neutral => (0.0, 0.0),
+        }
+    }
+
+    /// 状态机事件对应的 Telemetry 转换事件 (退出同时开始冷却)
+    fn transitions(event: &TrendEvent) -> Vec<Transition> {
+        match event {
+            TrendEvent::Entered { direction: TrendDirection::Long, .. } => vec![Transition::EnterLong],
+            TrendEvent::Entered { direction: TrendDirection::Short, .. } => vec![Transition::EnterShort],
+            TrendEvent::Entered { direction: TrendDirection::Neutral, .. } => vec![],
+            TrendEvent::Exited { reason, .. } => {
+                let exit = match reason {
+                    ExitReason::PriceFallback => Transition::ExitPriceFallback,
+                    ExitReason::SlopeReversal => Transition::ExitSlopeReversal,
+                    ExitReason::MaxHolding => Transition::ExitMaxHolding,
+                    ExitReason::TrailingStop => Transition::ExitTrailingStop,
+                };
+                vec![exit, Transition::CooldownStart]
+            }
+            TrendEvent::CooldownEnded => vec![Transition::CooldownEnd],
+        }
+    }
+
+    fn log_trend_event(&self, event: &TrendEvent, trade_ms: u64, price: f64) {
+        let symbol = self.symbol.as_str();
+        match *event {
+            TrendEvent::Entered { direction, slope, ofi } => info!(
+                symbol, event = "enter", ?direction, slope, ofi, trade_ms, price,
+                "📈 [{}] Trend entered {:?} (slope {:.2} $/s, OFI {:.2})", symbol, direction, slope, ofi,
+            ),
+            TrendEvent::Exited { reason, pnl_estimate, held_secs } => {
+                info!(
+                    symbol, event = "exit", ?reason, pnl_estimate, held_secs, trade_ms, price,
+                    "📉 [{}] Trend exited: {:?} (pnl ≈ ${:.2}, held {:.1}s)", symbol, reason, pnl_estimate, held_secs,
+                );
+                info!(symbol, event = "cooldown_start", trade_ms, "⏳ [{}] Trend cooldown started", symbol);
+            }
+            TrendEvent::CooldownEnded => info!(
+                symbol, event = "cooldown_end", trade_ms,
+                "⏳ [{}] Trend cooldown ended", symbol,
+            ),
+        }
+    }
+
+    fn log_regime_change(&self, change: &RegimeChange, trade_ms: u64) {
+        let symbol = self.symbol.as_str();
+        info!(
+            symbol, event = "regime", from = change.from.name(), to = change.to.name(),
+            vol = change.vol.0, dwell_secs = change.dwell_secs, trade_ms,
+            "🌡️ [{}] Vol regime {} -> {} (vol {:.2}, after {:.1}s)",
+            symbol, change.from.name(), change.to.name(), change.vol, change.dwell_secs,
+        );
+    }
+
+    /// 处理一条深度推送
+    ///
+    /// - `Partial` 模式: depth20 快照直接用于 OFI 与冲击价格，乱序推送丢弃
+    /// - `Diff` 模式: 增量先应用到本地订单簿，再以完整深度计算；
+    ///   序号断档时订单簿失效并请求重新同步
+    pub fn on_depth(&mut self, depth: &DepthUpdate, cfg: &MonitorConfig, out: &mut Vec<SignalOutput>) {
+        let bids = parse_levels(&depth.bids);
+        let asks = parse_levels(&depth.asks);
+
+        match cfg.order_book.mode {
+            DepthMode::Partial => {
+                // depth20 的 pu 指向底层增量流的上一条 u，而不是上一条 depth20 推送，
+                // 相邻推送之间本就不连续，无法据此判断断档；这里只丢弃乱序 / 重复的推送
+                if depth.update_id <= self.last_depth_id {
+                    return;
+                }
+                self.last_depth_id = depth.update_id;
+                self.update_depth_metrics(depth.update_id, depth.trans_time, &bids, &asks, cfg.trend.impact_qty);
+            }
+            DepthMode::Diff => {
+                let outcome = self.order_book.apply_diff(
+                    depth.first_update_id,
+                    depth.update_id,
+                    depth.prev_update_id,
+                    &bids,
+                    &asks,
+                );
+                match outcome {
+                    DiffOutcome::Applied => {
+                        let (full_bids, full_asks) = self.order_book.levels();
+                        self.update_depth_metrics(
+                            depth.update_id,
+                            depth.trans_time,
+                            &full_bids,
+                            &full_asks,
+                            cfg.trend.impact_qty,
+                        );
+                    }
+                    DiffOutcome::Gap { expected, got } => {
+                        self.record_depth_gap(expected, got);
+                        out.push(SignalOutput::ResyncRequired { symbol: self.symbol.clone() });
+                    }
+                    DiffOutcome::Stale | DiffOutcome::NotSynced => {}
+                }
+            }
+        }
+    }
+
+    fn record_depth_gap(&mut self, expected: u64, got: u64) {
+        self.gaps.depth_gaps += 1;
+        warn!(
+            "⚠️ [{}] Depth sequence gap: expected pu={}, got {} ({} gaps total). OFI reset.",
+            self.symbol, expected, got, self.gaps.depth_gaps,
+        );
+        self.mark_gap();
+    }
+
+    /// 处理订单簿 REST 快照
+    pub fn on_snapshot(&mut self, snapshot: &DepthSnapshot) {
+        let bids = parse_levels(&snapshot.bids);
+        let asks = parse_levels(&snapshot.asks);
+        self.order_book.apply_snapshot(snapshot.last_update_id, &bids, &asks);
+        info!(
+            "📚 [{}] Order book snapshot loaded: lastUpdateId={} ({} bids / {} asks)",
+            self.symbol, snapshot.last_update_id, bids.len(), asks.len(),
+        );
+    }
+
+    /// 用订单簿更新 OFI 状态与冲击价格
+    fn update_depth_metrics(
+        &mut self,
+        update_id: u64,
+        trans_time: u64,
+        bids: &[(f64, f64)],
+        asks: &[(f64, f64)],
+        impact_qty: f64,
+    ) {
+        // 更新 OFI 状态
+        if let Some((_raw_ofi, cum_ofi, _mid_price)) = self.depth_calc.update_depth(
+            update_id,
+            trans_time,
+            bids,
+            asks,
+        ) {
+            self.current_cum_ofi = cum_ofi;
+        }
+
+        // 计算冲击价格 (`trend.impact_qty` 个基础币)
+        self.depth_calc.calculate_impact_price(bids, asks, impact_qty);
+    }
+
+    pub fn order_book(&self) -> &OrderBook {
+        &self.order_book
+    }
+}
+
+/// 波动率状态对应的 Telemetry 转换事件
+fn regime_transition(regime: VolRegime) -> Transition {
+    match regime {
+        VolRegime::Calm => Transition::RegimeCalm,
+        VolRegime::Normal => Transition::RegimeNormal,
+        VolRegime::Elevated => Transition::RegimeElevated,
+        VolRegime::Extreme => Transition::RegimeExtreme,
+    }
+}
+
+/// 解析字符串档位，忽略无法解析的条目
+fn parse_levels(levels: &[(String, String)]) -> Vec<(f64, f64)> {
+    levels.iter()
+        .filter_map(|(p, q)| Some((p.parse().ok()?, q.parse().ok()?)))
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::common::clock::EventClock;
+
+    fn depth20(update_id: u64, prev_update_id: u64) -> DepthUpdate {
+        DepthUpdate {
+            symbol: "BTCUSDT".to_string(),
+            trans_time: 1_700_000_000_000 + update_id,
+            first_update_id: update_id - 5,
+            update_id,
+            prev_update_id,
+            bids: vec![("50000.00".to_string(), "1.0".to_string())],
+            asks: vec![("50000.10".to_string(), "1.0".to_string())],
+        }
+    }
+
+    #[test]
+    fn partial_depth_ignores_pu_chain() {
+        let mut cfg: MonitorConfig = serde_yaml::from_str(include_str!("../config.example.yaml")).unwrap();
+        cfg.order_book.mode = DepthMode::Partial;
+        let mut pipeline = SymbolPipeline::new("btcusdt", &cfg, Arc::new(EventClock::new(0)));
+        let mut out = Vec::new();
+
+        // 相邻 depth20 推送的 pu 指向底层增量流，不等于上一条推送的 u
+        for (u, pu) in [(100, 97), (110, 104), (118, 115), (118, 115), (112, 108), (130, 125)] {
+            pipeline.on_depth(&depth20(u, pu), &cfg, &mut out);
+        }
+        assert_eq!(pipeline.gap_stats().depth_gaps, 0);
+        assert_eq!(pipeline.last_depth_id, 130);
+        assert!(out.is_empty());
+    }
+
+    const T0: u64 = 1_700_000_000_000;
+
+    /// 1s / 10s 两个周期，只开启传入的形态报警
+    fn term_config(spike_ratio: Option<f64>, vol_of_vol_jump: Option<f64>) -> MonitorConfig {
+        let mut cfg: MonitorConfig = serde_yaml::from_str(include_str!("../config.example.yaml")).unwrap();
+        cfg.symbols = vec!["btcusdt".to_string()];
+        cfg.symbol_overrides.clear();
+        let ts = &mut cfg.term_structure;
+        ts.enabled = true;
+        ts.horizons_secs = vec![1.0, 10.0];
+        ts.short_secs = 1.0;
+        ts.long_secs = 10.0;
+        ts.bucket_ms = 100;
+        ts.min_samples = 5;
+        ts.publish_interval_ms = 1_000;
+        ts.spike_ratio = spike_ratio;
+        ts.vol_of_vol_jump = vol_of_vol_jump;
+        cfg
+    }
+
+    /// 每 100ms 一笔、价格在两档间来回：第 100-109 笔 (10.0s-10.9s) 振幅 50 USDT，其余 0.5 USDT
+    fn burst_price(i: u64) -> f64 {
+        let amplitude = if (100..110).contains(&i) { 50.0 } else { 0.5 };
+        50_000.0 + (i % 2) as f64 * amplitude
+    }
+
+    /// 依次喂入第 0..n 笔成交，返回期限结构报警
+    fn run_term(cfg: &MonitorConfig, n: u64) -> Vec<TermAlert> {
+        let clock = Arc::new(EventClock::new(T0));
+        let mut pipeline = SymbolPipeline::new("btcusdt", cfg, clock.clone());
+        let mut alerts = Vec::new();
+        for i in 0..n {
+            let ts = T0 + i * 100;
+            clock.observe(ts);
+            let trade = AggTrade {
+                symbol: "BTCUSDT".to_string(),
+                agg_id: i + 1,
+                trade_time: ts,
+                price: format!("{:.2}", burst_price(i)),
+                quantity: "0.1".to_string(),
+                is_buyer_maker: i % 2 == 0,
+            };
+            let mut out = Vec::new();
+            pipeline.on_trade(&trade, cfg, &mut out).unwrap();
+            alerts.extend(out.into_iter().filter_map(|o| match o {
+                SignalOutput::TermAlert(a) => Some(a),
+                _ => None,
+            }));
+        }
+        alerts
+    }
+
+    #[test]
+    fn spike_alert_fires_once_when_short_vol_outgrows_long() {
+        let cfg = term_config(Some(2.0), None);
+        let alerts = run_term(&cfg, 121);
+
+        // 11.0s 的检查：1s 周期全是大振幅，10s 周期被平静期稀释 (约 sqrt(10) 倍)
+        assert_eq!(alerts.len(), 1, "{:?}", alerts);
+        let alert = &alerts[0];
+        assert_eq!(alert.timestamp_ms, T0 + 11_000);
+        assert_eq!((alert.short_secs, alert.long_secs), (1.0, 10.0));
+        let (short, long) = (alert.term[0].vol.unwrap().0, alert.term[1].vol.unwrap().0);
+        match alert.kind {
+            TermAlertKind::Spike { ratio } => {
+                assert!((ratio - short / long).abs() < 1e-12);
+                assert!((2.0..4.0).contains(&ratio), "ratio {}", ratio);
+            }
+            other => panic!("unexpected {:?}", other),
+        }
+
+        // 未设置 spike_ratio 时关闭
+        assert!(run_term(&term_config(None, None), 121).is_empty());
+    }
+
+    #[test]
+    fn vol_of_vol_alert_tracks_jumps_in_short_vol() {
+        let mut cfg = term_config(None, Some(0.7));
+        cfg.cooldown_secs = 1;
+        let alerts = run_term(&cfg, 160);
+
+        // 第一笔大振幅收益率在 10.1s (10.0s 的价格与前一笔相同)
+        // 11s: 1s 周期全是大振幅收益率，Σr² 增加约 10⁴ 倍；
+        // 12s: 只剩 11.0s 回落的一笔，下降约 10 倍；13s: 回到平静期
+        let seen: Vec<(u64, bool)> = alerts.iter()
+            .map(|a| match a.kind {
+                TermAlertKind::VolOfVolJump { change } => {
+                    assert!(change.abs() >= 0.7, "change {}", change);
+                    ((a.timestamp_ms - T0) / 1_000, change > 0.0)
+                }
+                other => panic!("unexpected {:?}", other),
+            })
+            .collect();
+        assert_eq!(seen, vec![(11, true), (12, false), (13, false)]);
+
+        // 默认冷却 (15s) 下只报第一次
+        cfg.cooldown_secs = 15;
+        assert_eq!(run_term(&cfg, 160).len(), 1);
+        assert!(run_term(&term_config(None, None), 160).is_empty());
+    }
+}
@@ -0,0 +1,196 @@
+//! 原始行情录制与回放
+//!
+//! 将 combined-stream 的每一帧原始文本连同接收时间戳写入 gzip 压缩文件，
+//! 回放时逐帧读出，交给与实盘完全相同的解析与信号管线处理。
+//!
+//! # 文件格式
+//! gzip 压缩的文本，每行一帧：
+//! ```text
+//! <recv_ms>\t<原始 JSON 帧>
+//! ```
+//! 每次启动写入一个新文件 (见 `session_path`)，正常退出时由 `finish()` 写入 gzip 尾部。
+//! 进程被强制结束时最后一个 deflate 块不完整，但每 `FLUSH_EVERY_FRAMES` 帧一次的
+//! sync flush 之前的数据仍可解压；`FrameReader` 将结尾的 `UnexpectedEof` 视为数据结束。
+//! 读取时使用 `MultiGzDecoder`，多个文件拼接后也可以连续读出。
+
+use std::fs::{File, OpenOptions};
+use std::io::{self, BufRead, BufReader, BufWriter, Write};
+use std::path::{Path, PathBuf};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use chrono::{DateTime, Local};
+use flate2::Compression;
+use flate2::read::MultiGzDecoder;
+use flate2::write::GzEncoder;
+use tracing::warn;
+
+/// 每写入多少帧强制 flush 一次，限制进程崩溃时丢失的数据量
+const FLUSH_EVERY_FRAMES: u64 = 1000;
+
+/// 录制的一帧原始数据
+#[derive(Debug, Clone)]
+pub struct RecordedFrame {
+    pub recv_ms: u64,   // 本地接收时间戳 (毫秒)
+    pub text: String,   // 原始 JSON 文本
+}
+
+/// 本次会话的录制文件路径：在 `base` 的文件名与扩展名之间插入启动时间，例如
+/// `recordings/session.jsonl.gz` -> `recordings/session-20261016-102600.jsonl.gz`
+pub fn session_path(base: &Path, started: DateTime<Local>) -> PathBuf {
+    let stamp = started.format("%Y%m%d-%H%M%S");
+    let name = base.file_name().map(|n| n.to_string_lossy().into_owned()).unwrap_or_default();
+    let name = match name.split_once('.') {
+        Some((stem, ext)) => format!("{}-{}.{}", stem, stamp, ext),
+        None => format!("{}-{}", name, stamp),
+    };
+    base.with_file_name(name)
+}
+
+/// 原始帧录制器
+pub struct FrameRecorder {
+    encoder: GzEncoder<BufWriter<File>>,
+    frames_since_flush: u64,
+    path: PathBuf,
+}
+
+impl FrameRecorder {
+    /// 创建新的录制文件；文件已存在时报错，不会追加到旧录制之后
+    pub fn create<P: AsRef<Path>>(path: P) -> io::Result<Self> {
+        let file = OpenOptions::new().write(true).create_new(true).open(&path)?;
+        Ok(Self {
+            encoder: GzEncoder::new(BufWriter::new(file), Compression::fast()),
+            frames_since_flush: 0,
+            path: path.as_ref().to_path_buf(),
+        })
+    }
+
+    /// 以 `record_path` 为基础，按当前时间创建本次会话的录制文件
+    pub fn create_session<P: AsRef<Path>>(base: P) -> io::Result<Self> {
+        Self::create(session_path(base.as_ref(), Local::now()))
+    }
+
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    /// 使用当前系统时间作为接收时间写入一帧
+    pub fn record(&mut self, text: &str) -> io::Result<()> {
+        let recv_ms = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .unwrap()
+            .as_millis() as u64;
+        self.record_at(recv_ms, text)
+    }
+
+    /// 写入一帧 (指定接收时间)
+    pub fn record_at(&mut self, recv_ms: u64, text: &str) -> io::Result<()> {
+        // 币安推送的 JSON 为紧凑格式，不含换行；保险起见仍然跳过异常帧
+        if text.contains('\n') {
+            return Ok(());
+        }
+        writeln!(self.encoder, "{}\t{}", recv_ms, text)?;
+
+        self.frames_since_flush += 1;
+        if self.frames_since_flush >= FLUSH_EVERY_FRAMES {
+            self.flush()?;
+        }
+        Ok(())
+    }
+
+    pub fn flush(&mut self) -> io::Result<()> {
+        self.frames_since_flush = 0;
+        self.encoder.flush()
+    }
+
+    /// 写入 gzip 尾部并关闭文件
+    pub fn finish(self) -> io::Result<()> {
+        self.encoder.finish()?.flush()
+    }
+}
+
+/// 录制文件读取器，按顺序迭代 `RecordedFrame`
+pub struct FrameReader {
+    lines: io::Lines<BufReader<MultiGzDecoder<File>>>,
+    done: bool,
+}
+
+impl FrameReader {
+    pub fn open<P: AsRef<Path>>(path: P) -> io::Result<Self> {
+        let file = File::open(path)?;
+        Ok(Self {
+            lines: BufReader::new(MultiGzDecoder::new(file)).lines(),
+            done: false,
+        })
+    }
+}
+
+impl Iterator for FrameReader {
+    type Item = io::Result<RecordedFrame>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.done {
+            return None;
+        }
+        loop {
+            let line = match self.lines.next()? {
+                Ok(l) => l,
+                // 未正常结束的录制 (进程被强制结束)：之前的数据有效，其后视为结束
+                Err(e) if e.kind() == io::ErrorKind::UnexpectedEof => {
+                    warn!("⚠️ Recording ends with an unfinished gzip block ({}); stopping there.", e);
+                    self.done = true;
+                    return None;
+                }
+                Err(e) => return Some(Err(e)),
+            };
+            // 跳过格式不正确的行 (例如崩溃时写了一半的最后一行)
+            let Some((ts, text)) = line.split_once('\t') else { continue };
+            let Ok(recv_ms) = ts.parse() else { continue };
+            return Some(Ok(RecordedFrame { recv_ms, text: text.to_string() }));
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::TimeZone;
+
+    fn temp_path(name: &str) -> PathBuf {
+        std::env::temp_dir().join(format!("bnvol-{}-{}.jsonl.gz", name, std::process::id()))
+    }
+
+    #[test]
+    fn session_path_inserts_start_time_before_extension() {
+        let started = Local.with_ymd_and_hms(2026, 10, 16, 10, 26, 0).unwrap();
+        assert_eq!(
+            session_path(Path::new("recordings/session.jsonl.gz"), started),
+            PathBuf::from("recordings/session-20261016-102600.jsonl.gz"),
+        );
+        assert_eq!(session_path(Path::new("rec"), started), PathBuf::from("rec-20261016-102600"));
+    }
+
+    #[test]
+    fn create_refuses_to_append_to_existing_recording() {
+        let path = temp_path("exists");
+        FrameRecorder::create(&path).unwrap().finish().unwrap();
+        assert_eq!(FrameRecorder::create(&path).err().map(|e| e.kind()), Some(io::ErrorKind::AlreadyExists));
+        std::fs::remove_file(&path).unwrap();
+    }
+
+    #[test]
+    fn unfinished_recording_reads_up_to_last_flush() {
+        let path = temp_path("unfinished");
+        let mut recorder = FrameRecorder::create(&path).unwrap();
+        for i in 0..3 {
+            recorder.record_at(1000 + i, &format!("{{\"n\":{}}}", i)).unwrap();
+        }
+        recorder.flush().unwrap();
+        // 模拟进程被强制结束：既不 finish 也不运行 Drop
+        std::mem::forget(recorder);
+
+        let frames: Vec<RecordedFrame> = FrameReader::open(&path).unwrap().collect::<io::Result<_>>().unwrap();
+        assert_eq!(frames.iter().map(|f| f.recv_ms).collect::<Vec<_>>(), vec![1000, 1001, 1002]);
+        assert_eq!(frames[2].text, "{\"n\":2}");
+        std::fs::remove_file(&path).unwrap();
+    }
+}
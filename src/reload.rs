@@ -0,0 +1,218 @@
+//! 配置热加载
+//!
+//! 配置文件被修改 (按修改时间轮询) 或进程收到 SIGHUP 时重新加载并校验，
+//! 通过 `watch` 通道交给运行中的引擎，由 `SignalEngine::apply_config` 在两个事件之间整体替换，
+//! 因此不会断开币安连接或 Telemetry 客户端，也不会出现新旧参数混用的中间状态。
+//! 加载或校验失败时保留当前配置。
+//!
+//! `symbols`、`order_book`、`telemetry`、`record_path` 需要重建连接或服务，
+//! 修改后仅提示需要重启，不会热生效。
+
+use std::collections::BTreeMap;
+use std::future::pending;
+use std::sync::Arc;
+use std::time::{Duration, SystemTime};
+
+use serde_yaml::Value;
+use tokio::sync::watch;
+use tracing::{error, info};
+
+use crate::config::MonitorConfig;
+
+/// 配置文件修改时间的轮询间隔
+pub const POLL_INTERVAL: Duration = Duration::from_secs(2);
+
+/// 只能重启生效的顶层字段
+pub const RESTART_ONLY_FIELDS: [&str; 4] = ["symbols", "order_book", "telemetry", "record_path"];
+
+/// 监听配置变化，返回接收新配置的通道
+///
+/// `adjust` 在每次加载成功后调用，用于保留命令行施加的运行时修改 (例如 `--dry-run`)。
+pub fn spawn_config_watcher<F>(
+    path: String,
+    initial: MonitorConfig,
+    adjust: F,
+) -> watch::Receiver<Arc<MonitorConfig>>
+where
+    F: Fn(&mut MonitorConfig) + Send + 'static,
+{
+    let (tx, rx) = watch::channel(Arc::new(initial));
+
+    tokio::spawn(async move {
+        let mut last_modified = modified_time(&path);
+        let mut ticker = tokio::time::interval(POLL_INTERVAL);
+        ticker.set_missed_tick_behavior(tokio::time::MissedTickBehavior::Skip);
+        let mut hangup = hangup_signal();
+
+        loop {
+            let trigger = tokio::select! {
+                _ = ticker.tick() => {
+                    let modified = modified_time(&path);
+                    if modified == last_modified {
+                        continue;
+                    }
+                    last_modified = modified;
+                    "file change"
+                }
+                _ = recv_hangup(&mut hangup) => "SIGHUP",
+            };
+
+            info!("🔄 Reloading config from {} ({})", path, trigger);
+            match MonitorConfig::load_from(&path) {
+                Ok(mut cfg) => {
+                    adjust(&mut cfg);
+                    if tx.send(Arc::new(cfg)).is_err() {
+                        break;
+                    }
+                }
+                Err(e) => error!("❌ Config reload rejected, keeping current config: {}", e),
+            }
+        }
+    });
+
+    rx
+}
+
+fn modified_time(path: &str) -> Option<SystemTime> {
+    std::fs::metadata(path).and_then(|m| m.modified()).ok()
+}
+
+#[cfg(unix)]
+type Hangup = Option<tokio::signal::unix::Signal>;
+#[cfg(not(unix))]
+type Hangup = ();
+
+#[cfg(unix)]
+fn hangup_signal() -> Hangup {
+    use tokio::signal::unix::{signal, SignalKind};
+    match signal(SignalKind::hangup()) {
+        Ok(s) => Some(s),
+        Err(e) => {
+            error!("❌ Failed to install SIGHUP handler: {}", e);
+            None
+        }
+    }
+}
+
+#[cfg(not(unix))]
+fn hangup_signal() -> Hangup {}
+
+#[cfg(unix)]
+async fn recv_hangup(hangup: &mut Hangup) {
+    if let Some(s) = hangup.as_mut()
+        && s.recv().await.is_some()
+    {
+        return;
+    }
+    pending().await
+}
+
+#[cfg(not(unix))]
+async fn recv_hangup(_hangup: &mut Hangup) {
+    pending().await
+}
+
+/// 对比两份配置，返回 "路径: 旧值 → 新值" 列表 (webhook 地址已脱敏)
+pub fn diff_configs(old: &MonitorConfig, new: &MonitorConfig) -> Vec<String> {
+    let flatten_cfg = |cfg: &MonitorConfig| {
+        let mut out = BTreeMap::new();
+        if let Ok(v) = serde_yaml::to_value(cfg) {
+            flatten("", &v, &mut out);
+        }
+        out
+    };
+    let old_map = flatten_cfg(old);
+    let new_map = flatten_cfg(new);
+
+    let missing = "∅".to_string();
+    let mut keys: Vec<&String> = old_map.keys().chain(new_map.keys()).collect();
+    keys.sort();
+    keys.dedup();
+    keys.into_iter()
+        .filter_map(|key| {
+            let before = old_map.get(key).unwrap_or(&missing);
+            let after = new_map.get(key).unwrap_or(&missing);
+            if before == after {
+                return None;
+            }
+            if key == "slack_webhook_url" {
+                return Some(format!("{}: <changed>", key));
+            }
+            Some(format!("{}: {} → {}", key, before, after))
+        })
+        .collect()
+}
+
+/// 展开为 点分路径 -> 标量文本；列表整体作为一个值
+fn flatten(prefix: &str, value: &Value, out: &mut BTreeMap<String, String>) {
+    match value {
+        Value::Mapping(map) => {
+            for (k, v) in map {
+                let key = k.as_str().map(str::to_string).unwrap_or_else(|| format!("{:?}", k));
+                let path = if prefix.is_empty() { key } else { format!("{}.{}", prefix, key) };
+                flatten(&path, v, out);
+            }
+        }
+        other => {
+            let text = match other {
+                Value::String(s) => s.clone(),
+                _ => serde_json::to_string(other).unwrap_or_default(),
+            };
+            out.insert(prefix.to_string(), text);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::common::units::Percent;
+    use crate::indicators::vol::EstimatorKind;
+
+    fn example() -> MonitorConfig {
+        serde_yaml::from_str(include_str!("../config.example.yaml")).expect("config.example.yaml parses")
+    }
+
+    #[test]
+    fn identical_configs_have_no_diff() {
+        assert!(diff_configs(&example(), &example()).is_empty());
+    }
+
+    #[test]
+    fn diff_lists_changed_paths_in_order() {
+        let old = example();
+        let mut new = example();
+        new.threshold_pct = Percent(75.0);
+        new.trend.max_holding_secs = Some(30.0);
+        new.volatility.report_estimators = vec![EstimatorKind::Ewma];
+        new.symbol_overrides.remove("ethusdt");
+
+        let changes = diff_configs(&old, &new);
+        assert_eq!(changes, vec![
+            "symbol_overrides.ethusdt.trend.impact_qty: 20.0 → ∅",
+            "symbol_overrides.ethusdt.trend.max_price_fallback: 1.75 → ∅",
+            "symbol_overrides.ethusdt.trend.min_price_fallback: 0.5 → ∅",
+            "symbol_overrides.ethusdt.trend.slope_threshold: 0.2 → ∅",
+            "symbol_overrides.ethusdt.volatility.spread_adjust: 0.5 → ∅",
+            "threshold_pct: 60.0 → 75.0",
+            "trend.max_holding_secs: null → 30.0",
+            "volatility.report_estimators: [] → [\"ewma\"]",
+        ]);
+    }
+
+    #[test]
+    fn webhook_url_never_appears_in_diff() {
+        let mut old = example();
+        old.slack_webhook_url = "https://hooks.slack.com/services/T000/B000/OLDSECRET".to_string();
+        let mut new = old.clone();
+        new.slack_webhook_url = "https://hooks.slack.com/services/T000/B000/NEWSECRET".to_string();
+        new.cooldown_secs += 1;
+
+        let changes = diff_configs(&old, &new);
+        assert_eq!(changes.len(), 2);
+        assert!(changes.contains(&"slack_webhook_url: <changed>".to_string()), "{:?}", changes);
+        for change in &changes {
+            assert!(!change.contains("SECRET") && !change.contains("hooks.slack.com"), "{}", change);
+        }
+    }
+}
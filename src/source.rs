@@ -0,0 +1,308 @@
+//! 行情数据源抽象
+//!
+//! `MarketDataSource` 把 "从哪里拿到 `BinanceEvent`" 与信号逻辑解耦：
+//! - `BinanceWsSource`: 实盘 combined-stream WebSocket (可选同时录制原始帧)，
+//!   Diff 模式下负责拉取 REST 订单簿快照
+//! - `ReplaySource`: 回放 `recorder` 模块写出的 gzip 录制文件
+//! - `VecSource`: 内存中的事件序列，用于单元测试
+//!
+//! 数据源只负责产出事件，下游统一交给 `SignalEngine` 处理。
+
+use std::collections::VecDeque;
+use std::future::Future;
+use std::path::Path;
+
+use futures_util::{SinkExt, StreamExt};
+use tokio::net::TcpStream;
+use tokio_tungstenite::{connect_async, MaybeTlsStream, WebSocketStream};
+use tokio_tungstenite::tungstenite::protocol::Message;
+use tracing::{error, warn};
+
+use crate::config::{DepthMode, MonitorConfig, OrderBookConfig};
+use crate::models::{BinanceEvent, DepthSnapshot};
+use crate::recorder::{FrameReader, FrameRecorder};
+
+/// 行情数据源：异步产出 `BinanceEvent` 序列
+pub trait MarketDataSource {
+    /// 获取下一个事件
+    ///
+    /// - `Ok(Some(event))`: 新事件
+    /// - `Ok(None)`: 数据源正常结束 (连接关闭 / 文件读完)
+    /// - `Err(e)`: 数据源异常，调用方应重连或终止
+    fn next_event(&mut self) -> impl Future<Output = Result<Option<BinanceEvent>, Box<dyn std::error::Error>>>;
+
+    /// 请求重新同步某个交易对的订单簿 (重新拉取快照，之后由 `next_event` 产出)
+    ///
+    /// 回放与内存数据源无法主动拉取，默认忽略；录制文件中已包含实盘当时的快照。
+    fn resync(&mut self, _symbol: &str) -> impl Future<Output = Result<(), Box<dyn std::error::Error>>> {
+        async { Ok(()) }
+    }
+}
+
+// ============================================================================
+// 实盘 WebSocket
+// ============================================================================
+
+/// 根据交易对列表与深度模式拼接 combined-stream 地址
+pub fn stream_url(symbols: &[String], mode: DepthMode) -> String {
+    let depth_stream = match mode {
+        DepthMode::Partial => "depth20@100ms",
+        DepthMode::Diff => "depth@100ms",
+    };
+    let streams: Vec<String> = symbols.iter()
+        .map(|s| {
+            let s = s.to_lowercase();
+            format!("{s}@aggTrade/{s}@{depth_stream}")
+        })
+        .collect();
+    format!("wss://fstream.binance.com/stream?streams={}", streams.join("/"))
+}
+
+/// 从 `cfg.snapshot_url` 拉取一个交易对的 REST 快照
+///
+/// REST 响应不带交易对，这里按请求的交易对 (大写) 补上。
+pub async fn fetch_snapshot(
+    http: &reqwest::Client,
+    cfg: &OrderBookConfig,
+    symbol: &str,
+) -> Result<DepthSnapshot, Box<dyn std::error::Error>> {
+    let mut snapshot: DepthSnapshot = http
+        .get(&cfg.snapshot_url)
+        .query(&[("symbol", symbol.to_uppercase()), ("limit", cfg.snapshot_limit.to_string())])
+        .send()
+        .await?
+        .error_for_status()?
+        .json()
+        .await?;
+    snapshot.symbol = symbol.to_uppercase();
+    Ok(snapshot)
+}
+
+/// 币安 combined-stream WebSocket 数据源
+pub struct BinanceWsSource<'a> {
+    ws: WebSocketStream<MaybeTlsStream<TcpStream>>,
+    recorder: Option<&'a mut FrameRecorder>,
+    book_cfg: OrderBookConfig,
+    http: reqwest::Client,
+    pending: VecDeque<BinanceEvent>,   // 待产出的快照事件 (优先于 WS 帧)
+}
+
+impl<'a> BinanceWsSource<'a> {
+    /// 连接币安；若提供 `recorder`，每一帧原始文本都会先写入录制文件
+    ///
+    /// Diff 模式下先订阅增量流，再为每个交易对拉取 REST 快照，
+    /// 快照在连接建立后最先产出；早于快照的增量由 `OrderBook` 丢弃。
+    pub async fn connect(
+        cfg: &MonitorConfig,
+        recorder: Option<&'a mut FrameRecorder>,
+    ) -> Result<Self, Box<dyn std::error::Error>> {
+        let url = stream_url(&cfg.symbols, cfg.order_book.mode);
+        let (ws, _) = connect_async(url.as_str()).await?;
+        let mut source = Self {
+            ws,
+            recorder,
+            book_cfg: cfg.order_book.clone(),
+            http: reqwest::Client::new(),
+            pending: VecDeque::new(),
+        };
+
+        if cfg.order_book.mode == DepthMode::Diff {
+            for symbol in &cfg.symbols {
+                source.resync(symbol).await?;
+            }
+        }
+        Ok(source)
+    }
+
+    fn record(&mut self, text: &str) {
+        if let Some(rec) = self.recorder.as_deref_mut()
+            && let Err(e) = rec.record(text)
+        {
+            warn!("⚠️ Failed to record frame: {}", e);
+        }
+    }
+}
+
+impl MarketDataSource for BinanceWsSource<'_> {
+    async fn next_event(&mut self) -> Result<Option<BinanceEvent>, Box<dyn std::error::Error>> {
+        if let Some(event) = self.pending.pop_front() {
+            return Ok(Some(event));
+        }
+
+        while let Some(message) = self.ws.next().await {
+            let msg = match message {
+                Ok(m) => m,
+                Err(e) => { error!("WS Error: {:?}", e); return Err(Box::new(e)); }
+            };
+
+            match msg {
+                Message::Text(text_bytes) => {
+                    let text = text_bytes.as_str();
+                    self.record(text);
+                    if let Some(event) = BinanceEvent::from_frame(text) {
+                        return Ok(Some(event));
+                    }
+                }
+                Message::Ping(payload) => { self.ws.send(Message::Pong(payload)).await?; }
+                Message::Close(_) => { break; }
+                _ => (),
+            }
+        }
+
+        if let Some(rec) = self.recorder.as_deref_mut()
+            && let Err(e) = rec.flush()
+        {
+            warn!("⚠️ Failed to flush recording: {}", e);
+        }
+        Ok(None)
+    }
+
+    async fn resync(&mut self, symbol: &str) -> Result<(), Box<dyn std::error::Error>> {
+        let snapshot = fetch_snapshot(&self.http, &self.book_cfg, symbol).await?;
+        // 快照同样写入录制文件，保证回放时订单簿可以重建
+        self.record(&snapshot.to_frame());
+        self.pending.push_back(BinanceEvent::Snapshot(snapshot));
+        Ok(())
+    }
+}
+
+// ============================================================================
+// 录制文件回放
+// ============================================================================
+
+/// 录制文件回放数据源，按录制顺序尽快产出事件 (不按原始节奏等待)
+pub struct ReplaySource {
+    reader: FrameReader,
+}
+
+impl ReplaySource {
+    pub fn open<P: AsRef<Path>>(path: P) -> std::io::Result<Self> {
+        Ok(Self { reader: FrameReader::open(path)? })
+    }
+}
+
+impl MarketDataSource for ReplaySource {
+    async fn next_event(&mut self) -> Result<Option<BinanceEvent>, Box<dyn std::error::Error>> {
+        for frame in self.reader.by_ref() {
+            let frame = frame?;
+            if let Some(event) = BinanceEvent::from_frame(&frame.text) {
+                return Ok(Some(event));
+            }
+        }
+        Ok(None)
+    }
+}
+
+// ============================================================================
+// 内存序列
+// ============================================================================
+
+/// 内存事件序列数据源
+pub struct VecSource {
+    events: VecDeque<BinanceEvent>,
+}
+
+impl VecSource {
+    pub fn new(events: Vec<BinanceEvent>) -> Self {
+        Self { events: events.into() }
+    }
+}
+
+impl MarketDataSource for VecSource {
+    async fn next_event(&mut self) -> Result<Option<BinanceEvent>, Box<dyn std::error::Error>> {
+        Ok(self.events.pop_front())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io::{BufRead, BufReader, Write};
+    use std::net::TcpListener;
+    use std::sync::mpsc;
+
+    use super::*;
+
+    /// 本地 mock 快照接口：对每个连接返回一次 `status` + `body`，并把请求行发回测试
+    fn mock_snapshot_endpoint(status: &'static str, body: &'static str, requests: usize) -> (String, mpsc::Receiver<String>) {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let url = format!("http://{}/fapi/v1/depth", listener.local_addr().unwrap());
+        let (tx, rx) = mpsc::channel();
+        std::thread::spawn(move || {
+            for stream in listener.incoming().take(requests) {
+                let mut stream = stream.unwrap();
+                let mut reader = BufReader::new(stream.try_clone().unwrap());
+                let mut request_line = String::new();
+                reader.read_line(&mut request_line).unwrap();
+                let mut header = String::new();
+                while reader.read_line(&mut header).unwrap() > 0 && header != "\r\n" {
+                    header.clear();
+                }
+                write!(
+                    stream,
+                    "HTTP/1.1 {}\r\nContent-Type: application/json\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{}",
+                    status, body.len(), body,
+                ).unwrap();
+                tx.send(request_line.trim_end().to_string()).unwrap();
+            }
+        });
+        (url, rx)
+    }
+
+    fn book_cfg(snapshot_url: String) -> OrderBookConfig {
+        OrderBookConfig { mode: DepthMode::Diff, snapshot_url, snapshot_limit: 100 }
+    }
+
+    #[tokio::test]
+    async fn fetch_snapshot_parses_mock_endpoint() {
+        let body = r#"{"lastUpdateId":1027024,"E":1589436922972,"T":1589436922959,"bids":[["4.00000000","431.00000000"]],"asks":[["4.00000200","12.00000000"]]}"#;
+        let (url, requests) = mock_snapshot_endpoint("200 OK", body, 1);
+
+        let snapshot = fetch_snapshot(&reqwest::Client::new(), &book_cfg(url), "btcusdt").await.unwrap();
+        assert_eq!(snapshot.symbol, "BTCUSDT");
+        assert_eq!(snapshot.last_update_id, 1_027_024);
+        assert_eq!(snapshot.trans_time, 1_589_436_922_959);
+        assert_eq!(snapshot.bids, vec![("4.00000000".to_string(), "431.00000000".to_string())]);
+        assert_eq!(snapshot.asks, vec![("4.00000200".to_string(), "12.00000000".to_string())]);
+        assert_eq!(requests.recv().unwrap(), "GET /fapi/v1/depth?symbol=BTCUSDT&limit=100 HTTP/1.1");
+
+        // 补上交易对后的快照帧可被重新解析，回放时据此重建订单簿
+        match BinanceEvent::from_frame(&snapshot.to_frame()) {
+            Some(BinanceEvent::Snapshot(s)) => assert_eq!(s.last_update_id, 1_027_024),
+            other => panic!("unexpected event: {:?}", other),
+        }
+    }
+
+    #[tokio::test]
+    async fn fetch_snapshot_reports_http_errors() {
+        let (url, _requests) = mock_snapshot_endpoint("429 Too Many Requests", r#"{"code":-1003}"#, 1);
+        assert!(fetch_snapshot(&reqwest::Client::new(), &book_cfg(url), "btcusdt").await.is_err());
+    }
+
+    fn trade_frame(agg_id: u64, price: f64) -> String {
+        format!(
+            r#"{{"stream":"btcusdt@aggTrade","data":{{"e":"aggTrade","s":"BTCUSDT","a":{},"T":{},"p":"{}","q":"0.1","m":false}}}}"#,
+            agg_id, 1_700_000_000_000 + agg_id * 100, price,
+        )
+    }
+
+    #[tokio::test]
+    async fn replay_of_unfinished_recording_ends_cleanly() {
+        let path = std::env::temp_dir().join(format!("bnvol-replay-unfinished-{}.jsonl.gz", std::process::id()));
+        let mut recorder = FrameRecorder::create(&path).unwrap();
+        for i in 1..=5 {
+            recorder.record_at(i, &trade_frame(i, 50_000.0 + i as f64)).unwrap();
+        }
+        recorder.flush().unwrap();
+        std::mem::forget(recorder);
+
+        let mut source = ReplaySource::open(&path).unwrap();
+        let mut ids = Vec::new();
+        while let Some(event) = source.next_event().await.unwrap() {
+            if let BinanceEvent::Trade(t) = event {
+                ids.push(t.agg_id);
+            }
+        }
+        assert_eq!(ids, vec![1, 2, 3, 4, 5]);
+        std::fs::remove_file(&path).unwrap();
+    }
+}
@@ -1,11 +1,14 @@
+use crate::common::units::Fraction;
+use crate::indicators::vol_regime::VolRegime;
+
 pub struct VolatilityStats {
     pub buckets: Vec<usize>,
     pub count: u32,
-    pub step: f64,
+    pub step: Fraction,
 }
 
 impl VolatilityStats {
-    pub fn new(step: f64, bucket_count: usize) -> Self {
+    pub fn new(step: Fraction, bucket_count: usize) -> Self {
         Self {
             buckets: vec![0; bucket_count],
             count: 0,
@@ -14,12 +17,12 @@ impl VolatilityStats {
     }
 
     /// Records a new volatility sample into the appropriate bucket.
-    pub fn record(&mut self, vol: f64) {
+    pub fn record(&mut self, vol: Fraction) {
         self.count += 1;
         let max_idx = self.buckets.len() - 1;
 
         // Calculate bucket index based on step size.
-        let mut index = (vol / self.step) as usize;
+        let mut index = (vol.0 / self.step.0) as usize;
 
         // Clamp index to the last bucket if volatility exceeds the max range.
         if index > max_idx {
@@ -38,8 +41,8 @@ impl VolatilityStats {
         let active_buckets = self.buckets.iter().filter(|&&c| c > 0).count();
 
         let mut report = format!(
-            "📊 *Volatility Distribution ({} min)*\nStep: `{:.2}%` | Total Samples: `{}`\n```\n",
-            interval_minutes, self.step * 100.0, self.count
+            "📊 *Volatility Distribution ({} min)*\nStep: `{:.2}` | Total Samples: `{}`\n```\n",
+            interval_minutes, self.step, self.count
         );
         let mut has_data = false;
 
@@ -54,8 +57,8 @@ impl VolatilityStats {
             }
             has_data = true;
 
-            let lower = i as f64 * self.step * 100.0;
-            let upper = (i + 1) as f64 * self.step * 100.0;
+            let lower = i as f64 * self.step.to_percent().0;
+            let upper = (i + 1) as f64 * self.step.to_percent().0;
 
             // --- Color Logic (Emoji Heatmap) ---
             // 0-20%: Low (Blue)
@@ -99,4 +102,41 @@ impl VolatilityStats {
         report.push_str("```");
         report
     }
-}
\ No newline at end of file
+}
+
+/// Time spent in each volatility regime during one histogram interval.
+#[derive(Default)]
+pub struct RegimeStats {
+    pub millis: [u64; 4],
+    pub transitions: u32,
+    last: Option<(VolRegime, u64)>,
+}
+
+impl RegimeStats {
+    /// Records the regime in effect at `timestamp_ms`.
+    /// The time since the previous call is attributed to the previous regime.
+    pub fn record(&mut self, regime: VolRegime, timestamp_ms: u64) {
+        if let Some((prev, prev_ms)) = self.last {
+            self.millis[prev.index()] += timestamp_ms.saturating_sub(prev_ms);
+            if prev != regime {
+                self.transitions += 1;
+            }
+        }
+        self.last = Some((regime, timestamp_ms));
+    }
+
+    /// Starts a new interval, carrying over the current regime so no time is lost.
+    pub fn reset(&mut self) {
+        *self = Self { last: self.last, ..Self::default() };
+    }
+
+    /// One-line summary for the histogram report.
+    pub fn summary(&self) -> String {
+        let total = self.millis.iter().sum::<u64>().max(1) as f64;
+        let shares: Vec<String> = VolRegime::ALL
+            .iter()
+            .map(|r| format!("{} `{:.1}%`", r.name(), self.millis[r.index()] as f64 / total * 100.0))
+            .collect();
+        format!("ℹ️ Vol regime: {} | transitions `{}`", shares.join(" | "), self.transitions)
+    }
+}
@@ -0,0 +1,502 @@
+//! 参数网格搜索
+//!
+//! 在同一份录制数据上并行回测多组参数组合，输出按信号质量排序的排行榜。
+//!
+//! # 搜索空间文件
+//! 键为 `MonitorConfig` 中字段的点分路径，值为候选列表或等差区间：
+//! ```yaml
+//! params:
+//!   trend.slope_threshold: [3.0, 4.0, 5.0]
+//!   trend.ofi_decay: { start: 0.6, end: 0.9, step: 0.1 }
+//!   trend.cooldown_secs: [0.5, 1.0]
+//!   volatility.window_size: [20, 50, 100]
+//! ```
+//! 未出现的字段沿用基础配置。路径不存在或类型不匹配会在开始前报错。
+//!
+//! 被 `symbol_overrides` 中某个交易对覆盖的字段，改动顶层值对该交易对无效，因此同样在开始前
+//! 报错；需要分别搜索时直接写交易对内的路径，如 `symbol_overrides.ethusdt.trend.slope_threshold`。
+//!
+//! 回放出错的组合不会被丢弃，而是以 `error` 列出现在排行榜末尾。
+
+use std::collections::BTreeMap;
+use std::fmt::Write as _;
+use std::sync::Arc;
+use std::sync::atomic::{AtomicUsize, Ordering};
+
+use serde::{Deserialize, Serialize};
+use serde_yaml::Value;
+use tracing::warn;
+
+use crate::backtest::{BacktestReport, Backtester, FORWARD_HORIZONS_MS};
+use crate::common::clock::EventClock;
+use crate::config::{MonitorConfig, SYMBOL_OVERRIDE_FIELDS};
+use crate::engine::SignalEngine;
+use crate::models::BinanceEvent;
+
+/// 单个参数的取值范围
+#[derive(Debug, Clone, Deserialize)]
+#[serde(untagged)]
+pub enum ParamRange {
+    /// 显式候选值列表
+    List(Vec<Value>),
+    /// 等差区间 [start, end]，包含端点
+    Step { start: f64, end: f64, step: f64 },
+}
+
+impl ParamRange {
+    fn values(&self) -> Result<Vec<Value>, String> {
+        match self {
+            ParamRange::List(v) => Ok(v.clone()),
+            ParamRange::Step { start, end, step } => {
+                if *step <= 0.0 || end < start {
+                    return Err(format!("invalid range {{start: {}, end: {}, step: {}}}", start, end, step));
+                }
+                let n = ((end - start) / step + 1e-9).floor() as usize;
+                Ok((0..=n)
+                    .map(|i| {
+                        // 四舍五入到 1e-10，避免 0.1 累加产生的 0.30000000000000004
+                        let v = ((start + i as f64 * step) * 1e10).round() / 1e10;
+                        Value::from(v)
+                    })
+                    .collect())
+            }
+        }
+    }
+}
+
+/// 搜索空间定义
+#[derive(Debug, Clone, Deserialize)]
+pub struct SweepSpec {
+    pub params: BTreeMap<String, ParamRange>,
+}
+
+impl SweepSpec {
+    pub fn load_from(path: &str) -> Result<Self, Box<dyn std::error::Error>> {
+        let yaml_content = std::fs::read_to_string(path)
+            .map_err(|e| format!("❌ Failed to read {}: {}", path, e))?;
+        let spec: SweepSpec = serde_yaml::from_str(&yaml_content)
+            .map_err(|e| format!("❌ Failed to parse {}: {}", path, e))?;
+        Ok(spec)
+    }
+
+    /// 展开为全部参数组合 (笛卡尔积)
+    pub fn combinations(&self) -> Result<Vec<Vec<(String, Value)>>, String> {
+        let mut combos: Vec<Vec<(String, Value)>> = vec![Vec::new()];
+        for (path, range) in &self.params {
+            let values = range.values().map_err(|e| format!("{}: {}", path, e))?;
+            if values.is_empty() {
+                return Err(format!("{}: no candidate values", path));
+            }
+            combos = combos.into_iter()
+                .flat_map(|base| {
+                    values.iter().map(move |v| {
+                        let mut c = base.clone();
+                        c.push((path.clone(), v.clone()));
+                        c
+                    })
+                })
+                .collect();
+        }
+        Ok(combos)
+    }
+}
+
+/// 将一组 (点分路径, 值) 覆盖到基础配置上
+///
+/// 顶层路径被某个交易对的 `symbol_overrides` 遮蔽时报错；`symbol_overrides.<symbol>.<field>`
+/// 形式的路径写入该交易对的覆盖 (中间层级不存在时自动创建)。
+pub fn apply_overrides(base: &MonitorConfig, overrides: &[(String, Value)]) -> Result<MonitorConfig, String> {
+    let mut root = serde_yaml::to_value(base).map_err(|e| e.to_string())?;
+    for (path, value) in overrides {
+        if let Some(rest) = path.strip_prefix("symbol_overrides.") {
+            let (symbol, field) = rest.split_once('.')
+                .ok_or_else(|| format!("{}: expected symbol_overrides.<symbol>.<field>", path))?;
+            let top = field.split('.').next().unwrap_or_default();
+            if !SYMBOL_OVERRIDE_FIELDS.contains(&top) {
+                return Err(format!(
+                    "{}: {} cannot be overridden per symbol (allowed: {})",
+                    path, top, SYMBOL_OVERRIDE_FIELDS.join(", "),
+                ));
+            }
+            if lookup(&root, field).is_none() {
+                return Err(format!("unknown config field: {}", path));
+            }
+            let mut node = &mut root["symbol_overrides"][symbol];
+            for key in field.split('.') {
+                node = &mut node[key];
+            }
+            *node = value.clone();
+            continue;
+        }
+
+        if let Some(symbol) = base.symbol_overrides.iter()
+            .find(|(_, overlay)| lookup(overlay, path).is_some())
+            .map(|(symbol, _)| symbol)
+        {
+            return Err(format!(
+                "{} is shadowed by symbol_overrides.{} and would have no effect there; sweep symbol_overrides.{}.{} instead",
+                path, symbol, symbol, path,
+            ));
+        }
+        let mut node = &mut root;
+        for key in path.split('.') {
+            node = node.get_mut(key).ok_or_else(|| format!("unknown config field: {}", path))?;
+        }
+        *node = value.clone();
+    }
+    let cfg: MonitorConfig = serde_yaml::from_value(root).map_err(|e| format!("invalid override value: {}", e))?;
+    cfg.validate().map_err(|errors| format!("invalid config:\n{}", crate::config::format_errors(&errors)))?;
+    Ok(cfg)
+}
+
+/// 按点分路径查找节点
+fn lookup<'a>(node: &'a Value, path: &str) -> Option<&'a Value> {
+    path.split('.').try_fold(node, |n, key| n.get(key))
+}
+
+/// 排行榜中的一行
+#[derive(Debug, Clone, Serialize)]
+pub struct SweepResult {
+    pub params: BTreeMap<String, String>,
+    pub trend_signals: usize,
+    pub vol_signals: usize,
+    pub trend_hit_rate: Option<f64>,
+    pub trend_fwd_bps: [Option<f64>; FORWARD_HORIZONS_MS.len()],
+    pub trend_avg_adverse: Option<f64>,
+    pub vol_protection_ratio: Option<f64>,
+    /// 回放失败时的错误信息，此时各项指标为空
+    pub error: Option<String>,
+}
+
+impl SweepResult {
+    fn from_report(overrides: &[(String, Value)], report: &BacktestReport) -> Self {
+        let params = params_of(overrides);
+
+        // "U"/"D" 按信号数加权合并
+        let trend: Vec<_> = ["U", "D"].iter().filter_map(|s| report.sources.get(*s)).collect();
+        let trend_signals: usize = trend.iter().map(|m| m.count).sum();
+        let weighted = |f: &dyn Fn(&crate::backtest::SourceMetrics) -> Option<f64>| -> Option<f64> {
+            let (sum, n) = trend.iter()
+                .filter_map(|m| f(m).map(|v| (v * m.count as f64, m.count)))
+                .fold((0.0, 0), |(s, n), (v, c)| (s + v, n + c));
+            (n > 0).then(|| sum / n as f64)
+        };
+
+        let mut trend_fwd_bps = [None; FORWARD_HORIZONS_MS.len()];
+        for (i, slot) in trend_fwd_bps.iter_mut().enumerate() {
+            *slot = weighted(&|m| m.avg_forward_bps[i]);
+        }
+
+        let vol = report.sources.get("V");
+        Self {
+            params,
+            trend_signals,
+            vol_signals: vol.map(|m| m.count).unwrap_or(0),
+            trend_hit_rate: weighted(&|m| m.hit_rate),
+            trend_fwd_bps,
+            trend_avg_adverse: weighted(&|m| Some(m.avg_adverse_excursion)),
+            vol_protection_ratio: vol.and_then(|m| m.protection_ratio),
+            error: None,
+        }
+    }
+
+    fn failed(overrides: &[(String, Value)], error: String) -> Self {
+        Self {
+            params: params_of(overrides),
+            trend_signals: 0,
+            vol_signals: 0,
+            trend_hit_rate: None,
+            trend_fwd_bps: [None; FORWARD_HORIZONS_MS.len()],
+            trend_avg_adverse: None,
+            vol_protection_ratio: None,
+            error: Some(error),
+        }
+    }
+
+    /// 排序键：5s 前向收益优先，其次命中率；无趋势信号的组合排在后面，失败的组合排在最后
+    fn score(&self) -> (bool, f64, f64) {
+        (
+            self.error.is_none(),
+            self.trend_fwd_bps[1].unwrap_or(f64::NEG_INFINITY),
+            self.trend_hit_rate.unwrap_or(f64::NEG_INFINITY),
+        )
+    }
+}
+
+fn params_of(overrides: &[(String, Value)]) -> BTreeMap<String, String> {
+    overrides.iter()
+        .map(|(k, v)| (k.clone(), yaml_scalar(v)))
+        .collect()
+}
+
+fn yaml_scalar(v: &Value) -> String {
+    match v {
+        Value::String(s) => s.clone(),
+        other => serde_yaml::to_string(other).unwrap_or_default().trim().to_string(),
+    }
+}
+
+/// 在内存事件序列上并行运行所有参数组合，返回按得分降序排列的结果
+///
+/// `jobs` 为并行线程数；每个组合使用独立的 `SignalEngine` 与 `EventClock`。
+/// 回放失败的组合保留在结果中 (`error` 非空)，排在末尾。
+pub fn run_sweep(
+    base: &MonitorConfig,
+    spec: &SweepSpec,
+    events: &[BinanceEvent],
+    jobs: usize,
+) -> Result<Vec<SweepResult>, Box<dyn std::error::Error>> {
+    let combos = spec.combinations()?;
+    // 先全部校验，避免跑到一半才发现拼写错误
+    let configs: Vec<MonitorConfig> = combos.iter()
+        .map(|c| apply_overrides(base, c))
+        .collect::<Result<_, _>>()?;
+
+    let next = AtomicUsize::new(0);
+    let mut results: Vec<SweepResult> = std::thread::scope(|scope| {
+        let workers: Vec<_> = (0..jobs.max(1))
+            .map(|_| scope.spawn(|| {
+                let mut done = Vec::new();
+                loop {
+                    let i = next.fetch_add(1, Ordering::Relaxed);
+                    let Some(cfg) = configs.get(i) else { break };
+
+                    let engine = SignalEngine::new_with_clock(cfg.clone(), Arc::new(EventClock::default()));
+                    let mut backtester = Backtester::new(engine);
+                    if let Some(e) = events.iter().find_map(|e| backtester.on_event(e).err()) {
+                        warn!("⚠️ Combination #{} failed: {}", i, e);
+                        done.push(SweepResult::failed(&combos[i], e.to_string()));
+                        continue;
+                    }
+                    done.push(SweepResult::from_report(&combos[i], &backtester.finish()));
+                }
+                done
+            }))
+            .collect();
+        workers.into_iter().flat_map(|w| w.join().unwrap()).collect()
+    });
+
+    results.sort_by(|a, b| b.score().partial_cmp(&a.score()).unwrap_or(std::cmp::Ordering::Equal));
+    Ok(results)
+}
+
+fn fmt_cell(v: Option<f64>) -> String {
+    v.map(|x| format!("{:.4}", x)).unwrap_or_default()
+}
+
+/// 含逗号、引号或换行的字段加双引号，内部引号双写 (RFC 4180)
+fn csv_field(s: &str) -> String {
+    if s.contains([',', '"', '\n', '\r']) {
+        format!("\"{}\"", s.replace('"', "\"\""))
+    } else {
+        s.to_string()
+    }
+}
+
+/// 输出 CSV 排行榜
+pub fn to_csv(results: &[SweepResult]) -> String {
+    let param_names: Vec<&String> = results.first()
+        .map(|r| r.params.keys().collect())
+        .unwrap_or_default();
+
+    let mut out = String::from("rank");
+    for name in &param_names {
+        let _ = write!(out, ",{}", csv_field(name));
+    }
+    out.push_str(",trend_signals,vol_signals,trend_hit_rate,trend_fwd_1s_bps,trend_fwd_5s_bps,trend_fwd_30s_bps,trend_avg_adverse,vol_protection_ratio,error\n");
+
+    for (rank, r) in results.iter().enumerate() {
+        let _ = write!(out, "{}", rank + 1);
+        for name in &param_names {
+            let _ = write!(out, ",{}", csv_field(r.params.get(*name).map(String::as_str).unwrap_or("")));
+        }
+        let _ = writeln!(
+            out,
+            ",{},{},{},{},{},{},{},{},{}",
+            r.trend_signals,
+            r.vol_signals,
+            fmt_cell(r.trend_hit_rate),
+            fmt_cell(r.trend_fwd_bps[0]),
+            fmt_cell(r.trend_fwd_bps[1]),
+            fmt_cell(r.trend_fwd_bps[2]),
+            fmt_cell(r.trend_avg_adverse),
+            fmt_cell(r.vol_protection_ratio),
+            csv_field(r.error.as_deref().unwrap_or("")),
+        );
+    }
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn example() -> MonitorConfig {
+        serde_yaml::from_str(include_str!("../config.example.yaml")).expect("config.example.yaml parses")
+    }
+
+    fn spec(yaml: &str) -> SweepSpec {
+        serde_yaml::from_str(yaml).expect("sweep spec parses")
+    }
+
+    fn floats(values: &[Value]) -> Vec<f64> {
+        values.iter().map(|v| v.as_f64().unwrap()).collect()
+    }
+
+    fn result(params: &[(&str, &str)], fwd_5s: Option<f64>) -> SweepResult {
+        SweepResult {
+            params: params.iter().map(|(k, v)| (k.to_string(), v.to_string())).collect(),
+            trend_signals: 1,
+            vol_signals: 0,
+            trend_hit_rate: Some(0.5),
+            trend_fwd_bps: [None, fwd_5s, None],
+            trend_avg_adverse: None,
+            vol_protection_ratio: None,
+            error: None,
+        }
+    }
+
+    #[test]
+    fn step_range_includes_both_endpoints() {
+        let range = ParamRange::Step { start: 0.6, end: 0.9, step: 0.1 };
+        // (0.9 - 0.6) / 0.1 = 2.9999999999999996，上端点不能因浮点误差丢失
+        assert_eq!(floats(&range.values().unwrap()), vec![0.6, 0.7, 0.8, 0.9]);
+
+        let range = ParamRange::Step { start: 0.0, end: 1.0, step: 0.1 };
+        let values = floats(&range.values().unwrap());
+        assert_eq!(values.len(), 11);
+        assert_eq!(values[3], 0.3);
+        assert_eq!(values[10], 1.0);
+
+        // 步长不整除时不越过 end
+        let range = ParamRange::Step { start: 1.0, end: 2.0, step: 0.4 };
+        assert_eq!(floats(&range.values().unwrap()), vec![1.0, 1.4, 1.8]);
+
+        let single = ParamRange::Step { start: 5.0, end: 5.0, step: 1.0 };
+        assert_eq!(floats(&single.values().unwrap()), vec![5.0]);
+    }
+
+    #[test]
+    fn invalid_step_range_is_rejected() {
+        assert!(ParamRange::Step { start: 0.0, end: 1.0, step: 0.0 }.values().is_err());
+        assert!(ParamRange::Step { start: 1.0, end: 0.0, step: 0.1 }.values().is_err());
+
+        let err = spec("params:\n  trend.ofi_decay: { start: 0.9, end: 0.6, step: 0.1 }\n")
+            .combinations()
+            .unwrap_err();
+        assert!(err.starts_with("trend.ofi_decay:"), "{}", err);
+        assert!(spec("params:\n  trend.cooldown_secs: []\n").combinations().is_err());
+    }
+
+    #[test]
+    fn combinations_are_the_cartesian_product_in_path_order() {
+        let combos = spec(
+            "params:\n  trend.slope_threshold: [3.0, 4.0]\n  trend.cooldown_secs: { start: 0.5, end: 1.5, step: 0.5 }\n",
+        )
+        .combinations()
+        .unwrap();
+        assert_eq!(combos.len(), 6);
+
+        // 路径按字典序展开，最后一个路径变化最快
+        let rendered: Vec<Vec<(&str, f64)>> = combos.iter()
+            .map(|c| c.iter().map(|(k, v)| (k.as_str(), v.as_f64().unwrap())).collect())
+            .collect();
+        assert_eq!(rendered[0], vec![("trend.cooldown_secs", 0.5), ("trend.slope_threshold", 3.0)]);
+        assert_eq!(rendered[1], vec![("trend.cooldown_secs", 0.5), ("trend.slope_threshold", 4.0)]);
+        assert_eq!(rendered[2], vec![("trend.cooldown_secs", 1.0), ("trend.slope_threshold", 3.0)]);
+        assert_eq!(rendered[5], vec![("trend.cooldown_secs", 1.5), ("trend.slope_threshold", 4.0)]);
+    }
+
+    #[test]
+    fn overrides_patch_the_base_config() {
+        let cfg = apply_overrides(
+            &example(),
+            &[("trend.cooldown_secs".into(), Value::from(2.5)), ("volatility.window_size".into(), Value::from(40))],
+        )
+        .unwrap();
+        assert_eq!(cfg.trend.cooldown_secs, 2.5);
+        assert_eq!(cfg.volatility.window_size, 40);
+    }
+
+    #[test]
+    fn invalid_override_path_or_value_is_rejected() {
+        let base = example();
+        let err = apply_overrides(&base, &[("trend.no_such_field".into(), Value::from(1.0))]).unwrap_err();
+        assert!(err.contains("unknown config field: trend.no_such_field"), "{}", err);
+
+        let err = apply_overrides(&base, &[("trend.cooldown_secs".into(), Value::from("fast"))]).unwrap_err();
+        assert!(err.starts_with("invalid override value"), "{}", err);
+
+        // 类型正确但不通过 validate()
+        let err = apply_overrides(&base, &[("trend.ofi_decay".into(), Value::from(1.5))]).unwrap_err();
+        assert!(err.contains("trend.ofi_decay"), "{}", err);
+    }
+
+    #[test]
+    fn path_shadowed_by_symbol_override_is_rejected() {
+        // 示例配置中 ethusdt 覆盖了 trend.slope_threshold
+        let err = apply_overrides(&example(), &[("trend.slope_threshold".into(), Value::from(3.0))]).unwrap_err();
+        assert!(err.contains("shadowed by symbol_overrides.ethusdt"), "{}", err);
+        assert!(err.contains("symbol_overrides.ethusdt.trend.slope_threshold"), "{}", err);
+    }
+
+    #[test]
+    fn symbol_override_paths_patch_the_symbol_overlay() {
+        let base = example();
+        let cfg = apply_overrides(
+            &base,
+            &[
+                ("symbol_overrides.ethusdt.trend.slope_threshold".into(), Value::from(0.3)),
+                ("symbol_overrides.btcusdt.trend.cooldown_secs".into(), Value::from(2.0)),
+            ],
+        )
+        .unwrap();
+        assert_eq!(cfg.for_symbol("ethusdt").unwrap().trend.slope_threshold, 0.3);
+        assert_eq!(cfg.for_symbol("btcusdt").unwrap().trend.cooldown_secs, 2.0);
+        // 全局值与其它交易对不受影响
+        assert_eq!(cfg.trend.slope_threshold, base.trend.slope_threshold);
+        assert_eq!(cfg.for_symbol("ethusdt").unwrap().trend.cooldown_secs, base.trend.cooldown_secs);
+
+        let err = apply_overrides(&base, &[("symbol_overrides.ethusdt.trend.nope".into(), Value::from(1.0))])
+            .unwrap_err();
+        assert!(err.contains("unknown config field"), "{}", err);
+        let err = apply_overrides(&base, &[("symbol_overrides.ethusdt.order_book.snapshot_limit".into(), Value::from(100))])
+            .unwrap_err();
+        assert!(err.contains("cannot be overridden per symbol"), "{}", err);
+    }
+
+    #[test]
+    fn csv_quotes_fields_with_separators() {
+        let mut failed = SweepResult::failed(
+            &[("histogram.buckets".into(), Value::from("a,b"))],
+            "bad \"trade\"\nline 2".into(),
+        );
+        failed.params.insert("trend.note".into(), "plain".into());
+        let mut ok = result(&[("histogram.buckets", "a,b"), ("trend.note", "plain")], Some(1.0));
+        ok.trend_fwd_bps[0] = Some(-0.5);
+
+        let csv = to_csv(&[ok, failed]);
+        let mut lines = csv.lines();
+        assert_eq!(
+            lines.next().unwrap(),
+            "rank,histogram.buckets,trend.note,trend_signals,vol_signals,trend_hit_rate,trend_fwd_1s_bps,\
+             trend_fwd_5s_bps,trend_fwd_30s_bps,trend_avg_adverse,vol_protection_ratio,error",
+        );
+        assert_eq!(lines.next().unwrap(), "1,\"a,b\",plain,1,0,0.5000,-0.5000,1.0000,,,,");
+        // 错误信息中的引号双写，换行保留在引号内
+        assert!(csv.ends_with("2,\"a,b\",plain,0,0,,,,,,,\"bad \"\"trade\"\"\nline 2\"\n"), "{}", csv);
+    }
+
+    #[test]
+    fn failed_combinations_rank_last() {
+        let mut results = [
+            SweepResult::failed(&[], "boom".into()),
+            result(&[], None),
+            result(&[], Some(2.0)),
+        ];
+        results.sort_by(|a, b| b.score().partial_cmp(&a.score()).unwrap_or(std::cmp::Ordering::Equal));
+        assert_eq!(results[0].trend_fwd_bps[1], Some(2.0));
+        assert!(results[1].error.is_none());
+        assert!(results[2].error.is_some());
+    }
+}
@@ -0,0 +1,284 @@
+//! Telemetry 编码
+//!
+//! 每个客户端可以独立选择编码：
+//! - `json`: 文本帧，字段见 `TelemetryPacket` (默认)
+//! - `msgpack`: 二进制帧，MessagePack map，键名与 JSON 相同
+//! - `binary`: 二进制帧，定长小端布局 (见下)，解析开销最低
+//!
+//! # binary 布局 (版本 1, 共 43 字节, 小端)
+//! ```text
+//! offset size  field
+//! 0      1     version      = 1
+//! 1      8     timestamp    u64 毫秒
+//! 9      1     source       ASCII 字节 ("V"/"U"/"D"/"N"/"L"/"X")
+//! 10     1     flags        bit0 = degraded, bit1-4 = 转换事件码 (0 = 无, 见 `Transition::code`)
+//! 11     8     ask_adjust   f64
+//! 19     8     bid_adjust   f64
+//! 27     16    symbol       ASCII，右侧以 0 填充
+//! ```
+//! Python: `struct.unpack("<BQcBdd16s", frame)`
+//!
+//! binary 帧不携带期限结构 (`v`)，需要时使用 `snapshot` 命令获取。
+
+use serde::Deserialize;
+
+use super::{TelemetryPacket, Transition};
+
+/// binary 编码当前版本号
+pub const BINARY_VERSION: u8 = 1;
+
+/// binary 帧长度 (字节)
+pub const BINARY_FRAME_LEN: usize = 43;
+
+/// symbol 字段长度 (字节)
+const SYMBOL_LEN: usize = 16;
+
+const FLAG_DEGRADED: u8 = 0b0000_0001;
+const EVENT_SHIFT: u8 = 1;
+const EVENT_MASK: u8 = 0b0001_1110;
+
+/// 客户端编码
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Encoding {
+    #[default]
+    Json,
+    Msgpack,
+    Binary,
+}
+
+impl Encoding {
+    /// 解析编码名称 (忽略大小写)
+    pub fn parse(name: &str) -> Option<Self> {
+        match name.to_ascii_lowercase().as_str() {
+            "json" => Some(Encoding::Json),
+            "msgpack" | "messagepack" => Some(Encoding::Msgpack),
+            "binary" | "bin" => Some(Encoding::Binary),
+            _ => None,
+        }
+    }
+
+    /// 从连接 URL 的查询串中读取 `encoding=...`
+    pub fn from_query(query: Option<&str>) -> Option<Self> {
+        query?
+            .split('&')
+            .filter_map(|kv| kv.split_once('='))
+            .find(|(k, _)| *k == "encoding")
+            .and_then(|(_, v)| Self::parse(v))
+    }
+}
+
+/// 已编码的帧 (JSON 为文本帧，其余为二进制帧)
+#[derive(Debug, Clone, PartialEq)]
+pub enum EncodedFrame {
+    Text(String),
+    Binary(Vec<u8>),
+}
+
+/// 编码错误
+#[derive(Debug)]
+pub enum CodecError {
+    Json(serde_json::Error),
+    MsgpackEncode(rmp_serde::encode::Error),
+    MsgpackDecode(rmp_serde::decode::Error),
+    /// binary 帧长度或版本不匹配
+    InvalidFrame(String),
+}
+
+impl std::fmt::Display for CodecError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            CodecError::Json(e) => write!(f, "json: {}", e),
+            CodecError::MsgpackEncode(e) => write!(f, "msgpack encode: {}", e),
+            CodecError::MsgpackDecode(e) => write!(f, "msgpack decode: {}", e),
+            CodecError::InvalidFrame(msg) => write!(f, "invalid binary frame: {}", msg),
+        }
+    }
+}
+
+impl std::error::Error for CodecError {}
+
+/// 按指定编码编码一个数据包
+pub fn encode(packet: &TelemetryPacket, encoding: Encoding) -> Result<EncodedFrame, CodecError> {
+    match encoding {
+        Encoding::Json => serde_json::to_string(packet).map(EncodedFrame::Text).map_err(CodecError::Json),
+        Encoding::Msgpack => rmp_serde::to_vec_named(packet).map(EncodedFrame::Binary).map_err(CodecError::MsgpackEncode),
+        Encoding::Binary => Ok(EncodedFrame::Binary(encode_binary(packet).to_vec())),
+    }
+}
+
+/// 解码一个数据包 (客户端侧参考实现)
+pub fn decode(frame: &EncodedFrame, encoding: Encoding) -> Result<TelemetryPacket, CodecError> {
+    match (encoding, frame) {
+        (Encoding::Json, EncodedFrame::Text(text)) => serde_json::from_str(text).map_err(CodecError::Json),
+        (Encoding::Msgpack, EncodedFrame::Binary(bytes)) => rmp_serde::from_slice(bytes).map_err(CodecError::MsgpackDecode),
+        (Encoding::Binary, EncodedFrame::Binary(bytes)) => decode_binary(bytes),
+        (enc, _) => Err(CodecError::InvalidFrame(format!("frame type does not match encoding {:?}", enc))),
+    }
+}
+
+/// 定长小端编码；超过 16 字节的 symbol 会被截断
+pub fn encode_binary(packet: &TelemetryPacket) -> [u8; BINARY_FRAME_LEN] {
+    let mut buf = [0u8; BINARY_FRAME_LEN];
+    buf[0] = BINARY_VERSION;
+    buf[1..9].copy_from_slice(&packet.timestamp.to_le_bytes());
+    buf[9] = packet.source.as_bytes().first().copied().unwrap_or(b'N');
+    let event = packet.event.map(Transition::code).unwrap_or(0);
+    buf[10] = (if packet.degraded { FLAG_DEGRADED } else { 0 }) | ((event << EVENT_SHIFT) & EVENT_MASK);
+    buf[11..19].copy_from_slice(&packet.ask_adjust.to_le_bytes());
+    buf[19..27].copy_from_slice(&packet.bid_adjust.to_le_bytes());
+    let symbol = packet.symbol.as_bytes();
+    let n = symbol.len().min(SYMBOL_LEN);
+    buf[27..27 + n].copy_from_slice(&symbol[..n]);
+    buf
+}
+
+pub fn decode_binary(bytes: &[u8]) -> Result<TelemetryPacket, CodecError> {
+    if bytes.len() != BINARY_FRAME_LEN {
+        return Err(CodecError::InvalidFrame(format!("expected {} bytes, got {}", BINARY_FRAME_LEN, bytes.len())));
+    }
+    if bytes[0] != BINARY_VERSION {
+        return Err(CodecError::InvalidFrame(format!("unsupported version {}", bytes[0])));
+    }
+
+    let u64_at = |i: usize| u64::from_le_bytes(bytes[i..i + 8].try_into().unwrap());
+    let f64_at = |i: usize| f64::from_le_bytes(bytes[i..i + 8].try_into().unwrap());
+    let symbol_bytes = &bytes[27..27 + SYMBOL_LEN];
+    let symbol_end = symbol_bytes.iter().position(|b| *b == 0).unwrap_or(SYMBOL_LEN);
+
+    Ok(TelemetryPacket {
+        timestamp: u64_at(1),
+        symbol: String::from_utf8_lossy(&symbol_bytes[..symbol_end]).into_owned(),
+        source: (bytes[9] as char).to_string(),
+        ask_adjust: f64_at(11),
+        bid_adjust: f64_at(19),
+        degraded: bytes[10] & FLAG_DEGRADED != 0,
+        event: Transition::from_code((bytes[10] & EVENT_MASK) >> EVENT_SHIFT),
+        term_vols: None,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::common::units::Fraction;
+
+    const ALL_TRANSITIONS: [Transition; 12] = [
+        Transition::EnterLong,
+        Transition::EnterShort,
+        Transition::ExitPriceFallback,
+        Transition::ExitSlopeReversal,
+        Transition::CooldownStart,
+        Transition::CooldownEnd,
+        Transition::ExitMaxHolding,
+        Transition::ExitTrailingStop,
+        Transition::RegimeCalm,
+        Transition::RegimeNormal,
+        Transition::RegimeElevated,
+        Transition::RegimeExtreme,
+    ];
+
+    fn packet(event: Option<Transition>, term_vols: Option<Vec<Option<Fraction>>>) -> TelemetryPacket {
+        TelemetryPacket {
+            timestamp: 1_700_000_000_123,
+            symbol: "BTCUSDT".to_string(),
+            source: event.map(Transition::source).unwrap_or("V").to_string(),
+            ask_adjust: 1.25,
+            bid_adjust: -0.375,
+            degraded: true,
+            event,
+            term_vols,
+        }
+    }
+
+    #[test]
+    fn json_round_trip() {
+        let p = packet(Some(Transition::EnterLong), Some(vec![Some(Fraction(0.42)), None]));
+        let frame = encode(&p, Encoding::Json).unwrap();
+        assert!(matches!(frame, EncodedFrame::Text(_)));
+        assert_eq!(decode(&frame, Encoding::Json).unwrap(), p);
+
+        let plain = packet(None, None);
+        let EncodedFrame::Text(text) = encode(&plain, Encoding::Json).unwrap() else { unreachable!() };
+        assert!(!text.contains("\"e\"") && !text.contains("\"v\""));
+        assert_eq!(decode(&EncodedFrame::Text(text), Encoding::Json).unwrap(), plain);
+    }
+
+    #[test]
+    fn msgpack_round_trip() {
+        for p in [packet(Some(Transition::ExitTrailingStop), Some(vec![None, Some(Fraction(1.5))])), packet(None, None)] {
+            let frame = encode(&p, Encoding::Msgpack).unwrap();
+            assert!(matches!(frame, EncodedFrame::Binary(_)));
+            assert_eq!(decode(&frame, Encoding::Msgpack).unwrap(), p);
+        }
+    }
+
+    #[test]
+    fn binary_round_trip() {
+        for event in ALL_TRANSITIONS.into_iter().map(Some).chain([None]) {
+            let p = packet(event, None);
+            let frame = encode(&p, Encoding::Binary).unwrap();
+            let EncodedFrame::Binary(bytes) = &frame else { unreachable!() };
+            assert_eq!(bytes.len(), BINARY_FRAME_LEN);
+            assert_eq!(decode(&frame, Encoding::Binary).unwrap(), p);
+        }
+    }
+
+    #[test]
+    fn binary_layout_matches_documented_offsets() {
+        let mut p = packet(Some(Transition::RegimeExtreme), None);
+        p.degraded = false;
+        let bytes = encode_binary(&p);
+        assert_eq!(bytes[0], BINARY_VERSION);
+        assert_eq!(&bytes[1..9], &1_700_000_000_123u64.to_le_bytes());
+        assert_eq!(bytes[9], b'N');
+        assert_eq!(bytes[10], Transition::RegimeExtreme.code() << 1);
+        assert_eq!(&bytes[11..19], &1.25f64.to_le_bytes());
+        assert_eq!(&bytes[19..27], &(-0.375f64).to_le_bytes());
+        assert_eq!(&bytes[27..34], b"BTCUSDT");
+        assert!(bytes[34..].iter().all(|b| *b == 0));
+    }
+
+    #[test]
+    fn binary_drops_term_vols_and_truncates_long_symbols() {
+        let mut p = packet(None, Some(vec![Some(Fraction(0.3))]));
+        p.symbol = "1000SHIBUSDT_PERPETUAL".to_string();
+        let decoded = decode_binary(&encode_binary(&p)).unwrap();
+        assert_eq!(decoded.symbol, "1000SHIBUSDT_PER");
+        assert_eq!(decoded.term_vols, None);
+    }
+
+    #[test]
+    fn decode_rejects_wrong_length() {
+        let bytes = encode_binary(&packet(None, None));
+        for len in [0, BINARY_FRAME_LEN - 1] {
+            let err = decode(&EncodedFrame::Binary(bytes[..len].to_vec()), Encoding::Binary).unwrap_err();
+            assert!(matches!(err, CodecError::InvalidFrame(_)), "{}", err);
+        }
+        let mut longer = bytes.to_vec();
+        longer.push(0);
+        assert!(matches!(decode_binary(&longer), Err(CodecError::InvalidFrame(_))));
+    }
+
+    #[test]
+    fn decode_rejects_wrong_version() {
+        let mut bytes = encode_binary(&packet(None, None));
+        bytes[0] = BINARY_VERSION + 1;
+        let err = decode(&EncodedFrame::Binary(bytes.to_vec()), Encoding::Binary).unwrap_err();
+        assert!(err.to_string().contains("unsupported version 2"), "{}", err);
+    }
+
+    #[test]
+    fn decode_rejects_frame_type_mismatch() {
+        let frame = encode(&packet(None, None), Encoding::Json).unwrap();
+        assert!(matches!(decode(&frame, Encoding::Binary), Err(CodecError::InvalidFrame(_))));
+    }
+
+    #[test]
+    fn encoding_from_query() {
+        assert_eq!(Encoding::from_query(Some("token=x&encoding=MsgPack")), Some(Encoding::Msgpack));
+        assert_eq!(Encoding::from_query(Some("encoding=bin")), Some(Encoding::Binary));
+        assert_eq!(Encoding::from_query(Some("encoding=xml")), None);
+        assert_eq!(Encoding::from_query(None), None);
+    }
+}
@@ -0,0 +1,638 @@
+//! Telemetry WebSocket 服务
+//!
+//! 向报价端 (Python) 广播 `TelemetryPacket`。
+//! 每个客户端可独立选择编码 (见 `codec`)：
+//! - 连接时通过查询参数指定: `ws://host:9001/?encoding=msgpack`
+//! - 或在连接后发送命令: `{"cmd": "encoding", "encoding": "binary"}`
+//!
+//! 未指定时默认 JSON 文本帧。连接后客户端还可以订阅部分来源/交易对、
+//! 请求状态快照、发送 ping 测量延迟 (见 `protocol`)。
+//!
+//! 客户端连接后立即收到一条 `snapshot` 回复 (各交易对的当前状态)，
+//! 之后某交易对在 `heartbeat_interval_ms` 内没有任何推送时会收到 "N" 心跳包，
+//! 因此 "无信号" 与 "连接已断开" 可以区分。
+
+pub mod codec;
+pub mod protocol;
+
+use std::collections::HashMap;
+use std::net::SocketAddr;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+use serde::{Deserialize, Serialize};
+use tokio::net::TcpListener;
+use tokio::sync::broadcast;
+use futures_util::{SinkExt, StreamExt};
+use tokio_tungstenite::accept_hdr_async;
+use tungstenite::Message;
+use tungstenite::handshake::server::{Request, Response};
+use tracing::{info, error, warn};
+
+use crate::common::clock::{Clock, WallClock};
+use crate::common::units::Fraction;
+use crate::config::TelemetryConfig;
+use crate::indicators::trend_state::{StrategyState, TrendDirection};
+use crate::indicators::term_structure::TermPoint;
+use crate::indicators::vol::VolatilityResult;
+use crate::indicators::vol_regime::VolRegime;
+use codec::{EncodedFrame, Encoding};
+use protocol::{ClientCommand, ServerReply, Subscription};
+
+/// 遥测数据包 - 发送给 Python 客户端的价差调整信号
+/// 
+/// # 字段说明 (使用单字母以减少网络带宽)
+/// - `t`: 时间戳 (毫秒)
+/// - `S`: 交易对 (大写，例如 "BTCUSDT")
+/// - `s`: 信号来源 - "V"=高波动, "U"=上涨趋势, "D"=下跌趋势, "N"=无信号,
+///   "L"=行情连接恢复 (feed up), "X"=行情连接断开 (feed down，应视为无可靠信号)
+/// - `a`: ask 侧价差调整 (美元)
+/// - `b`: bid 侧价差调整 (美元)
+/// - `g`: 数据降级标志 - true 表示近期发生序号断档或订单簿未同步，信号可靠性降低
+/// - `e`: 趋势状态机 / 波动率状态转换事件 (可选，见 `Transition`)，普通信号包不携带
+/// - `v`: 波动率期限结构 (可选)，按 `term_structure.horizons_secs` 顺序的年化波动率 (1.0 = 100%)，
+///   样本不足的周期为 null。每个 `publish_interval_ms` 至多随一个信号包下发，心跳包总是携带
+///
+/// "N" 为心跳包：交易对在心跳间隔内没有其他推送时发送，价差调整为 0。
+/// 趋势退出/冷却事件同样以 "N" 包下发，客户端无需依赖超时判断趋势结束。
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct TelemetryPacket {
+    #[serde(rename = "t")]
+    pub timestamp: u64,
+    #[serde(rename = "S")]
+    pub symbol: String,
+    #[serde(rename = "s")]
+    pub source: String,
+    #[serde(rename = "a")]
+    pub ask_adjust: f64,
+    #[serde(rename = "b")]
+    pub bid_adjust: f64,
+    #[serde(rename = "g")]
+    pub degraded: bool,
+    #[serde(rename = "e", default, skip_serializing_if = "Option::is_none")]
+    pub event: Option<Transition>,
+    #[serde(rename = "v", default, skip_serializing_if = "Option::is_none")]
+    pub term_vols: Option<Vec<Option<Fraction>>>,
+}
+
+/// 趋势状态机与波动率状态转换事件
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Transition {
+    /// 进入多头持仓 (随 "U" 包下发)
+    EnterLong,
+    /// 进入空头持仓 (随 "D" 包下发)
+    EnterShort,
+    /// 价格回落超过阈值退出
+    ExitPriceFallback,
+    /// 斜率持续走弱退出
+    ExitSlopeReversal,
+    /// 持仓超过最长时间退出
+    ExitMaxHolding,
+    /// 自最有利价格回撤超过移动止损退出
+    ExitTrailingStop,
+    /// 退出后进入冷却期
+    CooldownStart,
+    /// 冷却期结束，恢复扫描
+    CooldownEnd,
+    /// 波动率状态切换到 Calm
+    RegimeCalm,
+    /// 波动率状态切换到 Normal
+    RegimeNormal,
+    /// 波动率状态切换到 Elevated
+    RegimeElevated,
+    /// 波动率状态切换到 Extreme
+    RegimeExtreme,
+}
+
+impl Transition {
+    /// binary 编码中的事件码 (flags 的 bit1-4)，0 表示无事件
+    pub fn code(self) -> u8 {
+        match self {
+            Transition::EnterLong => 1,
+            Transition::EnterShort => 2,
+            Transition::ExitPriceFallback => 3,
+            Transition::ExitSlopeReversal => 4,
+            Transition::CooldownStart => 5,
+            Transition::CooldownEnd => 6,
+            Transition::ExitMaxHolding => 7,
+            Transition::ExitTrailingStop => 8,
+            Transition::RegimeCalm => 9,
+            Transition::RegimeNormal => 10,
+            Transition::RegimeElevated => 11,
+            Transition::RegimeExtreme => 12,
+        }
+    }
+
+    pub fn from_code(code: u8) -> Option<Self> {
+        match code {
+            1 => Some(Transition::EnterLong),
+            2 => Some(Transition::EnterShort),
+            3 => Some(Transition::ExitPriceFallback),
+            4 => Some(Transition::ExitSlopeReversal),
+            5 => Some(Transition::CooldownStart),
+            6 => Some(Transition::CooldownEnd),
+            7 => Some(Transition::ExitMaxHolding),
+            8 => Some(Transition::ExitTrailingStop),
+            9 => Some(Transition::RegimeCalm),
+            10 => Some(Transition::RegimeNormal),
+            11 => Some(Transition::RegimeElevated),
+            12 => Some(Transition::RegimeExtreme),
+            _ => None,
+        }
+    }
+
+    /// 携带该事件的数据包来源
+    pub fn source(self) -> &'static str {
+        match self {
+            Transition::EnterLong => "U",
+            Transition::EnterShort => "D",
+            _ => "N",
+        }
+    }
+}
+
+impl TelemetryPacket {
+    /// 行情连接状态包 ("L" = feed up, "X" = feed down)，价差调整为 0
+    pub fn feed_status(symbol: &str, up: bool, timestamp: u64) -> Self {
+        Self {
+            timestamp,
+            symbol: symbol.to_uppercase(),
+            source: if up { "L" } else { "X" }.to_string(),
+            ask_adjust: 0.0,
+            bid_adjust: 0.0,
+            degraded: !up,
+            event: None,
+            term_vols: None,
+        }
+    }
+
+    /// 心跳包 ("N")，价差调整为 0
+    pub fn heartbeat(symbol: &str, degraded: bool, timestamp: u64) -> Self {
+        Self {
+            timestamp,
+            symbol: symbol.to_uppercase(),
+            source: "N".to_string(),
+            ask_adjust: 0.0,
+            bid_adjust: 0.0,
+            degraded,
+            event: None,
+            term_vols: None,
+        }
+    }
+}
+
+/// 单个交易对的当前信号状态，客户端连接时与 `snapshot` 命令中下发
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct SymbolState {
+    pub symbol: String,                        // 大写
+    pub last_trade_ms: Option<u64>,            // 最近一笔成交时间
+    pub volatility: Option<VolatilityResult>,  // 最近一次波动率计算结果
+    pub trend_state: StrategyState,
+    pub direction: TrendDirection,
+    pub entry_slope: Option<f64>,              // 持仓中的入场斜率 ($/s)
+    pub cum_ofi: f64,
+    pub impact_price: Option<f64>,             // 订单簿不足时为 None
+    pub degraded: bool,
+    #[serde(default)]
+    pub term_structure: Vec<TermPoint>,        // 各周期已实现波动率，未启用时为空
+    #[serde(default)]
+    pub regime: VolRegime,                     // 当前波动率状态
+}
+
+/// 最近一次推送的数据包及其发送时刻
+struct LatestPacket {
+    sent_at: Instant,
+    packet: Arc<TelemetryPacket>,
+}
+
+/// 发送端与各连接任务共享的状态
+struct Shared {
+    tx: broadcast::Sender<Arc<TelemetryPacket>>,
+    latest: Mutex<HashMap<String, LatestPacket>>,   // 每个交易对最近一次推送的数据包
+    states: Mutex<HashMap<String, SymbolState>>,    // 每个交易对的当前信号状态
+}
+
+impl Shared {
+    fn new(capacity: usize) -> Arc<Self> {
+        let (tx, _rx) = broadcast::channel(capacity);
+        Arc::new(Self {
+            tx,
+            latest: Mutex::new(HashMap::new()),
+            states: Mutex::new(HashMap::new()),
+        })
+    }
+
+    fn send(&self, packet: TelemetryPacket) {
+        let mut latest = self.latest.lock().unwrap_or_else(|e| e.into_inner());
+        self.send_locked(&mut latest, packet);
+    }
+
+    /// 在持有 `latest` 锁时记录并广播，保证广播顺序与 `latest` 的更新顺序一致
+    fn send_locked(&self, latest: &mut HashMap<String, LatestPacket>, packet: TelemetryPacket) {
+        let packet = Arc::new(packet);
+        // 记录最新状态，供 snapshot 与心跳使用
+        latest.insert(packet.symbol.clone(), LatestPacket { sent_at: Instant::now(), packet: packet.clone() });
+
+        // 只有当有接收者(Python已连接)时才广播；各客户端按自己的编码序列化
+        if self.tx.receiver_count() > 0 {
+            // send 可能会返回错误(如果没有接收者)，忽略即可
+            let _ = self.tx.send(packet);
+        }
+    }
+
+    fn snapshot(&self) -> ServerReply {
+        let mut packets: Vec<TelemetryPacket> = self.latest.lock()
+            .unwrap_or_else(|e| e.into_inner())
+            .values()
+            .map(|l| (*l.packet).clone())
+            .collect();
+        packets.sort_by(|a, b| a.symbol.cmp(&b.symbol));
+
+        let mut states: Vec<SymbolState> = self.states.lock()
+            .unwrap_or_else(|e| e.into_inner())
+            .values()
+            .cloned()
+            .collect();
+        states.sort_by(|a, b| a.symbol.cmp(&b.symbol));
+
+        ServerReply::Snapshot { server_ts: WallClock.now_ms(), packets, states }
+    }
+
+    /// 为静默超过 `interval` 的交易对发送 "N" 心跳包
+    ///
+    /// 行情已断开 ("X") 的交易对不发送心跳，避免被误认为恢复。
+    /// 检查与发送在同一次加锁内完成，"X" 之后不会再出现心跳。
+    /// 心跳包携带该交易对当前的波动率期限结构 (如有)。
+    fn send_heartbeats(&self, interval: Duration) {
+        // 留 10% 余量，避免定时器抖动导致心跳间隔翻倍
+        let quiet = interval - interval / 10;
+        let mut latest = self.latest.lock().unwrap_or_else(|e| e.into_inner());
+        let due: Vec<TelemetryPacket> = {
+            let states = self.states.lock().unwrap_or_else(|e| e.into_inner());
+            let now_ms = WallClock.now_ms();
+            latest.iter()
+                .filter(|(_, l)| l.sent_at.elapsed() >= quiet && l.packet.source != "X")
+                .map(|(symbol, l)| {
+                    let state = states.get(symbol);
+                    let degraded = state.map(|s| s.degraded).unwrap_or(l.packet.degraded);
+                    let mut packet = TelemetryPacket::heartbeat(symbol, degraded, now_ms);
+                    packet.term_vols = state
+                        .filter(|s| !s.term_structure.is_empty())
+                        .map(|s| s.term_structure.iter().map(|p| p.vol).collect());
+                    packet
+                })
+                .collect()
+        };
+
+        for packet in due {
+            self.send_locked(&mut latest, packet);
+        }
+    }
+}
+
+// --- 遥测服务 ---
+pub struct TelemetryServer {
+    shared: Arc<Shared>,
+    enabled: bool,
+    local_addr: Option<SocketAddr>,
+}
+
+impl TelemetryServer {
+    /// 根据配置启动服务
+    ///
+    /// 端口绑定在返回前完成，绑定失败直接返回错误，由调用方决定是否终止启动。
+    pub async fn start(cfg: &TelemetryConfig) -> std::io::Result<Self> {
+        // 创建广播通道。
+        // 原理：这是一个环形缓冲区。
+        // 如果 Python 消费太慢，旧数据会被覆盖，Rust 发送端永远不会阻塞。
+        let shared = Shared::new(cfg.channel_capacity.max(1));
+
+        if !cfg.enabled {
+            info!("📡 [Telemetry] Disabled by config.");
+            return Ok(Self { shared, enabled: false, local_addr: None });
+        }
+
+        let listener = TcpListener::bind((cfg.bind_address.as_str(), cfg.port)).await?;
+        let local_addr = listener.local_addr()?;
+        info!("📡 [Telemetry] Server running on ws://{}", local_addr);
+
+        if cfg.heartbeat_interval_ms > 0 {
+            let interval = Duration::from_millis(cfg.heartbeat_interval_ms);
+            let shared_hb = shared.clone();
+            tokio::spawn(async move {
+                let mut ticker = tokio::time::interval(interval);
+                ticker.set_missed_tick_behavior(tokio::time::MissedTickBehavior::Skip);
+                loop {
+                    ticker.tick().await;
+                    shared_hb.send_heartbeats(interval);
+                }
+            });
+        }
+
+        let shared_clone = shared.clone();
+        let allowed_ips = cfg.allowed_ips.clone();
+
+        // 启动异步任务接受连接
+        tokio::spawn(async move {
+            loop {
+                let (stream, peer) = match listener.accept().await {
+                    Ok(conn) => conn,
+                    Err(e) => {
+                        error!("❌ [Telemetry] Accept failed: {}", e);
+                        continue;
+                    }
+                };
+
+                if !allowed_ips.is_empty() && !allowed_ips.contains(&peer.ip()) {
+                    warn!("🚫 [Telemetry] Rejected client {} (not in allowed_ips)", peer);
+                    continue;
+                }
+
+                info!("🔌 [Telemetry] Client connected: {}", peer);
+                let shared_inner = shared_clone.clone();
+                // 为每个连接生成的 Python 客户端启动一个独立任务
+                tokio::spawn(async move {
+                    handle_connection(stream, shared_inner).await;
+                    info!("🔌 [Telemetry] Client disconnected: {}", peer);
+                });
+            }
+        });
+
+        Ok(Self { shared, enabled: true, local_addr: Some(local_addr) })
+    }
+
+    /// 不监听端口的空服务 (回放/测试使用)，所有发送都会被忽略
+    pub fn disabled() -> Self {
+        Self { shared: Shared::new(1), enabled: false, local_addr: None }
+    }
+
+    /// 实际监听的地址 (`port: 0` 时由系统分配)，未启用时为 None
+    pub fn local_addr(&self) -> Option<SocketAddr> {
+        self.local_addr
+    }
+
+    /// 向所有客户端广播行情连接状态 (每个交易对一个包)
+    ///
+    /// 服务跨币安重连存活，客户端保持连接，通过 "X"/"L" 得知行情中断与恢复。
+    pub fn send_feed_status(&self, symbols: &[String], up: bool) {
+        let now_ms = WallClock.now_ms();
+        for symbol in symbols {
+            self.send(TelemetryPacket::feed_status(symbol, up, now_ms));
+        }
+    }
+
+    /// 发送数据接口 (极快，纳秒级)
+    pub fn send(&self, packet: TelemetryPacket) {
+        if !self.enabled {
+            return;
+        }
+
+        self.shared.send(packet);
+    }
+
+    pub fn is_enabled(&self) -> bool {
+        self.enabled
+    }
+
+    /// 更新交易对的当前状态 (供新连接的快照使用，不广播)
+    pub fn update_state(&self, state: SymbolState) {
+        if !self.enabled {
+            return;
+        }
+        self.shared.states.lock()
+            .unwrap_or_else(|e| e.into_inner())
+            .insert(state.symbol.clone(), state);
+    }
+}
+
+/// 处理单个 WebSocket 连接
+// 握手回调的错误类型由 tungstenite 规定
+#[allow(clippy::result_large_err)]
+async fn handle_connection(stream: tokio::net::TcpStream, shared: Arc<Shared>) {
+    // 1. 将 TCP 升级为 WebSocket，同时读取查询参数中的编码
+    let mut encoding = Encoding::default();
+    let ws_stream = match accept_hdr_async(stream, |req: &Request, resp: Response| {
+        if let Some(enc) = Encoding::from_query(req.uri().query()) {
+            encoding = enc;
+        }
+        Ok(resp)
+    })
+    .await
+    {
+        Ok(ws) => ws,
+        Err(e) => {
+            warn!("WebSocket handshake failed: {}", e);
+            return;
+        }
+    };
+
+    let (mut ws_sender, mut ws_receiver) = ws_stream.split();
+
+    // 2. 订阅广播通道，随后立即下发当前状态快照
+    let mut rx = shared.tx.subscribe();
+    let mut subscription = Subscription::default();
+    if ws_sender.send(Message::Text(shared.snapshot().to_json().into())).await.is_err() {
+        return;
+    }
+
+    // 3. 循环转发广播，同时处理客户端命令
+    loop {
+        tokio::select! {
+            packet = rx.recv() => match packet {
+                Ok(packet) => {
+                    if !subscription.matches(&packet) {
+                        continue;
+                    }
+                    let frame = match codec::encode(&packet, encoding) {
+                        Ok(EncodedFrame::Text(text)) => Message::Text(text.into()),
+                        Ok(EncodedFrame::Binary(bytes)) => Message::Binary(bytes.into()),
+                        Err(e) => {
+                            error!("❌ [Telemetry] Encode failed: {}", e);
+                            continue;
+                        }
+                    };
+                    if ws_sender.send(frame).await.is_err() {
+                        // 发送失败意味着客户端断开
+                        break;
+                    }
+                }
+                Err(broadcast::error::RecvError::Lagged(_)) => {
+                    // Python 端处理太慢，导致丢包。
+                    // 这在 HFT 监控中是正常的，直接跳过，不用管。
+                    continue;
+                }
+                Err(broadcast::error::RecvError::Closed) => {
+                    break;
+                }
+            },
+            incoming = ws_receiver.next() => match incoming {
+                Some(Ok(Message::Text(text))) => {
+                    let reply = handle_command(text.as_str(), &shared, &mut encoding, &mut subscription);
+                    if ws_sender.send(Message::Text(reply.to_json().into())).await.is_err() {
+                        break;
+                    }
+                }
+                Some(Ok(Message::Close(_))) | Some(Err(_)) | None => break,
+                Some(Ok(_)) => {}
+            },
+        }
+    }
+}
+
+/// 执行一条客户端命令，返回回复 (未知命令返回 error)
+fn handle_command(
+    text: &str,
+    shared: &Shared,
+    encoding: &mut Encoding,
+    subscription: &mut Subscription,
+) -> ServerReply {
+    let cmd = match ClientCommand::parse(text) {
+        Ok(cmd) => cmd,
+        Err(message) => {
+            warn!("📡 [Telemetry] Rejected client message: {}", message);
+            return ServerReply::Error { message };
+        }
+    };
+
+    match cmd {
+        ClientCommand::Encoding { encoding: enc } => {
+            info!("📡 [Telemetry] Client switched encoding to {:?}", enc);
+            *encoding = enc;
+            ServerReply::Encoding { encoding: format!("{:?}", enc).to_lowercase() }
+        }
+        ClientCommand::Subscribe { sources, symbols } => {
+            info!("📡 [Telemetry] Client subscribed: sources={:?} symbols={:?}", sources, symbols);
+            *subscription = Subscription::new(sources.clone(), symbols.clone());
+            ServerReply::Subscribed { sources, symbols }
+        }
+        ClientCommand::Snapshot => shared.snapshot(),
+        ClientCommand::Ping { id, client_ts } => ServerReply::Pong {
+            id,
+            client_ts,
+            server_ts: WallClock.now_ms(),
+        },
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tokio::net::TcpStream;
+    use tokio_tungstenite::{MaybeTlsStream, WebSocketStream, connect_async};
+
+    type Client = WebSocketStream<MaybeTlsStream<TcpStream>>;
+
+    const HEARTBEAT_MS: u64 = 100;
+
+    async fn start_server() -> TelemetryServer {
+        let cfg = TelemetryConfig {
+            bind_address: "127.0.0.1".to_string(),
+            port: 0,
+            heartbeat_interval_ms: HEARTBEAT_MS,
+            ..TelemetryConfig::default()
+        };
+        TelemetryServer::start(&cfg).await.expect("bind to an ephemeral port")
+    }
+
+    async fn connect(server: &TelemetryServer) -> Client {
+        let url = format!("ws://{}/", server.local_addr().unwrap());
+        connect_async(url).await.expect("client connects").0
+    }
+
+    /// 下一个文本帧，解析为 JSON (超时视为失败)
+    async fn next_json(client: &mut Client) -> serde_json::Value {
+        let frame = tokio::time::timeout(Duration::from_secs(2), client.next())
+            .await
+            .expect("frame before timeout")
+            .expect("stream open")
+            .expect("valid frame");
+        match frame {
+            Message::Text(text) => serde_json::from_str(text.as_str()).unwrap(),
+            other => panic!("expected a text frame, got {:?}", other),
+        }
+    }
+
+    async fn next_packet(client: &mut Client) -> TelemetryPacket {
+        serde_json::from_value(next_json(client).await).expect("telemetry packet")
+    }
+
+    fn state(symbol: &str) -> SymbolState {
+        SymbolState {
+            symbol: symbol.to_string(),
+            last_trade_ms: Some(1_700_000_000_000),
+            volatility: None,
+            trend_state: StrategyState::Holding,
+            direction: TrendDirection::Long,
+            entry_slope: Some(4.5),
+            cum_ofi: 12.0,
+            impact_price: Some(65_000.5),
+            degraded: false,
+            term_structure: Vec::new(),
+            regime: VolRegime::Elevated,
+        }
+    }
+
+    fn signal(symbol: &str, source: &str) -> TelemetryPacket {
+        TelemetryPacket {
+            timestamp: WallClock.now_ms(),
+            symbol: symbol.to_string(),
+            source: source.to_string(),
+            ask_adjust: 1.5,
+            bid_adjust: -0.5,
+            degraded: false,
+            event: None,
+            term_vols: None,
+        }
+    }
+
+    #[tokio::test]
+    async fn first_frame_is_a_snapshot_of_current_state() {
+        let server = start_server().await;
+        server.update_state(state("BTCUSDT"));
+        server.send(signal("BTCUSDT", "U"));
+
+        let mut client = connect(&server).await;
+        let snapshot = next_json(&mut client).await;
+        assert_eq!(snapshot["type"], "snapshot");
+
+        let states: Vec<SymbolState> = serde_json::from_value(snapshot["states"].clone()).unwrap();
+        assert_eq!(states, vec![state("BTCUSDT")]);
+        let packets: Vec<TelemetryPacket> = serde_json::from_value(snapshot["packets"].clone()).unwrap();
+        assert_eq!(packets.len(), 1);
+        assert_eq!(packets[0].source, "U");
+    }
+
+    #[tokio::test]
+    async fn heartbeats_follow_quiet_periods_and_stop_after_feed_down() {
+        let server = start_server().await;
+        server.update_state(state("BTCUSDT"));
+        let mut client = connect(&server).await;
+        assert_eq!(next_json(&mut client).await["type"], "snapshot");
+
+        // 只订阅 "D"：心跳与行情状态包仍应送达，"U" 被过滤
+        client.send(Message::Text(r#"{"cmd":"subscribe","sources":["D"]}"#.into())).await.unwrap();
+        assert_eq!(next_json(&mut client).await["type"], "subscribed");
+        let quiet_since = Instant::now();
+        server.send(signal("BTCUSDT", "U"));
+
+        let first = next_packet(&mut client).await;
+        assert_eq!((first.symbol.as_str(), first.source.as_str()), ("BTCUSDT", "N"));
+        assert_eq!((first.ask_adjust, first.bid_adjust), (0.0, 0.0));
+        // 至少静默 90% 的心跳间隔后才发送
+        assert!(quiet_since.elapsed() >= Duration::from_millis(HEARTBEAT_MS * 9 / 10));
+
+        let first_at = Instant::now();
+        let second = next_packet(&mut client).await;
+        assert_eq!(second.source, "N");
+        assert!(first_at.elapsed() >= Duration::from_millis(HEARTBEAT_MS / 2));
+
+        server.send_feed_status(&["btcusdt".to_string()], false);
+        let down = next_packet(&mut client).await;
+        assert_eq!((down.symbol.as_str(), down.source.as_str(), down.degraded), ("BTCUSDT", "X", true));
+
+        // 行情断开后不再有心跳
+        let silent = tokio::time::timeout(Duration::from_millis(HEARTBEAT_MS * 4), client.next()).await;
+        assert!(silent.is_err(), "unexpected frame after feed down: {:?}", silent);
+    }
+}
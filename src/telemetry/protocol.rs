@@ -0,0 +1,187 @@
+//! Telemetry 双向协议
+//!
+//! 客户端通过 JSON 文本帧发送命令，服务端以 JSON 文本帧回复 (与数据包编码无关)。
+//! 使用 msgpack/binary 编码的客户端可据帧类型区分：文本帧为控制回复，二进制帧为数据包。
+//!
+//! # 命令 (`cmd` 字段)
+//! ```text
+//! {"cmd":"encoding","encoding":"binary"}                 切换数据包编码 (兼容旧格式 {"encoding":"binary"})
+//! {"cmd":"subscribe","sources":["U","D"],"symbols":["BTCUSDT"]}
+//!                                                        只接收指定来源/交易对，省略或 null 表示不过滤
+//! {"cmd":"snapshot"}                                     请求当前状态快照 (连接时也会自动下发一次)
+//! {"cmd":"ping","id":1,"client_ts":1700000000000}        延迟测量
+//! ```
+//!
+//! # 回复 (`type` 字段)
+//! `encoding` / `subscribed` / `snapshot` / `pong` / `error`。
+//! 无法识别的命令返回 `error`，不会被静默丢弃。
+
+use std::collections::HashSet;
+
+use serde::{Deserialize, Serialize};
+
+use super::{SymbolState, TelemetryPacket};
+use super::codec::Encoding;
+
+/// 状态类数据包来源：不受 `sources` 订阅过滤影响 (仍受 `symbols` 过滤)
+pub const STATUS_SOURCES: [&str; 3] = ["L", "X", "N"];
+
+/// 客户端命令
+#[derive(Debug, Clone, Deserialize)]
+#[serde(tag = "cmd", rename_all = "snake_case")]
+pub enum ClientCommand {
+    Encoding {
+        encoding: Encoding,
+    },
+    Subscribe {
+        #[serde(default)]
+        sources: Option<Vec<String>>,
+        #[serde(default)]
+        symbols: Option<Vec<String>>,
+    },
+    Snapshot,
+    Ping {
+        #[serde(default)]
+        id: Option<u64>,
+        #[serde(default)]
+        client_ts: Option<u64>,
+    },
+}
+
+/// 旧版握手消息：`{"encoding": "msgpack"}`
+#[derive(Debug, Deserialize)]
+struct LegacyHandshake {
+    encoding: Encoding,
+}
+
+impl ClientCommand {
+    /// 解析客户端文本帧
+    pub fn parse(text: &str) -> Result<Self, String> {
+        match serde_json::from_str::<ClientCommand>(text) {
+            Ok(cmd) => Ok(cmd),
+            Err(e) => serde_json::from_str::<LegacyHandshake>(text)
+                .map(|hs| ClientCommand::Encoding { encoding: hs.encoding })
+                .map_err(|_| format!("unrecognized command: {}", e)),
+        }
+    }
+}
+
+/// 服务端回复
+#[derive(Debug, Clone, Serialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum ServerReply {
+    Encoding {
+        encoding: String,
+    },
+    Subscribed {
+        sources: Option<Vec<String>>,
+        symbols: Option<Vec<String>>,
+    },
+    Snapshot {
+        server_ts: u64,
+        packets: Vec<TelemetryPacket>,     // 每个交易对最近一次推送的数据包
+        states: Vec<SymbolState>,          // 每个交易对的当前信号状态
+    },
+    Pong {
+        id: Option<u64>,
+        client_ts: Option<u64>,
+        server_ts: u64,
+    },
+    Error {
+        message: String,
+    },
+}
+
+impl ServerReply {
+    pub fn to_json(&self) -> String {
+        serde_json::to_string(self).unwrap_or_else(|e| {
+            format!(r#"{{"type":"error","message":"failed to encode reply: {}"}}"#, e)
+        })
+    }
+}
+
+/// 单个客户端的订阅过滤器
+#[derive(Debug, Clone, Default)]
+pub struct Subscription {
+    sources: Option<HashSet<String>>,
+    symbols: Option<HashSet<String>>,   // 大写
+}
+
+impl Subscription {
+    pub fn new(sources: Option<Vec<String>>, symbols: Option<Vec<String>>) -> Self {
+        Self {
+            sources: sources.map(|v| v.into_iter().collect()),
+            symbols: symbols.map(|v| v.into_iter().map(|s| s.to_uppercase()).collect()),
+        }
+    }
+
+    pub fn matches(&self, packet: &TelemetryPacket) -> bool {
+        if let Some(symbols) = &self.symbols
+            && !symbols.contains(&packet.symbol)
+        {
+            return false;
+        }
+        if STATUS_SOURCES.contains(&packet.source.as_str()) {
+            return true;
+        }
+        self.sources.as_ref().is_none_or(|s| s.contains(&packet.source))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn packet(symbol: &str, source: &str) -> TelemetryPacket {
+        let mut p = TelemetryPacket::heartbeat(symbol, false, 0);
+        p.source = source.to_string();
+        p
+    }
+
+    #[test]
+    fn default_subscription_matches_everything() {
+        let sub = Subscription::default();
+        for source in ["V", "U", "D", "N", "L", "X"] {
+            assert!(sub.matches(&packet("BTCUSDT", source)), "{}", source);
+        }
+    }
+
+    #[test]
+    fn status_sources_bypass_the_sources_filter() {
+        let sub = Subscription::new(Some(vec!["U".into()]), None);
+        assert!(sub.matches(&packet("BTCUSDT", "U")));
+        assert!(!sub.matches(&packet("BTCUSDT", "D")));
+        assert!(!sub.matches(&packet("BTCUSDT", "V")));
+        for source in STATUS_SOURCES {
+            assert!(sub.matches(&packet("BTCUSDT", source)), "{}", source);
+        }
+
+        // 空列表同样只放行状态包
+        let none = Subscription::new(Some(Vec::new()), None);
+        assert!(!none.matches(&packet("BTCUSDT", "V")));
+        assert!(none.matches(&packet("BTCUSDT", "X")));
+    }
+
+    #[test]
+    fn symbols_filter_applies_to_status_sources_too() {
+        let sub = Subscription::new(Some(vec!["V".into()]), Some(vec!["ethusdt".into()]));
+        assert!(sub.matches(&packet("ETHUSDT", "V")));
+        assert!(sub.matches(&packet("ETHUSDT", "N")));
+        assert!(!sub.matches(&packet("BTCUSDT", "V")));
+        assert!(!sub.matches(&packet("BTCUSDT", "N")));
+        assert!(!sub.matches(&packet("BTCUSDT", "X")));
+    }
+
+    #[test]
+    fn parses_commands_and_legacy_handshake() {
+        assert!(matches!(
+            ClientCommand::parse(r#"{"cmd":"subscribe","sources":["U"]}"#),
+            Ok(ClientCommand::Subscribe { sources: Some(_), symbols: None })
+        ));
+        assert!(matches!(
+            ClientCommand::parse(r#"{"encoding":"msgpack"}"#),
+            Ok(ClientCommand::Encoding { encoding: Encoding::Msgpack })
+        ));
+        assert!(ClientCommand::parse(r#"{"cmd":"unknown"}"#).is_err());
+    }
+}